@@ -2,9 +2,17 @@
 extern crate pokey_logger;
 
 use pokey_logger::existing_log_handler::ExistingLogHandler;
-use pokey_logger::{Logger, LOGGER};
+#[cfg(feature = "config")]
+use pokey_logger::LOGGER;
+use pokey_logger::{flush_guard, install_panic_hook, Logger};
 
 fn main() {
+    // This is important to ensure the log files are fully written before
+    // shutting down, even when returning early.
+    let _guard = flush_guard();
+    // Panics are logged, and the logs flushed, before the program ends.
+    install_panic_hook();
+
     // Load a configuration file if the config feature is enabled.
     #[cfg(feature = "config")]
     match LOGGER.load_config_file("examples/full_usage/config.yml") {
@@ -21,12 +29,6 @@ fn main() {
     // This is an example of creating a separate logger instance that also
     // saves a new log every
     file_renaming();
-
-    // This is important to ensure the log files are fully written before
-    // shutting down.
-    if let Err(e) = LOGGER.flush() {
-        error!("Error flushing logs: {e:?}");
-    }
 }
 
 /// An example of renaming the log file if it already exists before running.
@@ -35,13 +37,14 @@ fn main() {
 fn file_renaming() {
     let logger = Logger::new();
     #[cfg(feature = "env")]
-    logger.load_env_vars();
+    for invalid in logger.load_env_vars() {
+        warn!("{invalid}");
+    }
     match logger.set_log_path("examples/full_usage/logs/rename_log.log") {
         Ok(_) => info!("Log file set"),
         Err(e) => error!("Error setting log file: {e}")
     }
     logger.set_existing_log_handler(ExistingLogHandler::Rename);
     logger.info("This is cool");
-    logger.flush().unwrap();
-    drop(logger);
+    // The logger is flushed when it is dropped
 }
@@ -0,0 +1,752 @@
+//! A builder for [`Logger`] instances that checks all of the settings at once.
+//!
+//! ```
+//! use pokey_logger::{Level, Logger};
+//!
+//! let logger = Logger::builder()
+//!     .level(Level::Info)
+//!     .color(false)
+//!     .build()
+//!     .expect("settings should be valid");
+//! logger.info("Hello, world!");
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::clock::Clock;
+#[cfg(feature = "env")]
+use crate::environment::{self, EnvSettings, InvalidEnvVar};
+#[cfg(feature = "log_files")]
+use crate::existing_log_handler::ExistingLogHandler;
+#[cfg(feature = "log_files")]
+use crate::file_output::FileOutput;
+use crate::flight_recorder::FlightRecorder;
+#[cfg(feature = "log_files")]
+use crate::flush_policy::FlushPolicy;
+use crate::rate_limit::RateLimit;
+use crate::sink::Sink;
+#[cfg(feature = "time")]
+use crate::time::{self, InvalidTimestampFormat, TimestampStyle};
+#[cfg(feature = "log_files")]
+use crate::write_error_policy::WriteErrorPolicy;
+#[cfg(feature = "log_files")]
+use crate::SetLogPathError;
+#[cfg(feature = "config")]
+use crate::{ConfigFile, ConfigFileLoadError};
+use crate::{Level, Logger};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "log_files")]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Collects settings for a [`Logger`] and creates it with [`build`].
+///
+/// None of the settings are checked until [`build`] is called, so the order
+/// of the calls does not matter. Every setting starts at the same default
+/// as [`Logger::new`].
+///
+/// [`build`]: LoggerBuilder::build
+#[derive(Debug)]
+pub struct LoggerBuilder {
+    level: Level,
+    color: bool,
+    #[cfg(feature = "log_files")]
+    log_file_color: bool,
+    #[cfg(feature = "time")]
+    show_time: bool,
+    #[cfg(feature = "time")]
+    timestamp_format: Option<String>,
+    #[cfg(feature = "time")]
+    timestamp_style: TimestampStyle,
+    #[cfg(feature = "time")]
+    timestamp_utc: bool,
+    show_thread: bool,
+    show_process_id: bool,
+    #[cfg(feature = "log_files")]
+    log_path: Option<PathBuf>,
+    #[cfg(feature = "log_files")]
+    existing_log_handler: ExistingLogHandler,
+    #[cfg(feature = "log_files")]
+    log_archive_directory: Option<PathBuf>,
+    #[cfg(feature = "log_files")]
+    flush_policy: FlushPolicy,
+    #[cfg(feature = "log_files")]
+    sync_on_error: bool,
+    #[cfg(feature = "log_files")]
+    atomic_appends: bool,
+    #[cfg(feature = "file_lock")]
+    file_locking: bool,
+    #[cfg(feature = "log_files")]
+    write_error_policy: WriteErrorPolicy,
+    #[cfg(feature = "log_files")]
+    create_log_directories: bool,
+    #[cfg(feature = "log_files")]
+    log_directory_mode: Option<u32>,
+    #[cfg(feature = "log_files")]
+    max_log_bytes: Option<u64>,
+    #[cfg(feature = "log_files")]
+    prune_log_archives: bool,
+    #[cfg(feature = "disk_space")]
+    min_free_space: Option<u64>,
+    #[cfg(feature = "log_files")]
+    log_files: Vec<FileOutput>,
+    flight_recorder: Option<FlightRecorder>,
+    rate_limit: Option<RateLimit>,
+    collapse_duplicates: bool,
+    sinks: Vec<Arc<dyn Sink>>,
+    clock: Option<Arc<dyn Clock>>,
+    /// Problems found while reading settings from the environment, which are
+    /// reported by `build`.
+    #[cfg(feature = "env")]
+    invalid_env_vars: Vec<InvalidEnvVar>,
+    /// A config file named by an environment variable that could not be
+    /// loaded, which is reported by `build`.
+    #[cfg(feature = "config")]
+    config_file_error: Option<ConfigFileLoadError>,
+    /// A log directory mode from a config file that is not an octal
+    /// permission mode, which is reported by `build`.
+    #[cfg(all(feature = "config", feature = "log_files"))]
+    invalid_log_directory_mode: Option<String>
+}
+
+impl LoggerBuilder {
+    /// Create a builder with all of the default settings.
+    pub fn new() -> LoggerBuilder {
+        LoggerBuilder {
+            level: crate::DEFAULT_LEVEL,
+            color: crate::DEFAULT_COLOR,
+            #[cfg(feature = "log_files")]
+            log_file_color: false,
+            #[cfg(feature = "time")]
+            show_time: crate::DEFAULT_SHOW_TIME,
+            #[cfg(feature = "time")]
+            timestamp_format: None,
+            #[cfg(feature = "time")]
+            timestamp_style: TimestampStyle::default(),
+            #[cfg(feature = "time")]
+            timestamp_utc: false,
+            show_thread: false,
+            show_process_id: false,
+            #[cfg(feature = "log_files")]
+            log_path: None,
+            #[cfg(feature = "log_files")]
+            existing_log_handler: crate::DEFAULT_EXISTING_LOG_HANDLER,
+            #[cfg(feature = "log_files")]
+            log_archive_directory: None,
+            #[cfg(feature = "log_files")]
+            flush_policy: FlushPolicy::default(),
+            #[cfg(feature = "log_files")]
+            sync_on_error: false,
+            #[cfg(feature = "log_files")]
+            atomic_appends: false,
+            #[cfg(feature = "file_lock")]
+            file_locking: false,
+            #[cfg(feature = "log_files")]
+            write_error_policy: WriteErrorPolicy::default(),
+            #[cfg(feature = "log_files")]
+            create_log_directories: false,
+            #[cfg(feature = "log_files")]
+            log_directory_mode: None,
+            #[cfg(feature = "log_files")]
+            max_log_bytes: None,
+            #[cfg(feature = "log_files")]
+            prune_log_archives: false,
+            #[cfg(feature = "disk_space")]
+            min_free_space: None,
+            #[cfg(feature = "log_files")]
+            log_files: Vec::new(),
+            flight_recorder: None,
+            rate_limit: None,
+            collapse_duplicates: false,
+            sinks: Vec::new(),
+            clock: None,
+            #[cfg(feature = "env")]
+            invalid_env_vars: Vec::new(),
+            #[cfg(feature = "config")]
+            config_file_error: None,
+            #[cfg(all(feature = "config", feature = "log_files"))]
+            invalid_log_directory_mode: None
+        }
+    }
+
+    /// Create a builder from the `PL_` environment variables. See
+    /// [`environment::configure`] for the variables.
+    ///
+    /// Invalid variables are not reported until [`build`](Self::build).
+    #[cfg(feature = "env")]
+    pub fn from_env() -> LoggerBuilder {
+        LoggerBuilder::new().env(environment::DEFAULT_PREFIX)
+    }
+
+    /// Create a builder from environment variables that use the given prefix
+    /// instead of `PL_`.
+    #[cfg(feature = "env")]
+    pub fn from_env_with_prefix(prefix: &str) -> LoggerBuilder {
+        LoggerBuilder::new().env(prefix)
+    }
+
+    /// Create a builder with all of the settings from a config file.
+    #[cfg(feature = "config")]
+    pub fn from_config(config: &ConfigFile) -> LoggerBuilder {
+        LoggerBuilder::new().config(config)
+    }
+
+    /// Apply the environment variables with the given prefix on top of the
+    /// current settings. Variables that are not set are left alone.
+    #[cfg(feature = "env")]
+    pub fn env(mut self, prefix: &str) -> LoggerBuilder {
+        let (settings, errors) = EnvSettings::read(prefix);
+        self.invalid_env_vars.extend(errors);
+
+        #[cfg(feature = "config")]
+        if let Some(ref path) = settings.config {
+            match ConfigFile::load(path) {
+                Ok(config) => self = self.config(&config),
+                Err(e) => self.config_file_error = Some(e)
+            }
+        }
+
+        if let Some(color) = settings.color {
+            self.color = color;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(color) = settings.file_color {
+            self.log_file_color = color;
+        }
+        #[cfg(feature = "time")]
+        if let Some(show_time) = settings.show_time {
+            self.show_time = show_time;
+        }
+        #[cfg(feature = "time")]
+        if let Some(format) = settings.timestamp_format {
+            self.timestamp_format = format;
+        }
+        #[cfg(feature = "time")]
+        if let Some(style) = settings.timestamp_style {
+            self.timestamp_style = style;
+        }
+        #[cfg(feature = "time")]
+        if let Some(utc) = settings.timestamp_utc {
+            self.timestamp_utc = utc;
+        }
+        if let Some(show_thread) = settings.show_thread {
+            self.show_thread = show_thread;
+        }
+        if let Some(show_process_id) = settings.show_process_id {
+            self.show_process_id = show_process_id;
+        }
+        if let Some(level) = settings.level {
+            self.level = level;
+        }
+        if let Some(limit) = settings.rate_limit {
+            self.rate_limit = limit;
+        }
+        if let Some(collapse) = settings.collapse_duplicates {
+            self.collapse_duplicates = collapse;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(handler) = settings.existing_log_handler {
+            self.existing_log_handler = handler;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(directory) = settings.log_archive_directory {
+            self.log_archive_directory = directory.map(PathBuf::from);
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(policy) = settings.flush_policy {
+            self.flush_policy = policy;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(sync) = settings.sync_on_error {
+            self.sync_on_error = sync;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(policy) = settings.write_error_policy {
+            self.write_error_policy = policy;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(create) = settings.create_log_directories {
+            self.create_log_directories = create;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(mode) = settings.log_directory_mode {
+            self.log_directory_mode = mode;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(max_bytes) = settings.max_log_bytes {
+            self.max_log_bytes = max_bytes;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(prune) = settings.prune_log_archives {
+            self.prune_log_archives = prune;
+        }
+        #[cfg(feature = "disk_space")]
+        if let Some(min_bytes) = settings.min_free_space {
+            self.min_free_space = min_bytes;
+        }
+        #[cfg(feature = "log_files")]
+        if let Some(path) = settings.file {
+            self.log_path = Some(PathBuf::from(path));
+        }
+
+        self
+    }
+
+    /// Replace all of the settings that a config file has with the values
+    /// from the config file. Like [`Logger::load_config_file`], settings
+    /// that are missing from the file are set to their defaults.
+    #[cfg(feature = "config")]
+    pub fn config(mut self, config: &ConfigFile) -> LoggerBuilder {
+        self.level = config.level;
+        self.color = config.color;
+        self.show_thread = config.show_thread;
+        self.show_process_id = config.show_process_id;
+        self.flight_recorder = config.flight_recorder.clone();
+        self.rate_limit = config.rate_limit;
+        self.collapse_duplicates = config.collapse_duplicates;
+        #[cfg(feature = "time")]
+        {
+            self.show_time = config.time_stamp;
+            self.timestamp_format = config.timestamp_format.clone();
+            self.timestamp_style = config.timestamp_style;
+            self.timestamp_utc = config.timestamp_utc;
+        }
+        #[cfg(feature = "log_files")]
+        {
+            self.log_file_color = config.file_color;
+            self.existing_log_handler = config.existing_log_handler;
+            self.log_archive_directory = config.log_archive_directory.as_ref().map(PathBuf::from);
+            self.flush_policy = config.flush_policy;
+            self.sync_on_error = config.sync_on_error;
+            self.write_error_policy = config.write_error_policy;
+            self.create_log_directories = config.create_log_directories;
+            match config.log_directory_mode.as_deref() {
+                Some(mode) => match crate::existing_log_handler::parse_directory_mode(mode) {
+                    Ok(mode) => self.log_directory_mode = Some(mode),
+                    Err(e) => self.invalid_log_directory_mode = Some(e)
+                },
+                None => self.log_directory_mode = None
+            }
+            self.log_path = config.log_file_path.as_ref().map(PathBuf::from);
+            self.max_log_bytes = config.max_log_bytes;
+            self.prune_log_archives = config.prune_log_archives;
+            self.log_files = config.log_files.clone();
+        }
+        #[cfg(feature = "disk_space")]
+        {
+            self.min_free_space = config.min_free_space;
+        }
+
+        self
+    }
+
+    /// Set the minimum level to log. See [`Logger::set_level`].
+    pub fn level(mut self, level: Level) -> LoggerBuilder {
+        self.level = level;
+        self
+    }
+
+    /// Set whether or not to use colors in the terminal. See
+    /// [`Logger::set_color`].
+    pub fn color(mut self, color: bool) -> LoggerBuilder {
+        self.color = color;
+        self
+    }
+
+    /// Set whether or not to use colors in the log file. See
+    /// [`Logger::set_log_file_color`].
+    #[cfg(feature = "log_files")]
+    pub fn log_file_color(mut self, color: bool) -> LoggerBuilder {
+        self.log_file_color = color;
+        self
+    }
+
+    /// Set whether or not to show the timestamp. See
+    /// [`Logger::set_should_show_time`].
+    #[cfg(feature = "time")]
+    pub fn show_time(mut self, show_time: bool) -> LoggerBuilder {
+        self.show_time = show_time;
+        self
+    }
+
+    /// Set the format of the timestamp. The format is checked by `build`.
+    /// See [`Logger::set_timestamp_format`].
+    #[cfg(feature = "time")]
+    pub fn timestamp_format<S: Into<String>>(mut self, format: S) -> LoggerBuilder {
+        self.timestamp_format = Some(format.into());
+        self
+    }
+
+    /// Set how the timestamp is written. See
+    /// [`Logger::set_timestamp_style`].
+    #[cfg(feature = "time")]
+    pub fn timestamp_style(mut self, style: TimestampStyle) -> LoggerBuilder {
+        self.timestamp_style = style;
+        self
+    }
+
+    /// Set whether or not timestamps are in UTC. See
+    /// [`Logger::set_timestamp_utc`].
+    #[cfg(feature = "time")]
+    pub fn timestamp_utc(mut self, utc: bool) -> LoggerBuilder {
+        self.timestamp_utc = utc;
+        self
+    }
+
+    /// Set whether or not to show the thread on messages. See
+    /// [`Logger::set_should_show_thread`].
+    pub fn show_thread(mut self, show_thread: bool) -> LoggerBuilder {
+        self.show_thread = show_thread;
+        self
+    }
+
+    /// Set whether or not to show the process ID on messages. See
+    /// [`Logger::set_should_show_process_id`].
+    pub fn show_process_id(mut self, show_process_id: bool) -> LoggerBuilder {
+        self.show_process_id = show_process_id;
+        self
+    }
+
+    /// Set the file to log to. The path is checked, and the file created, by
+    /// `build`. See [`Logger::set_log_path`].
+    #[cfg(feature = "log_files")]
+    pub fn log_path<P: Into<PathBuf>>(mut self, path: P) -> LoggerBuilder {
+        self.log_path = Some(path.into());
+        self
+    }
+
+    /// Set how an existing log file is handled. See
+    /// [`Logger::set_existing_log_handler`].
+    #[cfg(feature = "log_files")]
+    pub fn existing_log_handler(mut self, handler: ExistingLogHandler) -> LoggerBuilder {
+        self.existing_log_handler = handler;
+        self
+    }
+
+    /// Set the directory that existing log files are moved to when renamed.
+    /// See [`Logger::set_log_archive_directory`].
+    #[cfg(feature = "log_files")]
+    pub fn log_archive_directory<P: Into<PathBuf>>(mut self, directory: P) -> LoggerBuilder {
+        self.log_archive_directory = Some(directory.into());
+        self
+    }
+
+    /// Set when messages written to the log file are flushed. See
+    /// [`Logger::set_flush_policy`].
+    #[cfg(feature = "log_files")]
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> LoggerBuilder {
+        self.flush_policy = policy;
+        self
+    }
+
+    /// Set whether or not error messages are synced to the disk. See
+    /// [`Logger::set_sync_on_error`].
+    #[cfg(feature = "log_files")]
+    pub fn sync_on_error(mut self, sync: bool) -> LoggerBuilder {
+        self.sync_on_error = sync;
+        self
+    }
+
+    /// Set whether or not each message is written to the log file with a
+    /// single write. See [`Logger::set_atomic_appends`].
+    #[cfg(feature = "log_files")]
+    pub fn atomic_appends(mut self, atomic: bool) -> LoggerBuilder {
+        self.atomic_appends = atomic;
+        self
+    }
+
+    /// Set whether or not the log file is locked while writing to it. See
+    /// [`Logger::set_file_locking`].
+    #[cfg(feature = "file_lock")]
+    pub fn file_locking(mut self, locking: bool) -> LoggerBuilder {
+        self.file_locking = locking;
+        self
+    }
+
+    /// Set what happens when writing to the log file fails. See
+    /// [`Logger::set_write_error_policy`].
+    #[cfg(feature = "log_files")]
+    pub fn write_error_policy(mut self, policy: WriteErrorPolicy) -> LoggerBuilder {
+        self.write_error_policy = policy;
+        self
+    }
+
+    /// Set whether or not the missing directories of the log file are
+    /// created. See [`Logger::set_create_log_directories`].
+    #[cfg(feature = "log_files")]
+    pub fn create_log_directories(mut self, create: bool) -> LoggerBuilder {
+        self.create_log_directories = create;
+        self
+    }
+
+    /// Set the Unix permission mode of created log directories. See
+    /// [`Logger::set_log_directory_mode`].
+    #[cfg(feature = "log_files")]
+    pub fn log_directory_mode(mut self, mode: u32) -> LoggerBuilder {
+        self.log_directory_mode = Some(mode);
+        self
+    }
+
+    /// Set the most bytes that the log file and its rotated files may take
+    /// up together. See [`Logger::set_max_log_bytes`].
+    #[cfg(feature = "log_files")]
+    pub fn max_log_bytes(mut self, max_bytes: u64) -> LoggerBuilder {
+        self.max_log_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set whether or not the oldest renamed logs are deleted to stay under
+    /// the size limit. See [`Logger::set_prune_log_archives`].
+    #[cfg(feature = "log_files")]
+    pub fn prune_log_archives(mut self, prune: bool) -> LoggerBuilder {
+        self.prune_log_archives = prune;
+        self
+    }
+
+    /// Set the least free space that the disk of the log file must have.
+    /// See [`Logger::set_min_free_space`].
+    #[cfg(feature = "disk_space")]
+    pub fn min_free_space(mut self, min_bytes: u64) -> LoggerBuilder {
+        self.min_free_space = Some(min_bytes);
+        self
+    }
+
+    /// Add an extra log file. The file is opened by `build`. See
+    /// [`Logger::add_log_file`].
+    #[cfg(feature = "log_files")]
+    pub fn log_file(mut self, output: FileOutput) -> LoggerBuilder {
+        self.log_files.push(output);
+        self
+    }
+
+    /// Keep the last messages in memory to be dumped when something goes
+    /// wrong. See [`Logger::set_flight_recorder`].
+    pub fn flight_recorder(mut self, recorder: FlightRecorder) -> LoggerBuilder {
+        self.flight_recorder = Some(recorder);
+        self
+    }
+
+    /// Set the most messages logged from each call site in an interval. See
+    /// [`Logger::set_rate_limit`].
+    pub fn rate_limit(mut self, limit: RateLimit) -> LoggerBuilder {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Set whether or not repeats of the last message are counted instead
+    /// of logged. See [`Logger::set_collapse_duplicates`].
+    pub fn collapse_duplicates(mut self, collapse: bool) -> LoggerBuilder {
+        self.collapse_duplicates = collapse;
+        self
+    }
+
+    /// Add an extra output for messages. See [`Logger::add_sink`].
+    pub fn sink<S: Sink + 'static>(mut self, sink: S) -> LoggerBuilder {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Set where the logger gets the current time from. See
+    /// [`Logger::set_clock`].
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> LoggerBuilder {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Check all of the settings and create the logger.
+    ///
+    /// Every setting, including whether the log files could be opened, is
+    /// checked before anything is created on the disk, and the first problem
+    /// found is returned. Creating the log files and their directories can
+    /// still fail after that, such as without permission to write to them,
+    /// and anything created before the failure is left in place.
+    pub fn build(self) -> Result<Logger, BuildError> {
+        #[cfg(feature = "config")]
+        if let Some(e) = self.config_file_error {
+            return Err(BuildError::ConfigFile(e));
+        }
+        #[cfg(feature = "env")]
+        if !self.invalid_env_vars.is_empty() {
+            return Err(BuildError::InvalidEnvVars(self.invalid_env_vars));
+        }
+        #[cfg(feature = "time")]
+        if let Some(ref format) = self.timestamp_format {
+            time::validate_format(format)?;
+        }
+        #[cfg(all(feature = "config", feature = "log_files"))]
+        if let Some(mode) = self.invalid_log_directory_mode {
+            return Err(BuildError::InvalidLogDirectoryMode(mode));
+        }
+        #[cfg(feature = "log_files")]
+        {
+            if let Some(ref path) = self.log_path {
+                check_log_path(path, self.existing_log_handler, self.create_log_directories)?;
+            }
+            for output in self.log_files.iter() {
+                check_log_path(
+                    &output.path,
+                    output.existing_log_handler,
+                    self.create_log_directories
+                )?;
+            }
+        }
+
+        let logger = Logger::new();
+        logger.set_level(self.level);
+        logger.set_color(self.color);
+        logger.set_should_show_thread(self.show_thread);
+        logger.set_should_show_process_id(self.show_process_id);
+        logger.set_flight_recorder(self.flight_recorder);
+        logger.set_rate_limit(self.rate_limit);
+        logger.set_collapse_duplicates(self.collapse_duplicates);
+        *crate::lock(&logger.sinks) = self.sinks;
+        if let Some(clock) = self.clock {
+            logger.set_clock_arc(clock);
+        }
+        #[cfg(feature = "time")]
+        {
+            logger.set_should_show_time(self.show_time);
+            logger.set_timestamp_style(self.timestamp_style);
+            logger.set_timestamp_utc(self.timestamp_utc);
+            logger.set_timestamp_format(self.timestamp_format)?;
+        }
+        #[cfg(feature = "log_files")]
+        {
+            logger.set_log_file_color(self.log_file_color);
+            logger.set_existing_log_handler(self.existing_log_handler);
+            logger.set_log_archive_directory(self.log_archive_directory);
+            logger.set_flush_policy(self.flush_policy);
+            logger.set_sync_on_error(self.sync_on_error);
+            logger.set_atomic_appends(self.atomic_appends);
+            #[cfg(feature = "file_lock")]
+            logger.set_file_locking(self.file_locking);
+            logger.set_write_error_policy(self.write_error_policy);
+            logger.set_create_log_directories(self.create_log_directories);
+            logger.set_log_directory_mode(self.log_directory_mode);
+            logger.set_max_log_bytes(self.max_log_bytes);
+            logger.set_prune_log_archives(self.prune_log_archives);
+            #[cfg(feature = "disk_space")]
+            logger.set_min_free_space(self.min_free_space);
+            if let Some(ref path) = self.log_path {
+                logger.set_log_path_path(path)?;
+            }
+            for output in self.log_files {
+                logger.add_log_file(output)?;
+            }
+        }
+
+        Ok(logger)
+    }
+}
+
+/// Check that a log file could be opened at the path, without creating
+/// anything.
+#[cfg(feature = "log_files")]
+fn check_log_path(
+    path: &Path,
+    handler: ExistingLogHandler,
+    create_directories: bool
+) -> Result<(), SetLogPathError> {
+    if path.exists() {
+        if !path.is_file() {
+            return Err(SetLogPathError::PathIsNotAFile);
+        }
+        if handler == ExistingLogHandler::FailIfExists {
+            return Err(SetLogPathError::AlreadyExists);
+        }
+    } else if !create_directories {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new(".")
+        };
+        if !parent.is_dir() {
+            return Err(SetLogPathError::CouldNotCreateLogFile(
+                std::io::ErrorKind::NotFound.into()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl Default for LoggerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An invalid setting found by [`LoggerBuilder::build`].
+#[derive(Debug)]
+pub enum BuildError {
+    /// The timestamp format is not a valid chrono format.
+    #[cfg(feature = "time")]
+    InvalidTimestampFormat(InvalidTimestampFormat),
+    /// The log file could not be used.
+    #[cfg(feature = "log_files")]
+    InvalidLogPath(SetLogPathError),
+    /// Some environment variables had invalid values.
+    #[cfg(feature = "env")]
+    InvalidEnvVars(Vec<InvalidEnvVar>),
+    /// A config file named by an environment variable could not be loaded.
+    #[cfg(feature = "config")]
+    ConfigFile(ConfigFileLoadError),
+    /// The log directory mode from a config file is not an octal permission
+    /// mode.
+    #[cfg(all(feature = "config", feature = "log_files"))]
+    InvalidLogDirectoryMode(String)
+}
+
+impl Display for BuildError {
+    // Without any features there are no variants to write with `f`.
+    #[allow(unused_variables)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            #[cfg(feature = "time")]
+            BuildError::InvalidTimestampFormat(ref e) => write!(f, "{e}"),
+            #[cfg(feature = "log_files")]
+            BuildError::InvalidLogPath(ref e) => write!(f, "{e}"),
+            #[cfg(feature = "env")]
+            BuildError::InvalidEnvVars(ref errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+            #[cfg(feature = "config")]
+            BuildError::ConfigFile(ref e) => write!(f, "{e}"),
+            #[cfg(all(feature = "config", feature = "log_files"))]
+            BuildError::InvalidLogDirectoryMode(ref e) => write!(f, "{e}")
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            #[cfg(feature = "time")]
+            BuildError::InvalidTimestampFormat(ref e) => Some(e),
+            #[cfg(feature = "log_files")]
+            BuildError::InvalidLogPath(ref e) => Some(e),
+            #[cfg(feature = "env")]
+            BuildError::InvalidEnvVars(_) => None,
+            #[cfg(feature = "config")]
+            BuildError::ConfigFile(ref e) => Some(e),
+            #[cfg(all(feature = "config", feature = "log_files"))]
+            BuildError::InvalidLogDirectoryMode(_) => None
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<InvalidTimestampFormat> for BuildError {
+    fn from(error: InvalidTimestampFormat) -> Self {
+        BuildError::InvalidTimestampFormat(error)
+    }
+}
+
+#[cfg(feature = "log_files")]
+impl From<SetLogPathError> for BuildError {
+    fn from(error: SetLogPathError) -> Self {
+        BuildError::InvalidLogPath(error)
+    }
+}
@@ -0,0 +1,274 @@
+use super::*;
+
+#[test]
+fn test_defaults_match_new_logger() {
+    let built = LoggerBuilder::new().build().unwrap();
+    let new = Logger::new();
+
+    assert_eq!(built.get_level(), new.get_level());
+    assert_eq!(built.get_color(), new.get_color());
+    #[cfg(feature = "time")]
+    assert_eq!(built.should_show_time(), new.should_show_time());
+    #[cfg(feature = "log_files")]
+    {
+        assert_eq!(built.get_log_path(), None);
+        assert_eq!(built.get_log_file_color(), new.get_log_file_color());
+        assert_eq!(
+            built.get_existing_log_handler(),
+            new.get_existing_log_handler()
+        );
+        assert_eq!(built.get_flush_policy(), new.get_flush_policy());
+        assert_eq!(built.get_write_error_policy(), new.get_write_error_policy());
+        assert_eq!(built.get_max_log_bytes(), new.get_max_log_bytes());
+    }
+    assert_eq!(built.should_show_thread(), new.should_show_thread());
+    assert_eq!(built.get_rate_limit(), new.get_rate_limit());
+}
+
+#[cfg(all(feature = "log_files", unix))]
+#[test]
+fn test_log_path_that_is_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join(std::ffi::OsStr::from_bytes(b"server-\xff.log"));
+
+    let logger = Logger::builder().log_path(&path).build().unwrap();
+
+    assert_eq!(logger.get_log_path(), Some(path.clone()));
+    assert!(path.exists());
+}
+
+#[test]
+fn test_settings_are_applied() {
+    let logger = Logger::builder()
+        .level(Level::Warn)
+        .color(false)
+        .build()
+        .unwrap();
+
+    assert_eq!(logger.get_level(), Level::Warn);
+    assert!(!logger.get_color());
+}
+
+#[test]
+fn test_sinks_are_added() {
+    let capture = crate::capture::Capture::new();
+    let logger = Logger::builder().sink(capture.clone()).build().unwrap();
+
+    logger.info("to the sink");
+
+    assert!(capture.contains(Level::Info, "to the sink"));
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_invalid_timestamp_format_fails_build() {
+    let result = Logger::builder().timestamp_format("%Q").build();
+
+    match result {
+        Err(BuildError::InvalidTimestampFormat(e)) => assert_eq!(e.0, "%Q"),
+        other => panic!("expected an invalid timestamp format, got {:?}", other)
+    }
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_log_file_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("builder.log");
+
+    let logger = Logger::builder()
+        .log_path(&path)
+        .log_file_color(true)
+        .existing_log_handler(ExistingLogHandler::Append)
+        .build()
+        .unwrap();
+
+    assert_eq!(logger.get_log_path(), Some(path.clone()));
+    assert!(logger.get_log_file_color());
+    assert_eq!(
+        logger.get_existing_log_handler(),
+        ExistingLogHandler::Append
+    );
+    assert!(path.exists());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_invalid_log_path_fails_build() {
+    let result = Logger::builder()
+        .log_path("this/path/is/not/real/file.log")
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(BuildError::InvalidLogPath(
+            SetLogPathError::CouldNotCreateLogFile(_)
+        ))
+    ));
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_create_log_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs/server.log");
+
+    let logger = Logger::builder()
+        .create_log_directories(true)
+        .log_directory_mode(0o750)
+        .log_path(&path)
+        .build()
+        .unwrap();
+
+    assert!(logger.get_create_log_directories());
+    assert_eq!(logger.get_log_directory_mode(), Some(0o750));
+    assert!(path.exists());
+}
+
+#[test]
+fn test_rate_limit_and_duplicates() {
+    let limit = crate::rate_limit::RateLimit::new(5, std::time::Duration::from_secs(1));
+
+    let logger = Logger::builder()
+        .rate_limit(limit)
+        .collapse_duplicates(true)
+        .build()
+        .unwrap();
+
+    assert_eq!(logger.get_rate_limit(), Some(limit));
+    assert!(logger.get_collapse_duplicates());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_disk_limits() {
+    let builder = Logger::builder()
+        .max_log_bytes(1_048_576)
+        .prune_log_archives(true);
+    #[cfg(feature = "disk_space")]
+    let builder = builder.min_free_space(4096);
+
+    let logger = builder.build().unwrap();
+
+    assert_eq!(logger.get_max_log_bytes(), Some(1_048_576));
+    assert!(logger.get_prune_log_archives());
+    #[cfg(feature = "disk_space")]
+    assert_eq!(logger.get_min_free_space(), Some(4096));
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_log_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    let output = crate::file_output::FileOutput::new(&errors).min_level(Level::Error);
+
+    let logger = Logger::builder().log_file(output.clone()).build().unwrap();
+
+    assert_eq!(logger.get_log_files(), [output]);
+    assert!(errors.exists());
+    assert!(matches!(
+        Logger::builder()
+            .log_file(crate::file_output::FileOutput::new(dir.path()))
+            .build(),
+        Err(BuildError::InvalidLogPath(SetLogPathError::PathIsNotAFile))
+    ));
+}
+
+#[cfg(feature = "env")]
+#[test]
+fn test_from_env_with_prefix() {
+    std::env::set_var("PLT_BUILDER_LEVEL", "error");
+    std::env::set_var("PLT_BUILDER_COLOR", "no");
+
+    let logger = LoggerBuilder::from_env_with_prefix("PLT_BUILDER_")
+        .build()
+        .unwrap();
+
+    assert_eq!(logger.get_level(), Level::Error);
+    assert!(!logger.get_color());
+}
+
+#[cfg(feature = "env")]
+#[test]
+fn test_builder_settings_override_env() {
+    std::env::set_var("PLT_BUILDER_OVERRIDE_LEVEL", "error");
+
+    let logger = LoggerBuilder::from_env_with_prefix("PLT_BUILDER_OVERRIDE_")
+        .level(Level::Info)
+        .build()
+        .unwrap();
+
+    assert_eq!(logger.get_level(), Level::Info);
+}
+
+#[cfg(feature = "env")]
+#[test]
+fn test_invalid_env_vars_fail_build() {
+    std::env::set_var("PLT_BUILDER_INVALID_LEVEL", "loud");
+
+    match LoggerBuilder::from_env_with_prefix("PLT_BUILDER_INVALID_").build() {
+        Err(BuildError::InvalidEnvVars(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].name, "PLT_BUILDER_INVALID_LEVEL");
+        }
+        other => panic!("expected invalid env vars, got {:?}", other)
+    }
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_from_config() {
+    let config: ConfigFile = serde_yaml::from_str("level: Warn\ncolor: false\n").unwrap();
+
+    let logger = LoggerBuilder::from_config(&config).build().unwrap();
+
+    assert_eq!(logger.get_level(), Level::Warn);
+    assert!(!logger.get_color());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_invalid_log_file_creates_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+
+    let result = Logger::builder()
+        .log_path(&path)
+        .log_file(crate::file_output::FileOutput::new(dir.path()))
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(BuildError::InvalidLogPath(SetLogPathError::PathIsNotAFile))
+    ));
+    assert!(!path.exists());
+}
+
+#[cfg(all(feature = "config", feature = "log_files"))]
+#[test]
+fn test_invalid_log_directory_mode_from_config() {
+    let config: ConfigFile = serde_yaml::from_str("log_directory_mode: \"999\"\n").unwrap();
+
+    let result = LoggerBuilder::from_config(&config).build();
+
+    assert!(matches!(
+        result,
+        Err(BuildError::InvalidLogDirectoryMode(_))
+    ));
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_build_error_source() {
+    let error = Logger::builder()
+        .timestamp_format("%Q")
+        .build()
+        .unwrap_err();
+
+    assert!(error.source().is_some());
+}
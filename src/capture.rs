@@ -0,0 +1,81 @@
+//! An in-memory sink for checking what has been logged, mainly for tests.
+//!
+//! ```
+//! use pokey_logger::{assert_logged, assert_not_logged, lwarn, Level, Logger};
+//!
+//! let logger = Logger::new();
+//! let capture = logger.capture();
+//!
+//! lwarn!(logger, "disk almost full: {}%", 95);
+//!
+//! assert_logged!(capture, Level::Warn, "disk almost full");
+//! assert_not_logged!(capture, Level::Error, "disk");
+//! ```
+//!
+//! Each capture only sees the logger it was made from, so tests can each use
+//! their own logger and run in parallel without touching the global logger.
+
+#[cfg(test)]
+mod tests;
+
+use crate::sink::{Record, Sink};
+use crate::Level;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A sink that keeps every record it is given in memory.
+///
+/// Clones share the same records, so one clone can be added to a logger with
+/// [`Logger::add_sink`](crate::Logger::add_sink) while another is used to
+/// check the records. [`Logger::capture`](crate::Logger::capture) does both
+/// of these.
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    records: Arc<Mutex<Vec<Record>>>
+}
+
+impl Capture {
+    /// Create a new capture with no records.
+    pub fn new() -> Capture {
+        Capture::default()
+    }
+
+    /// A copy of all the records captured so far, oldest first.
+    pub fn records(&self) -> Vec<Record> {
+        self.lock().clone()
+    }
+
+    /// Whether or not a record was captured at exactly the given level, with
+    /// a message that contains the given text.
+    pub fn contains(&self, level: Level, text: &str) -> bool {
+        self.lock()
+            .iter()
+            .any(|record| record.level == level && record.message.contains(text))
+    }
+
+    /// The number of records captured so far.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether or not nothing has been captured.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all the records captured so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Lock the records, even if a thread panicked while holding them, so
+    /// that a failed test does not break the checks of other threads.
+    fn lock(&self) -> MutexGuard<'_, Vec<Record>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Sink for Capture {
+    fn log(&self, record: &Record) {
+        self.lock().push(record.clone());
+    }
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::Logger;
+
+#[test]
+fn test_capture_records_logged_messages() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+
+    logger.info("first");
+    logger.warn("second");
+
+    let records = capture.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].level, Level::Info);
+    assert_eq!(records[0].message, "first");
+    assert_eq!(records[1].level, Level::Warn);
+    assert_eq!(records[1].message, "second");
+}
+
+#[test]
+fn test_capture_only_records_messages_that_pass_the_level() {
+    let logger = Logger::new();
+    logger.set_level(Level::Warn);
+    let capture = logger.capture();
+
+    logger.info("filtered out");
+    logger.error("kept");
+
+    assert_eq!(capture.len(), 1);
+    assert!(capture.contains(Level::Error, "kept"));
+    assert!(!capture.contains(Level::Info, "filtered out"));
+}
+
+#[test]
+fn test_contains_matches_level_and_text() {
+    let capture = Capture::new();
+    capture.log(&Record::new(
+        Level::Warn,
+        "disk almost full",
+        std::time::SystemTime::now()
+    ));
+
+    assert!(capture.contains(Level::Warn, "disk almost full"));
+    assert!(capture.contains(Level::Warn, "almost"));
+    assert!(!capture.contains(Level::Error, "disk almost full"));
+    assert!(!capture.contains(Level::Warn, "disk is full"));
+}
+
+#[test]
+fn test_clear() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+    logger.info("something");
+    assert!(!capture.is_empty());
+
+    capture.clear();
+    assert!(capture.is_empty());
+}
+
+#[test]
+fn test_clear_sinks_stops_capturing() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+
+    logger.clear_sinks();
+    logger.info("not captured");
+
+    assert!(capture.is_empty());
+}
+
+#[test]
+fn test_capture_still_works_after_a_panic_while_locked() {
+    let capture = Capture::new();
+    let poisoner = capture.clone();
+    let _ = std::thread::spawn(move || {
+        let _records = poisoner.records.lock().unwrap();
+        panic!("poison the records");
+    })
+    .join();
+
+    capture.log(&Record::new(
+        Level::Info,
+        "after",
+        std::time::SystemTime::now()
+    ));
+
+    assert!(capture.contains(Level::Info, "after"));
+}
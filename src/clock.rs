@@ -0,0 +1,77 @@
+//! Where the logger gets the current time from.
+//!
+//! By default a [`Logger`](crate::Logger) uses the [`SystemClock`]. Tests
+//! can give it a [`ManualClock`] instead with
+//! [`set_clock`](crate::Logger::set_clock), so that timestamps and renamed
+//! log files are predictable.
+//!
+//! ```
+//! use pokey_logger::clock::ManualClock;
+//! use pokey_logger::Logger;
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_000_000));
+//! let logger = Logger::new();
+//! logger.set_clock(clock.clone());
+//!
+//! logger.info("logged at the same second every time");
+//! clock.advance(Duration::from_secs(5));
+//! logger.info("logged 5 seconds later");
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The real time, from [`SystemTime::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only changes when it is told to.
+///
+/// Clones share the same time, so one clone can be given to a logger while
+/// another is used to move the time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    time: Arc<Mutex<SystemTime>>
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the given time.
+    pub fn new(time: SystemTime) -> ManualClock {
+        ManualClock {
+            time: Arc::new(Mutex::new(time))
+        }
+    }
+
+    /// Change the time of the clock.
+    pub fn set(&self, time: SystemTime) {
+        *crate::lock(&self.time) = time;
+    }
+
+    /// Move the time of the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *crate::lock(&self.time) += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *crate::lock(&self.time)
+    }
+}
@@ -0,0 +1,26 @@
+use super::*;
+use std::time::UNIX_EPOCH;
+
+#[test]
+fn test_manual_clock_only_moves_when_told() {
+    let start = UNIX_EPOCH + Duration::from_secs(1_000);
+    let clock = ManualClock::new(start);
+    assert_eq!(clock.now(), start);
+    assert_eq!(clock.now(), start);
+
+    clock.advance(Duration::from_millis(1_500));
+    assert_eq!(clock.now(), start + Duration::from_millis(1_500));
+
+    clock.set(UNIX_EPOCH);
+    assert_eq!(clock.now(), UNIX_EPOCH);
+}
+
+#[test]
+fn test_manual_clock_clones_share_time() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let other = clock.clone();
+
+    clock.advance(Duration::from_secs(1));
+
+    assert_eq!(other.now(), UNIX_EPOCH + Duration::from_secs(1));
+}
@@ -1,10 +1,27 @@
 //! This module contains everything to do with ConfigFile parsing and loading.
 
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "log_files")]
+use crate::existing_log_handler;
+#[cfg(feature = "log_files")]
+use crate::file_output::FileOutput;
+use crate::flight_recorder::FlightRecorder;
+#[cfg(feature = "log_files")]
+use crate::flush_policy::FlushPolicy;
+use crate::rate_limit::RateLimit;
+#[cfg(feature = "log_files")]
+use crate::write_error_policy::WriteErrorPolicy;
 #[cfg(feature = "log_files")]
-use crate::ExistingLogHandler;
+use crate::{ExistingLogHandler, SetLogPathError};
 
+#[cfg(feature = "time")]
+use crate::time::{self, InvalidTimestampFormat, TimestampStyle};
 use crate::Level;
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::io;
 
@@ -33,6 +50,12 @@ pub struct ConfigFile {
     /// Whether or not to include timestamps in the terminal output.
     #[serde(default = "default_true")]
     pub time_stamp: bool,
+    /// Whether or not to include the name or ID of the thread in messages.
+    #[serde(default = "default_false")]
+    pub show_thread: bool,
+    /// Whether or not to include the process ID in messages.
+    #[serde(default = "default_false")]
+    pub show_process_id: bool,
     /// Whether or not to include colors in the file output.
     #[serde(default = "default_false")]
     pub file_color: bool,
@@ -43,9 +66,72 @@ pub struct ConfigFile {
     #[cfg(feature = "log_files")]
     #[serde(default = "ExistingLogHandler::default")]
     pub existing_log_handler: ExistingLogHandler,
+    /// The directory that existing log files are moved to when they are
+    /// renamed. If none, they stay next to the log file.
+    #[cfg(feature = "log_files")]
+    pub log_archive_directory: Option<String>,
+    /// When messages written to the log file are flushed, such as
+    /// `"every_message"` or `"level:warn"`. See [`FlushPolicy`].
+    #[cfg(feature = "log_files")]
+    #[serde(default = "FlushPolicy::default")]
+    pub flush_policy: FlushPolicy,
+    /// Whether or not error messages are synced to the disk.
+    #[cfg(feature = "log_files")]
+    #[serde(default = "default_false")]
+    pub sync_on_error: bool,
+    /// What to do when writing to the log file fails, such as `"stderr"` or
+    /// `"retry:3:100ms"`. See [`WriteErrorPolicy`].
+    #[cfg(feature = "log_files")]
+    #[serde(default = "WriteErrorPolicy::default")]
+    pub write_error_policy: WriteErrorPolicy,
+    /// Whether or not to create the missing directories of the log file.
+    #[cfg(feature = "log_files")]
+    #[serde(default = "default_false")]
+    pub create_log_directories: bool,
+    /// The Unix permission mode of created log directories in octal, such as
+    /// `"750"`. If none, the usual permissions are used.
+    #[cfg(feature = "log_files")]
+    pub log_directory_mode: Option<String>,
+    /// The most bytes the log file and the files rotated from it may take up
+    /// together. If none, there is no limit.
+    #[cfg(feature = "log_files")]
+    pub max_log_bytes: Option<u64>,
+    /// Whether or not the oldest renamed logs are deleted to stay under
+    /// `max_log_bytes`.
+    #[cfg(feature = "log_files")]
+    #[serde(default = "default_false")]
+    pub prune_log_archives: bool,
+    /// The least bytes of free space on the disk for file logging to
+    /// continue. If none, there is no limit.
+    #[cfg(feature = "disk_space")]
+    pub min_free_space: Option<u64>,
+    /// Extra log files, each with their own range of levels. See
+    /// [`FileOutput`].
+    #[cfg(feature = "log_files")]
+    #[serde(default)]
+    pub log_files: Vec<FileOutput>,
+    /// Keep the last messages in memory to be dumped when something goes
+    /// wrong. See [`FlightRecorder`].
+    pub flight_recorder: Option<FlightRecorder>,
+    /// The most messages logged from each call site in an interval, such as
+    /// `"10/1s"`. If none, there is no limit. See [`RateLimit`].
+    pub rate_limit: Option<RateLimit>,
+    /// Whether or not repeats of the last message are counted instead of
+    /// logged.
+    #[serde(default = "default_false")]
+    pub collapse_duplicates: bool,
     /// The format of the timestamp that is added to log messages. The time
-    /// format is that of what is used in the [`chrono`] crate.
-    pub timestamp_format: Option<String>
+    /// format is that of what is used in the [`chrono`] crate. If none, then
+    /// the default format is used.
+    pub timestamp_format: Option<String>,
+    /// How the timestamp is written, such as `Rfc3339` or `Elapsed`.
+    #[cfg(feature = "time")]
+    #[serde(default = "TimestampStyle::default")]
+    pub timestamp_style: TimestampStyle,
+    /// Whether or not timestamps are in UTC instead of the local timezone.
+    #[cfg(feature = "time")]
+    #[serde(default = "default_false")]
+    pub timestamp_utc: bool
 }
 
 /// An error in loading a configuration file.
@@ -55,7 +141,54 @@ pub enum ConfigFileLoadError {
     IoError(io::Error),
     /// Something went wrong while parsing the yaml file. This can the actual
     /// syntax itself or it could be a problem with incorrect fields or values.
-    YamlError(serde_yaml::Error)
+    YamlError(serde_yaml::Error),
+    /// The timestamp format in the file is not a valid chrono format string.
+    #[cfg(feature = "time")]
+    InvalidTimestampFormat(InvalidTimestampFormat),
+    /// The log directory mode in the file is not an octal permission mode.
+    #[cfg(feature = "log_files")]
+    InvalidLogDirectoryMode(String),
+    /// The log file path in the file could not be used, such as when its
+    /// directory does not exist and directories are not created.
+    #[cfg(feature = "log_files")]
+    InvalidLogPath(SetLogPathError)
+}
+
+impl Display for ConfigFileLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigFileLoadError::IoError(e) => write!(f, "Could not read config file: {e}"),
+            ConfigFileLoadError::YamlError(e) => write!(f, "Could not parse config file: {e}"),
+            #[cfg(feature = "time")]
+            ConfigFileLoadError::InvalidTimestampFormat(e) => write!(f, "{e}"),
+            #[cfg(feature = "log_files")]
+            ConfigFileLoadError::InvalidLogDirectoryMode(e) => write!(f, "{e}"),
+            #[cfg(feature = "log_files")]
+            ConfigFileLoadError::InvalidLogPath(e) => write!(f, "{e}")
+        }
+    }
+}
+
+impl Error for ConfigFileLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigFileLoadError::IoError(e) => Some(e),
+            ConfigFileLoadError::YamlError(e) => Some(e),
+            #[cfg(feature = "time")]
+            ConfigFileLoadError::InvalidTimestampFormat(e) => Some(e),
+            #[cfg(feature = "log_files")]
+            ConfigFileLoadError::InvalidLogDirectoryMode(_) => None,
+            #[cfg(feature = "log_files")]
+            ConfigFileLoadError::InvalidLogPath(e) => Some(e)
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<InvalidTimestampFormat> for ConfigFileLoadError {
+    fn from(error: InvalidTimestampFormat) -> Self {
+        ConfigFileLoadError::InvalidTimestampFormat(error)
+    }
 }
 
 impl ConfigFile {
@@ -69,6 +202,29 @@ impl ConfigFile {
             serde_yaml::from_str(&file).map_err(ConfigFileLoadError::YamlError)?;
         Ok(config)
     }
+
+    /// Check the values in the config file that can not be checked while
+    /// parsing, such as the timestamp format.
+    pub fn validate(&self) -> Result<(), ConfigFileLoadError> {
+        #[cfg(feature = "time")]
+        if let Some(ref format) = self.timestamp_format {
+            time::validate_format(format)?;
+        }
+        #[cfg(feature = "log_files")]
+        self.parsed_log_directory_mode()?;
+
+        Ok(())
+    }
+
+    /// The `log_directory_mode` field, parsed.
+    #[cfg(feature = "log_files")]
+    pub fn parsed_log_directory_mode(&self) -> Result<Option<u32>, ConfigFileLoadError> {
+        self.log_directory_mode
+            .as_deref()
+            .map(existing_log_handler::parse_directory_mode)
+            .transpose()
+            .map_err(ConfigFileLoadError::InvalidLogDirectoryMode)
+    }
 }
 
 /// Used to set default values for the config file
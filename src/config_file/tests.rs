@@ -0,0 +1,319 @@
+use super::*;
+use crate::Logger;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// Write the given yaml to a temporary file that lives as long as the return
+/// value.
+fn config_file_with(contents: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().expect("could not create temp file");
+    file.write_all(contents.as_bytes())
+        .expect("could not write temp file");
+    file
+}
+
+#[test]
+fn test_timestamp_format_is_applied() {
+    let file = config_file_with("level: Warn\ntimestamp_format: \"%Y-%m-%d\"\n");
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(logger.get_level(), Level::Warn);
+    #[cfg(feature = "time")]
+    assert_eq!(logger.get_timestamp_format(), Some("%Y-%m-%d".to_string()));
+}
+
+#[test]
+fn test_missing_timestamp_format_resets_to_default() {
+    let file = config_file_with("level: Warn\n");
+    let logger = Logger::new();
+    #[cfg(feature = "time")]
+    logger.set_timestamp_format(Some("%Y".to_string())).unwrap();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    #[cfg(feature = "time")]
+    assert_eq!(logger.get_timestamp_format(), None);
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_invalid_timestamp_format_is_rejected() {
+    let file = config_file_with("level: Warn\ntimestamp_format: \"%H:%Q\"\n");
+    let logger = Logger::new();
+    logger.set_level(Level::Error);
+
+    match logger.load_config_file(file.path().to_str().unwrap()) {
+        Err(ConfigFileLoadError::InvalidTimestampFormat(e)) => {
+            assert_eq!(e, InvalidTimestampFormat("%H:%Q".to_string()))
+        }
+        other => panic!("expected an invalid timestamp format, got {:?}", other)
+    }
+
+    // Nothing from the file should have been applied
+    assert_eq!(logger.get_level(), Level::Error);
+    assert_eq!(logger.get_timestamp_format(), None);
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_timestamp_style_and_utc_are_applied() {
+    let file = config_file_with("timestamp_style: Rfc3339\ntimestamp_utc: true\n");
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(logger.get_timestamp_style(), crate::TimestampStyle::Rfc3339);
+    assert!(logger.get_timestamp_utc());
+}
+
+#[test]
+fn test_thread_and_process_id_are_applied() {
+    let file = config_file_with("show_thread: true\nshow_process_id: true\n");
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert!(logger.should_show_thread());
+    assert!(logger.should_show_process_id());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_log_directory_settings_are_applied() {
+    let file = config_file_with("create_log_directories: true\nlog_directory_mode: \"750\"\n");
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert!(logger.get_create_log_directories());
+    assert_eq!(logger.get_log_directory_mode(), Some(0o750));
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_invalid_log_directory_mode_is_rejected() {
+    let file = config_file_with("log_directory_mode: \"u+rwx\"\n");
+    let logger = Logger::new();
+
+    assert!(matches!(
+        logger.load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::InvalidLogDirectoryMode(_))
+    ));
+    assert_eq!(logger.get_log_directory_mode(), None);
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_log_file_path_in_missing_directory_is_rejected() {
+    let file = config_file_with(
+        "level: Warn\nlog_directory_mode: \"750\"\nlog_file_path: \"this/path/is/not/real/file.log\"\n"
+    );
+    let logger = Logger::new();
+    logger.set_level(Level::Error);
+
+    assert!(matches!(
+        logger.load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::InvalidLogPath(
+            SetLogPathError::CouldNotCreateLogFile(_)
+        ))
+    ));
+    // Nothing from the file should have been applied
+    assert_eq!(logger.get_log_path(), None);
+    assert_eq!(logger.get_level(), Level::Error);
+    assert_eq!(logger.get_log_directory_mode(), None);
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_invalid_extra_log_file_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = config_file_with(&format!(
+        "level: Warn\nlog_file_path: {:?}\nlog_files:\n  - path: {:?}\n",
+        dir.path().join("server.log"),
+        dir.path()
+    ));
+    let logger = Logger::new();
+    logger.set_level(Level::Error);
+
+    assert!(matches!(
+        logger.load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::InvalidLogPath(
+            SetLogPathError::PathIsNotAFile
+        ))
+    ));
+    assert_eq!(logger.get_log_path(), None);
+    assert_eq!(logger.get_level(), Level::Error);
+    assert!(logger.get_log_files().is_empty());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_existing_log_handler_names() {
+    for (name, handler) in [
+        ("FailIfExists", ExistingLogHandler::FailIfExists),
+        ("fail_if_exists", ExistingLogHandler::FailIfExists),
+        ("new-numbered", ExistingLogHandler::NewNumbered),
+        (
+            "append_with_session_header",
+            ExistingLogHandler::AppendWithSessionHeader
+        )
+    ] {
+        let file = config_file_with(&format!("existing_log_handler: {name}\n"));
+        let logger = Logger::new();
+
+        logger
+            .load_config_file(file.path().to_str().unwrap())
+            .expect("config file should load");
+
+        assert_eq!(logger.get_existing_log_handler(), handler);
+    }
+
+    let file = config_file_with("existing_log_handler: sideways\n");
+    assert!(matches!(
+        Logger::new().load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::YamlError(_))
+    ));
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_flush_settings_are_applied() {
+    let file = config_file_with(
+        "flush_policy: \"level:warn\"\nsync_on_error: true\nwrite_error_policy: \"buffer:1024\"\n"
+    );
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(
+        logger.get_flush_policy(),
+        crate::flush_policy::FlushPolicy::AtLevel(Level::Warn)
+    );
+    assert!(logger.get_sync_on_error());
+    assert_eq!(
+        logger.get_write_error_policy(),
+        crate::write_error_policy::WriteErrorPolicy::Buffer { max_bytes: 1024 }
+    );
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_invalid_flush_policy_is_rejected() {
+    let file = config_file_with("flush_policy: sometimes\n");
+
+    assert!(matches!(
+        Logger::new().load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::YamlError(_))
+    ));
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_log_files_are_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    let file = config_file_with(&format!(
+        "log_files:\n  - path: {:?}\n    min_level: Error\n    color: true\n",
+        errors
+    ));
+    let logger = Logger::new();
+    logger
+        .add_log_file(crate::file_output::FileOutput::new(
+            dir.path().join("old.log")
+        ))
+        .unwrap();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(
+        logger.get_log_files(),
+        [crate::file_output::FileOutput::new(&errors)
+            .min_level(Level::Error)
+            .color(true)]
+    );
+    assert!(errors.exists());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_disk_limits_are_applied() {
+    let file = config_file_with(
+        "max_log_bytes: 1048576\nprune_log_archives: true\nmin_free_space: 4096\n"
+    );
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(logger.get_max_log_bytes(), Some(1_048_576));
+    assert!(logger.get_prune_log_archives());
+    #[cfg(feature = "disk_space")]
+    assert_eq!(logger.get_min_free_space(), Some(4096));
+}
+
+#[test]
+fn test_flight_recorder_is_applied() {
+    let file = config_file_with(
+        "flight_recorder:\n  capacity: 50\n  trigger: Warn\n  dump_to:\n    File: \"crash.log\"\n"
+    );
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(
+        logger.get_flight_recorder(),
+        Some(
+            crate::flight_recorder::FlightRecorder::new(50)
+                .trigger(Level::Warn)
+                .dump_to(crate::flight_recorder::DumpTarget::File("crash.log".into()))
+        )
+    );
+}
+
+#[test]
+fn test_rate_limit_and_duplicates_are_applied() {
+    let file = config_file_with("rate_limit: \"10/500ms\"\ncollapse_duplicates: true\n");
+    let logger = Logger::new();
+
+    logger
+        .load_config_file(file.path().to_str().unwrap())
+        .expect("config file should load");
+
+    assert_eq!(
+        logger.get_rate_limit(),
+        Some(crate::rate_limit::RateLimit::new(
+            10,
+            std::time::Duration::from_millis(500)
+        ))
+    );
+    assert!(logger.get_collapse_duplicates());
+}
+
+#[test]
+fn test_invalid_rate_limit_is_rejected() {
+    let file = config_file_with("rate_limit: \"often\"\n");
+
+    assert!(matches!(
+        Logger::new().load_config_file(file.path().to_str().unwrap()),
+        Err(ConfigFileLoadError::YamlError(_))
+    ));
+}
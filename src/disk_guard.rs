@@ -0,0 +1,218 @@
+//! Limits on how much disk the log files may use. See
+//! [`Logger::set_max_log_bytes`](crate::Logger::set_max_log_bytes) and
+//! `Logger::set_min_free_space`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::existing_log_handler::split_file_name;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the disk is looked at while logging to a file. The total size
+/// of the log files is also checked early when the messages written since
+/// the last check could have gone over the limit.
+pub(crate) const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the disk limits need to remember between messages.
+#[derive(Debug)]
+pub(crate) struct DiskGuardState {
+    /// When the disk was last looked at.
+    last_check: Option<SystemTime>,
+    /// The total size of the log files at the last check, plus the size of
+    /// the messages written since.
+    estimated_bytes: u64,
+    /// Whether or not file logging is paused.
+    paused: bool
+}
+
+impl DiskGuardState {
+    /// A state that checks the disk before the next message.
+    pub(crate) fn new() -> DiskGuardState {
+        DiskGuardState {
+            last_check: None,
+            estimated_bytes: 0,
+            paused: false
+        }
+    }
+
+    /// Whether or not file logging is paused.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Look at the disk again before the next message, such as after the
+    /// limits were changed.
+    pub(crate) fn check_soon(&mut self) {
+        self.last_check = None;
+    }
+
+    /// Whether or not the disk should be looked at before writing a message
+    /// of the given size.
+    pub(crate) fn check_due(
+        &self,
+        max_bytes: Option<u64>,
+        message_len: u64,
+        now: SystemTime
+    ) -> bool {
+        let last_check = match self.last_check {
+            Some(last_check) => last_check,
+            None => return true
+        };
+        let interval_passed = now
+            .duration_since(last_check)
+            .map(|elapsed| elapsed >= CHECK_INTERVAL)
+            .unwrap_or(false);
+        // While paused, the size is only looked at every interval, so that a
+        // flood of messages does not read the directory for each one.
+        let limit_reached = !self.paused
+            && max_bytes.map_or(false, |max_bytes| {
+                self.estimated_bytes.saturating_add(message_len) > max_bytes
+            });
+
+        interval_passed || limit_reached
+    }
+
+    /// Remember the result of looking at the disk. Returns whether or not
+    /// file logging was paused before.
+    pub(crate) fn checked(&mut self, now: SystemTime, total_bytes: u64, pause: bool) -> bool {
+        let was_paused = self.paused;
+        self.last_check = Some(now);
+        self.estimated_bytes = total_bytes;
+        self.paused = pause;
+
+        was_paused
+    }
+
+    /// Count a message that is about to be written, unless file logging is
+    /// paused. Returns whether or not it may be written.
+    pub(crate) fn message_allowed(&mut self, message_len: u64) -> bool {
+        if !self.paused {
+            self.estimated_bytes = self.estimated_bytes.saturating_add(message_len);
+        }
+
+        !self.paused
+    }
+}
+
+/// Find the log file at `path` and the files that were rotated or renamed
+/// from it, next to it and in the archive directory.
+///
+/// For `server.log` these are files such as `server.2022-03-12_18-14-09.log`
+/// and `server.3.log` from the existing log handlers, and `server.log.1` or
+/// `server.log-20220312` from tools like logrotate. The part after the name
+/// must start with a digit, so that other files such as `server.debug.log`
+/// are left alone.
+pub(crate) fn log_file_siblings(
+    path: &Path,
+    archive_directory: Option<&Path>
+) -> io::Result<Vec<(PathBuf, Metadata)>> {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name,
+        None => return Ok(Vec::new())
+    };
+    let (stem, extension) = split_file_name(file_name);
+    let file_name = file_name.to_string_lossy();
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from(".")
+    };
+
+    let is_sibling = |name: &str| {
+        let renamed = name
+            .strip_prefix(&format!("{stem}."))
+            .and_then(|rest| rest.strip_suffix(&extension));
+        let rotated = name
+            .strip_prefix(&*file_name)
+            .and_then(|rest| rest.strip_prefix('.').or_else(|| rest.strip_prefix('-')));
+
+        name == file_name
+            || [renamed, rotated]
+                .iter()
+                .flatten()
+                .any(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    };
+
+    let mut directories = vec![directory];
+    if let Some(archive_directory) = archive_directory {
+        if archive_directory.is_dir() && !directories.iter().any(|d| d == archive_directory) {
+            directories.push(archive_directory.to_path_buf());
+        }
+    }
+
+    let mut siblings = Vec::new();
+    for directory in directories {
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            if !is_sibling(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                siblings.push((entry.path(), metadata));
+            }
+        }
+    }
+
+    Ok(siblings)
+}
+
+/// Add up the size of the log file at `path` and its siblings. If
+/// `archives` is given, the oldest of them are deleted until the total is no
+/// more than `max_bytes`. Returns the total size left.
+///
+/// Only files in `archives`, which are the renamed logs made by the logger,
+/// are ever deleted, oldest first. Ones that are deleted or no longer there
+/// are taken out of it.
+pub(crate) fn enforce_max_bytes(
+    path: &Path,
+    archive_directory: Option<&Path>,
+    archives: Option<&mut Vec<PathBuf>>,
+    max_bytes: u64
+) -> io::Result<u64> {
+    let siblings = log_file_siblings(path, archive_directory)?;
+    let mut total: u64 = siblings.iter().map(|(_, metadata)| metadata.len()).sum();
+    let archives = match archives {
+        Some(archives) => archives,
+        None => return Ok(total)
+    };
+
+    // The archives are in the order they were made
+    while total > max_bytes && !archives.is_empty() {
+        match fs::metadata(&archives[0]) {
+            Ok(metadata) => {
+                fs::remove_file(&archives[0])?;
+                total = total.saturating_sub(metadata.len());
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e)
+        }
+        archives.remove(0);
+    }
+
+    Ok(total)
+}
+
+/// The bytes available to this process on the file system of `path`.
+#[cfg(all(feature = "disk_space", unix))]
+pub(crate) fn free_space(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // Safety: the path is a valid C string, and statvfs fills in the stats
+    // when it returns 0.
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stats.assume_init()
+    };
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
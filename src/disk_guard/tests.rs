@@ -0,0 +1,140 @@
+use super::*;
+use std::time::UNIX_EPOCH;
+
+#[test]
+fn test_check_due() {
+    let start = UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut state = DiskGuardState::new();
+    assert!(
+        state.check_due(None, 10, start),
+        "the first message is checked"
+    );
+
+    assert!(!state.checked(start, 100, false));
+    assert!(state.message_allowed(10));
+    assert!(!state.check_due(None, 10, start));
+    assert!(!state.check_due(Some(200), 90, start));
+    assert!(
+        state.check_due(Some(200), 91, start),
+        "could go over the limit"
+    );
+    assert!(state.check_due(None, 10, start + CHECK_INTERVAL));
+
+    state.check_soon();
+    assert!(state.check_due(None, 10, start));
+}
+
+#[test]
+fn test_paused_only_checks_every_interval() {
+    let start = UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut state = DiskGuardState::new();
+
+    assert!(!state.checked(start, 300, true));
+    assert!(state.is_paused());
+    assert!(!state.message_allowed(10));
+    assert!(!state.check_due(Some(200), 10, start));
+    assert!(state.check_due(Some(200), 10, start + CHECK_INTERVAL));
+
+    assert!(state.checked(start + CHECK_INTERVAL, 100, false));
+    assert!(state.message_allowed(10));
+}
+
+#[test]
+fn test_log_file_siblings() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("archive");
+    fs::create_dir(&archive).unwrap();
+    for name in [
+        "server.log",
+        "server.3.log",
+        "server.2022-03-12_18-14-09.log",
+        "server.log.1",
+        "server.log.2.gz",
+        "server.log-20220312",
+        "server.debug.log",
+        "server.log.old",
+        "servers.1.log",
+        "errors.log"
+    ] {
+        fs::write(dir.path().join(name), "data").unwrap();
+    }
+    fs::write(archive.join("server.2022-03-11_09-00-00.log"), "data").unwrap();
+    fs::write(archive.join("other.log"), "data").unwrap();
+
+    let mut names: Vec<String> = log_file_siblings(&dir.path().join("server.log"), Some(&archive))
+        .unwrap()
+        .into_iter()
+        .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    assert_eq!(
+        names,
+        [
+            "server.2022-03-11_09-00-00.log",
+            "server.2022-03-12_18-14-09.log",
+            "server.3.log",
+            "server.log",
+            "server.log-20220312",
+            "server.log.1",
+            "server.log.2.gz"
+        ]
+    );
+}
+
+#[test]
+fn test_enforce_max_bytes_deletes_nothing_without_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    for name in ["app.log", "app.1.log", "app.log.1"] {
+        fs::write(dir.path().join(name), [b'x'; 100]).unwrap();
+    }
+
+    assert_eq!(enforce_max_bytes(&path, None, None, 50).unwrap(), 300);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test]
+fn test_enforce_max_bytes_deletes_only_the_oldest_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    for name in ["app.log", "app.log.1", "app.1.log", "app.2.log"] {
+        fs::write(dir.path().join(name), [b'x'; 100]).unwrap();
+    }
+    let mut archives = vec![
+        dir.path().join("app.0.log"),
+        dir.path().join("app.1.log"),
+        dir.path().join("app.2.log"),
+    ];
+
+    assert_eq!(
+        enforce_max_bytes(&path, None, Some(&mut archives), 400).unwrap(),
+        400
+    );
+    assert_eq!(archives.len(), 3, "nothing is deleted under the limit");
+
+    assert_eq!(
+        enforce_max_bytes(&path, None, Some(&mut archives), 350).unwrap(),
+        300
+    );
+    assert_eq!(archives, [dir.path().join("app.2.log")]);
+    assert!(!dir.path().join("app.1.log").exists());
+
+    // Files that were not made by the logger are kept, even over the limit
+    assert_eq!(
+        enforce_max_bytes(&path, None, Some(&mut archives), 50).unwrap(),
+        200
+    );
+    assert!(archives.is_empty());
+    assert!(path.exists());
+    assert!(dir.path().join("app.log.1").exists());
+}
+
+#[cfg(all(feature = "disk_space", unix))]
+#[test]
+fn test_free_space() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert!(free_space(dir.path()).unwrap() > 0);
+    assert!(free_space(&dir.path().join("missing")).is_err());
+}
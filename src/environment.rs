@@ -2,71 +2,432 @@
 //!
 //! This module is not included unles the `env` feature is enabled.
 
+#[cfg(test)]
+mod tests;
+
 use super::Logger;
+#[cfg(feature = "log_files")]
+use crate::existing_log_handler::{self, ExistingLogHandler};
+#[cfg(feature = "log_files")]
+use crate::flush_policy::FlushPolicy;
+use crate::rate_limit::RateLimit;
+#[cfg(feature = "time")]
+use crate::time::{self, TimestampStyle};
+#[cfg(feature = "log_files")]
+use crate::write_error_policy::WriteErrorPolicy;
 use crate::Level;
 use std::env;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The prefix used for all variables by [`configure`].
+pub const DEFAULT_PREFIX: &str = "PL_";
+
+/// An environment variable that was set, but could not be applied to the
+/// logger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEnvVar {
+    /// The full name of the variable, including the prefix.
+    pub name: String,
+    /// The value the variable was set to.
+    pub value: String,
+    /// Why the value could not be used.
+    pub reason: String
+}
+
+impl Display for InvalidEnvVar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid value {:?} for {}: {}",
+            self.value, self.name, self.reason
+        )
+    }
+}
+
+impl Error for InvalidEnvVar {}
+
+/// Parse a boolean flag.
+///
+/// `true`, `1`, `yes`, and `on` are true, while `false`, `0`, `no`, and `off`
+/// are false. Case is ignored. Anything else is not a boolean.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None
+    }
+}
 
 /// Apply the environment variable configuration.
 ///
 /// Usually it will be easier to call the `load_env_vars` method on the Logger
-/// struct instead of this.
+/// struct instead of this. This is the same as calling
+/// [`configure_with_prefix`] with the [`DEFAULT_PREFIX`].
 ///
 /// # Variables
 ///
 /// | Name       | Type    | Description |
 /// |------------|---------|-------------|
+/// | `PL_CONFIG` | string | Path of a config file to load. Requires the `config` feature |
 /// | `PL_COLOR` | boolean | Whether or not to color the terminal output |
 /// | `PL_FILE_COLOR` | boolean | Whether or not to color the file output |
 /// | `PL_SHOW_TIME` | boolean | Whether or not to show a time stamp on messages |
+/// | `PL_TIMESTAMP_FORMAT` | string | chrono format of the time stamp. Empty for the default |
+/// | `PL_TIMESTAMP_STYLE` | format,rfc3339,iso8601_millis,iso8601_micros,unix_seconds,unix_millis,elapsed | How the time stamp is written |
+/// | `PL_TIMESTAMP_UTC` | boolean | Whether or not the time stamp is in UTC |
+/// | `PL_SHOW_THREAD` | boolean | Whether or not to show the thread on messages |
+/// | `PL_SHOW_PROCESS_ID` | boolean | Whether or not to show the process ID on messages |
 /// | `PL_LEVEL` | debug,info,warn,error,none | Logging level |
 /// | `PL_FILE` | string | Log file path |
+/// | `PL_EXISTING_LOG_HANDLER` | append,overwrite,rename,fail_if_exists,new_numbered,append_with_session_header | What to do with an existing log file |
+/// | `PL_LOG_ARCHIVE_DIRECTORY` | string | Where renamed log files are moved to. Empty for next to the log file |
+/// | `PL_FLUSH_POLICY` | never,every_message,level:\<level\>,every:\<count\>,interval:\<duration\> | When the log file is flushed. Durations are like `250ms`, `5s` or `1m` |
+/// | `PL_SYNC_ON_ERROR` | boolean | Whether or not error messages are synced to the disk |
+/// | `PL_WRITE_ERROR_POLICY` | disable,stderr,buffer:\<bytes\>,retry:\<attempts\>:\<duration\> | What to do when the log file can not be written to |
+/// | `PL_CREATE_LOG_DIRECTORIES` | boolean | Whether or not to create missing directories of the log file |
+/// | `PL_LOG_DIRECTORY_MODE` | octal | Unix permission mode of created log directories, such as `750`. Empty for the default |
+/// | `PL_MAX_LOG_BYTES` | integer | Most bytes the log file and its rotated files may take up. Empty for no limit |
+/// | `PL_PRUNE_LOG_ARCHIVES` | boolean | Whether or not the oldest renamed logs are deleted to stay under `PL_MAX_LOG_BYTES` |
+/// | `PL_MIN_FREE_SPACE` | integer | Least bytes free on the disk for file logging to continue. Empty for no limit. Requires the `disk_space` feature |
+/// | `PL_RATE_LIMIT` | \<messages\>/\<duration\> | Most messages logged from each call site in an interval, such as `10/1s`. Empty for no limit |
+/// | `PL_COLLAPSE_DUPLICATES` | boolean | Whether or not repeats of the last message are counted instead of logged |
 ///
-/// For boolean flags, if set to "true", they will be true; if not set, then
-/// they will have no effect either way; and if they are set to anything other
-/// than "true", they will be "false".
-pub fn configure(logger: &Logger) {
-    match env::var("PL_COLOR") {
-        Ok(val) if val == "true" => logger.set_color(true),
-        Ok(_) => logger.set_color(false),
-        // Unset, or invalid
-        _ => {}
-    };
+/// If `PL_CONFIG` is set, the config file is loaded first, and then all the
+/// other variables are applied on top of it. This means that environment
+/// variables always take priority over the config file. A config file that
+/// can not be loaded is logged as an error on the logger and otherwise
+/// ignored.
+///
+/// Boolean flags accept `true`, `1`, `yes`, or `on` for true, and `false`,
+/// `0`, `no`, or `off` for false, ignoring case. Unset variables have no
+/// effect.
+///
+/// # Returns
+/// Every variable that was set but could not be applied. The logger setting
+/// for these variables is left unchanged.
+pub fn configure(logger: &Logger) -> Vec<InvalidEnvVar> {
+    configure_with_prefix(logger, DEFAULT_PREFIX)
+}
 
-    #[cfg(feature = "log_files")]
-    match env::var("PL_FILE_COLOR") {
-        Ok(val) if val == "true" => logger.set_log_file_color(true),
-        Ok(_) => logger.set_log_file_color(false),
-        // Unset, or invalid
-        _ => {}
-    };
+/// Apply the environment variable configuration, using the given prefix for
+/// the variable names instead of `PL_`.
+///
+/// For example with the prefix `MYAPP_LOG_` the level is read from
+/// `MYAPP_LOG_LEVEL`. See [`configure`] for the list of variables.
+pub fn configure_with_prefix(logger: &Logger, prefix: &str) -> Vec<InvalidEnvVar> {
+    #[cfg_attr(not(feature = "log_files"), allow(unused_mut))]
+    let (settings, mut errors) = EnvSettings::read(prefix);
+
+    #[cfg(feature = "config")]
+    if let Some(ref path) = settings.config {
+        if let Err(e) = logger.load_config_file(path) {
+            logger.error(&format!("Could not load config file {path:?}: {e}"));
+        }
+    }
 
+    if let Some(color) = settings.color {
+        logger.set_color(color);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(color) = settings.file_color {
+        logger.set_log_file_color(color);
+    }
     #[cfg(feature = "time")]
-    match env::var("PL_SHOW_TIME") {
-        Ok(val) if val == "true" => logger.set_should_show_time(true),
-        Ok(_) => logger.set_should_show_time(false),
-        // Unset, or invalid
-        _ => {}
-    };
+    if let Some(show_time) = settings.show_time {
+        logger.set_should_show_time(show_time);
+    }
+    #[cfg(feature = "time")]
+    if let Some(format) = settings.timestamp_format {
+        // Already validated while reading
+        let _ = logger.set_timestamp_format(format);
+    }
+    #[cfg(feature = "time")]
+    if let Some(style) = settings.timestamp_style {
+        logger.set_timestamp_style(style);
+    }
+    #[cfg(feature = "time")]
+    if let Some(utc) = settings.timestamp_utc {
+        logger.set_timestamp_utc(utc);
+    }
+    if let Some(show_thread) = settings.show_thread {
+        logger.set_should_show_thread(show_thread);
+    }
+    if let Some(show_process_id) = settings.show_process_id {
+        logger.set_should_show_process_id(show_process_id);
+    }
+    if let Some(level) = settings.level {
+        logger.set_level(level);
+    }
+    if let Some(limit) = settings.rate_limit {
+        logger.set_rate_limit(limit);
+    }
+    if let Some(collapse) = settings.collapse_duplicates {
+        logger.set_collapse_duplicates(collapse);
+    }
+    // The handler is applied before the file so that it is used for the file.
+    #[cfg(feature = "log_files")]
+    if let Some(handler) = settings.existing_log_handler {
+        logger.set_existing_log_handler(handler);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(directory) = settings.log_archive_directory {
+        logger.set_log_archive_directory(directory);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(policy) = settings.flush_policy {
+        logger.set_flush_policy(policy);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(sync) = settings.sync_on_error {
+        logger.set_sync_on_error(sync);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(policy) = settings.write_error_policy {
+        logger.set_write_error_policy(policy);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(create) = settings.create_log_directories {
+        logger.set_create_log_directories(create);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(mode) = settings.log_directory_mode {
+        logger.set_log_directory_mode(mode);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(max_bytes) = settings.max_log_bytes {
+        logger.set_max_log_bytes(max_bytes);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(prune) = settings.prune_log_archives {
+        logger.set_prune_log_archives(prune);
+    }
+    #[cfg(feature = "disk_space")]
+    if let Some(min_bytes) = settings.min_free_space {
+        logger.set_min_free_space(min_bytes);
+    }
+    #[cfg(feature = "log_files")]
+    if let Some(path) = settings.file {
+        if let Err(e) = logger.set_log_path(&path) {
+            errors.push(InvalidEnvVar {
+                name: format!("{prefix}FILE"),
+                value: path,
+                reason: e.to_string()
+            });
+        }
+    }
 
-    match env::var("PL_LEVEL") {
-        Ok(val) => match val.to_lowercase().as_ref() {
-            "debug" => logger.set_level(Level::Debug),
-            "info" => logger.set_level(Level::Info),
-            "warn" => logger.set_level(Level::Warn),
-            "error" => logger.set_level(Level::Error),
-            "none" => logger.set_level(Level::None),
-            _ => {}
-        },
-        // Unset, or invalid
-        _ => {}
-    };
+    errors
+}
 
+/// The values of all the environment variables that are set, parsed but not
+/// yet applied to anything.
+///
+/// A field is `None` when its variable is not set or has an invalid value.
+#[derive(Debug, Default)]
+pub(crate) struct EnvSettings {
+    #[cfg(feature = "config")]
+    pub config: Option<String>,
+    pub color: Option<bool>,
+    #[cfg(feature = "log_files")]
+    pub file_color: Option<bool>,
+    #[cfg(feature = "time")]
+    pub show_time: Option<bool>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// selects the default format.
+    #[cfg(feature = "time")]
+    pub timestamp_format: Option<Option<String>>,
+    #[cfg(feature = "time")]
+    pub timestamp_style: Option<TimestampStyle>,
+    #[cfg(feature = "time")]
+    pub timestamp_utc: Option<bool>,
+    pub show_thread: Option<bool>,
+    pub show_process_id: Option<bool>,
+    pub level: Option<Level>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// removes the limit.
+    pub rate_limit: Option<Option<RateLimit>>,
+    pub collapse_duplicates: Option<bool>,
+    #[cfg(feature = "log_files")]
+    pub existing_log_handler: Option<ExistingLogHandler>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// removes the archive directory.
+    #[cfg(feature = "log_files")]
+    pub log_archive_directory: Option<Option<String>>,
+    #[cfg(feature = "log_files")]
+    pub flush_policy: Option<FlushPolicy>,
+    #[cfg(feature = "log_files")]
+    pub sync_on_error: Option<bool>,
+    #[cfg(feature = "log_files")]
+    pub write_error_policy: Option<WriteErrorPolicy>,
     #[cfg(feature = "log_files")]
-    match env::var("PL_FILE") {
-        Ok(val) => {
-            let _ = logger.set_log_path(&val);
+    pub create_log_directories: Option<bool>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// selects the default permissions.
+    #[cfg(feature = "log_files")]
+    pub log_directory_mode: Option<Option<u32>>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// removes the limit.
+    #[cfg(feature = "log_files")]
+    pub max_log_bytes: Option<Option<u64>>,
+    #[cfg(feature = "log_files")]
+    pub prune_log_archives: Option<bool>,
+    /// `Some(None)` means the variable was set to an empty string, which
+    /// removes the limit.
+    #[cfg(feature = "disk_space")]
+    pub min_free_space: Option<Option<u64>>,
+    #[cfg(feature = "log_files")]
+    pub file: Option<String>
+}
+
+impl EnvSettings {
+    /// Read and parse all the variables with the given prefix. Variables with
+    /// values that can not be parsed are returned as errors.
+    pub(crate) fn read(prefix: &str) -> (EnvSettings, Vec<InvalidEnvVar>) {
+        let mut errors = Vec::new();
+        let settings = EnvSettings {
+            #[cfg(feature = "config")]
+            config: read_var(prefix, "CONFIG", &mut errors, |val| Ok(val.to_string())),
+            color: read_var(prefix, "COLOR", &mut errors, parse_bool_var),
+            #[cfg(feature = "log_files")]
+            file_color: read_var(prefix, "FILE_COLOR", &mut errors, parse_bool_var),
+            #[cfg(feature = "time")]
+            show_time: read_var(prefix, "SHOW_TIME", &mut errors, parse_bool_var),
+            #[cfg(feature = "time")]
+            timestamp_format: read_var(prefix, "TIMESTAMP_FORMAT", &mut errors, |val| {
+                if val.is_empty() {
+                    Ok(None)
+                } else {
+                    time::validate_format(val).map_err(|e| e.to_string())?;
+                    Ok(Some(val.to_string()))
+                }
+            }),
+            #[cfg(feature = "time")]
+            timestamp_style: read_var(
+                prefix,
+                "TIMESTAMP_STYLE",
+                &mut errors,
+                TimestampStyle::from_str
+            ),
+            #[cfg(feature = "time")]
+            timestamp_utc: read_var(prefix, "TIMESTAMP_UTC", &mut errors, parse_bool_var),
+            show_thread: read_var(prefix, "SHOW_THREAD", &mut errors, parse_bool_var),
+            show_process_id: read_var(prefix, "SHOW_PROCESS_ID", &mut errors, parse_bool_var),
+            level: read_var(prefix, "LEVEL", &mut errors, Level::from_str),
+            rate_limit: read_var(prefix, "RATE_LIMIT", &mut errors, |val| {
+                if val.trim().is_empty() {
+                    Ok(None)
+                } else {
+                    RateLimit::from_str(val).map(Some)
+                }
+            }),
+            collapse_duplicates: read_var(
+                prefix,
+                "COLLAPSE_DUPLICATES",
+                &mut errors,
+                parse_bool_var
+            ),
+            #[cfg(feature = "log_files")]
+            existing_log_handler: read_var(
+                prefix,
+                "EXISTING_LOG_HANDLER",
+                &mut errors,
+                ExistingLogHandler::from_str
+            ),
+            #[cfg(feature = "log_files")]
+            log_archive_directory: read_var(prefix, "LOG_ARCHIVE_DIRECTORY", &mut errors, |val| {
+                Ok(Some(val.to_string()).filter(|val| !val.is_empty()))
+            }),
+            #[cfg(feature = "log_files")]
+            flush_policy: read_var(prefix, "FLUSH_POLICY", &mut errors, FlushPolicy::from_str),
+            #[cfg(feature = "log_files")]
+            sync_on_error: read_var(prefix, "SYNC_ON_ERROR", &mut errors, parse_bool_var),
+            #[cfg(feature = "log_files")]
+            write_error_policy: read_var(
+                prefix,
+                "WRITE_ERROR_POLICY",
+                &mut errors,
+                WriteErrorPolicy::from_str
+            ),
+            #[cfg(feature = "log_files")]
+            create_log_directories: read_var(
+                prefix,
+                "CREATE_LOG_DIRECTORIES",
+                &mut errors,
+                parse_bool_var
+            ),
+            #[cfg(feature = "log_files")]
+            log_directory_mode: read_var(prefix, "LOG_DIRECTORY_MODE", &mut errors, |val| {
+                if val.is_empty() {
+                    Ok(None)
+                } else {
+                    existing_log_handler::parse_directory_mode(val).map(Some)
+                }
+            }),
+            #[cfg(feature = "log_files")]
+            max_log_bytes: read_var(prefix, "MAX_LOG_BYTES", &mut errors, parse_optional_bytes),
+            #[cfg(feature = "log_files")]
+            prune_log_archives: read_var(prefix, "PRUNE_LOG_ARCHIVES", &mut errors, parse_bool_var),
+            #[cfg(feature = "disk_space")]
+            min_free_space: read_var(prefix, "MIN_FREE_SPACE", &mut errors, parse_optional_bytes),
+            #[cfg(feature = "log_files")]
+            file: read_var(prefix, "FILE", &mut errors, |val| Ok(val.to_string()))
+        };
+
+        (settings, errors)
+    }
+}
+
+/// Read a variable and convert it with the given function. If the variable is
+/// set but can not be converted, the problem is added to `errors`.
+fn read_var<T, F>(prefix: &str, name: &str, errors: &mut Vec<InvalidEnvVar>, f: F) -> Option<T>
+where
+    F: FnOnce(&str) -> Result<T, String>
+{
+    let name = format!("{prefix}{name}");
+    let value = match env::var(&name) {
+        Ok(value) => value,
+        Err(env::VarError::NotPresent) => return None,
+        Err(env::VarError::NotUnicode(value)) => {
+            errors.push(InvalidEnvVar {
+                name,
+                value: value.to_string_lossy().to_string(),
+                reason: "not valid unicode".to_string()
+            });
+            return None;
         }
-        // Unset, or invalid
-        _ => {}
     };
+
+    match f(&value) {
+        Ok(result) => Some(result),
+        Err(reason) => {
+            errors.push(InvalidEnvVar {
+                name,
+                value,
+                reason
+            });
+            None
+        }
+    }
+}
+
+/// Parse a number of bytes, or no limit for an empty string, for use with
+/// [`read_var`].
+#[cfg(feature = "log_files")]
+fn parse_optional_bytes(value: &str) -> Result<Option<u64>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| "expected a number of bytes".to_string())
+}
+
+/// [`parse_bool`] with an error message, for use with [`read_var`].
+fn parse_bool_var(value: &str) -> Result<bool, String> {
+    parse_bool(value).ok_or_else(|| "expected one of true/1/yes/on or false/0/no/off".to_string())
 }
@@ -0,0 +1,245 @@
+//! Each test uses its own variable prefix so that the tests can run in
+//! parallel without seeing each other's variables.
+
+use super::*;
+
+#[test]
+fn test_parse_bool() {
+    for value in ["true", "1", "yes", "on", "TRUE", "Yes", " on "] {
+        assert_eq!(parse_bool(value), Some(true), "{:?}", value);
+    }
+    for value in ["false", "0", "no", "off", "FALSE", "No"] {
+        assert_eq!(parse_bool(value), Some(false), "{:?}", value);
+    }
+    for value in ["", "2", "maybe", "truee"] {
+        assert_eq!(parse_bool(value), None, "{:?}", value);
+    }
+}
+
+#[test]
+fn test_configure_with_prefix_applies_values() {
+    env::set_var("PLT_APPLY_COLOR", "off");
+    env::set_var("PLT_APPLY_LEVEL", "warn");
+    env::set_var("PLT_APPLY_SHOW_THREAD", "1");
+    env::set_var("PLT_APPLY_SHOW_PROCESS_ID", "true");
+    env::set_var("PLT_APPLY_RATE_LIMIT", "10/1s");
+    env::set_var("PLT_APPLY_COLLAPSE_DUPLICATES", "yes");
+    #[cfg(feature = "time")]
+    env::set_var("PLT_APPLY_SHOW_TIME", "0");
+    #[cfg(feature = "time")]
+    env::set_var("PLT_APPLY_TIMESTAMP_FORMAT", "%Y");
+    #[cfg(feature = "time")]
+    env::set_var("PLT_APPLY_TIMESTAMP_STYLE", "unix_millis");
+    #[cfg(feature = "time")]
+    env::set_var("PLT_APPLY_TIMESTAMP_UTC", "on");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_FILE_COLOR", "yes");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_EXISTING_LOG_HANDLER", "new_numbered");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_LOG_ARCHIVE_DIRECTORY", "logs/archive");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_FLUSH_POLICY", "every:5");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_SYNC_ON_ERROR", "on");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_WRITE_ERROR_POLICY", "stderr");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_CREATE_LOG_DIRECTORIES", "yes");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_LOG_DIRECTORY_MODE", "0750");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_MAX_LOG_BYTES", "1048576");
+    #[cfg(feature = "log_files")]
+    env::set_var("PLT_APPLY_PRUNE_LOG_ARCHIVES", "yes");
+    #[cfg(feature = "disk_space")]
+    env::set_var("PLT_APPLY_MIN_FREE_SPACE", "4096");
+
+    let logger = Logger::new();
+    let errors = configure_with_prefix(&logger, "PLT_APPLY_");
+
+    assert_eq!(errors, vec![]);
+    assert!(!logger.get_color());
+    assert_eq!(logger.get_level(), Level::Warn);
+    assert!(logger.should_show_thread());
+    assert!(logger.should_show_process_id());
+    assert_eq!(
+        logger.get_rate_limit(),
+        Some(crate::rate_limit::RateLimit::new(
+            10,
+            std::time::Duration::from_secs(1)
+        ))
+    );
+    assert!(logger.get_collapse_duplicates());
+    #[cfg(feature = "time")]
+    assert!(!logger.should_show_time());
+    #[cfg(feature = "time")]
+    assert_eq!(logger.get_timestamp_format(), Some("%Y".to_string()));
+    #[cfg(feature = "time")]
+    assert_eq!(logger.get_timestamp_style(), TimestampStyle::UnixMillis);
+    #[cfg(feature = "time")]
+    assert!(logger.get_timestamp_utc());
+    #[cfg(feature = "log_files")]
+    assert!(logger.get_log_file_color());
+    #[cfg(feature = "log_files")]
+    assert_eq!(
+        logger.get_existing_log_handler(),
+        ExistingLogHandler::NewNumbered
+    );
+    #[cfg(feature = "log_files")]
+    assert_eq!(
+        logger.get_log_archive_directory(),
+        Some(std::path::PathBuf::from("logs/archive"))
+    );
+    #[cfg(feature = "log_files")]
+    assert_eq!(
+        logger.get_flush_policy(),
+        crate::flush_policy::FlushPolicy::EveryN(5)
+    );
+    #[cfg(feature = "log_files")]
+    assert!(logger.get_sync_on_error());
+    #[cfg(feature = "log_files")]
+    assert_eq!(
+        logger.get_write_error_policy(),
+        crate::write_error_policy::WriteErrorPolicy::FallbackToStderr
+    );
+    #[cfg(feature = "log_files")]
+    assert!(logger.get_create_log_directories());
+    #[cfg(feature = "log_files")]
+    assert_eq!(logger.get_log_directory_mode(), Some(0o750));
+    #[cfg(feature = "log_files")]
+    assert_eq!(logger.get_max_log_bytes(), Some(1_048_576));
+    #[cfg(feature = "log_files")]
+    assert!(logger.get_prune_log_archives());
+    #[cfg(feature = "disk_space")]
+    assert_eq!(logger.get_min_free_space(), Some(4096));
+}
+
+#[test]
+fn test_configure_with_prefix_reports_invalid_values() {
+    env::set_var("PLT_INVALID_COLOR", "sometimes");
+    env::set_var("PLT_INVALID_LEVEL", "loud");
+
+    let logger = Logger::new();
+    logger.set_color(true);
+    logger.set_level(Level::Info);
+    let errors = configure_with_prefix(&logger, "PLT_INVALID_");
+
+    let names: Vec<&str> = errors.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["PLT_INVALID_COLOR", "PLT_INVALID_LEVEL"]);
+    assert_eq!(errors[0].value, "sometimes");
+    // Invalid values leave the settings alone
+    assert!(logger.get_color());
+    assert_eq!(logger.get_level(), Level::Info);
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_configure_with_prefix_reports_invalid_timestamp_format() {
+    env::set_var("PLT_TIME_TIMESTAMP_FORMAT", "%Q");
+
+    let logger = Logger::new();
+    let errors = configure_with_prefix(&logger, "PLT_TIME_");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].name, "PLT_TIME_TIMESTAMP_FORMAT");
+    assert_eq!(logger.get_timestamp_format(), None);
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_configure_with_prefix_reports_invalid_files() {
+    env::set_var("PLT_FILE_FILE", "this/path/is/not/real/file.log");
+    env::set_var("PLT_FILE_EXISTING_LOG_HANDLER", "shred");
+    env::set_var("PLT_FILE_LOG_DIRECTORY_MODE", "rwx");
+    env::set_var("PLT_FILE_MAX_LOG_BYTES", "1GB");
+
+    let logger = Logger::new();
+    let errors = configure_with_prefix(&logger, "PLT_FILE_");
+
+    let names: Vec<&str> = errors.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "PLT_FILE_EXISTING_LOG_HANDLER",
+            "PLT_FILE_LOG_DIRECTORY_MODE",
+            "PLT_FILE_MAX_LOG_BYTES",
+            "PLT_FILE_FILE"
+        ]
+    );
+    assert!(logger.get_log_path().is_none());
+}
+
+#[test]
+fn test_unset_variables_have_no_effect() {
+    let logger = Logger::new();
+    logger.set_color(false);
+
+    assert_eq!(configure_with_prefix(&logger, "PLT_UNSET_"), vec![]);
+    assert!(!logger.get_color());
+}
+
+#[cfg(feature = "config")]
+mod config_tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn config_file_with(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("could not create temp file");
+        file.write_all(contents.as_bytes())
+            .expect("could not write temp file");
+        file
+    }
+
+    #[test]
+    fn test_config_file_is_loaded() {
+        let file = config_file_with("level: Error\ncolor: false\n");
+        env::set_var("PLT_CONFIG_LOAD_CONFIG", file.path());
+
+        let logger = Logger::new();
+        assert_eq!(configure_with_prefix(&logger, "PLT_CONFIG_LOAD_"), vec![]);
+
+        assert_eq!(logger.get_level(), Level::Error);
+        assert!(!logger.get_color());
+    }
+
+    #[test]
+    fn test_variables_override_config_file() {
+        let file = config_file_with("level: Error\ncolor: false\n");
+        env::set_var("PLT_CONFIG_ORDER_CONFIG", file.path());
+        env::set_var("PLT_CONFIG_ORDER_LEVEL", "warn");
+
+        let logger = Logger::new();
+        assert_eq!(configure_with_prefix(&logger, "PLT_CONFIG_ORDER_"), vec![]);
+
+        assert_eq!(logger.get_level(), Level::Warn);
+        assert!(!logger.get_color());
+    }
+
+    #[test]
+    fn test_missing_config_file_does_not_stop_other_variables() {
+        env::set_var("PLT_CONFIG_MISSING_CONFIG", "this/path/is/not/real.yml");
+        env::set_var("PLT_CONFIG_MISSING_LEVEL", "error");
+
+        let logger = Logger::new();
+        assert_eq!(
+            configure_with_prefix(&logger, "PLT_CONFIG_MISSING_"),
+            vec![]
+        );
+
+        assert_eq!(logger.get_level(), Level::Error);
+    }
+}
+
+#[test]
+fn test_empty_rate_limit_removes_it() {
+    env::set_var("PLT_RATE_RATE_LIMIT", "");
+
+    let logger = Logger::new();
+    logger.set_rate_limit(Some("1/1s".parse().unwrap()));
+    let errors = configure_with_prefix(&logger, "PLT_RATE_");
+
+    assert_eq!(errors, vec![]);
+    assert_eq!(logger.get_rate_limit(), None);
+}
@@ -7,27 +7,76 @@ mod tests;
 #[cfg(feature = "config")]
 use serde::{Deserialize, Serialize};
 
-use std::fs::File;
-use std::io;
-use std::io::Write;
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, DirBuilder, File};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::SystemTime;
 
 /// The method of handling a pre-existing log file when starting a new session.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+///
+/// In config files and environment variables the names may be written in any
+/// case, with or without dashes or underscores, such as `fail_if_exists`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String")
+)]
 pub enum ExistingLogHandler {
-    /// Append to the existing log file.
+    /// Append to the existing log file. This is the default.
+    #[default]
     Append,
     /// Overwrite the existing log file.
     Overwrite,
-    /// Rename the existing log file with date and time appended to the name.
-    Rename
+    /// Rename the existing log file with date and time appended to the name,
+    /// optionally moving it to an archive directory. See
+    /// [`OpenSettings::archive_directory`].
+    Rename,
+    /// Refuse to log to the file if it already exists.
+    FailIfExists,
+    /// Never touch the file at the path itself. Instead each session writes
+    /// to a new numbered file next to it, such as `app.1.log`, then
+    /// `app.2.log`, and so on. The number is one more than the highest
+    /// number already in use.
+    NewNumbered,
+    /// Append to the existing log file, after a line that marks the start of
+    /// the session with the time, process ID, and executable name.
+    AppendWithSessionHeader
 }
 
-impl Default for ExistingLogHandler {
-    /// Append to the end of the last log.
-    fn default() -> Self {
-        ExistingLogHandler::Append
+impl FromStr for ExistingLogHandler {
+    type Err = String;
+
+    /// Parse the name of a handler, ignoring case, dashes and underscores.
+    /// For example `"rename"` or `"fail_if_exists"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+
+        match name.as_str() {
+            "append" => Ok(ExistingLogHandler::Append),
+            "overwrite" => Ok(ExistingLogHandler::Overwrite),
+            "rename" => Ok(ExistingLogHandler::Rename),
+            "failifexists" => Ok(ExistingLogHandler::FailIfExists),
+            "newnumbered" => Ok(ExistingLogHandler::NewNumbered),
+            "appendwithsessionheader" => Ok(ExistingLogHandler::AppendWithSessionHeader),
+            _ => Err(format!("Invalid existing log handler: {}", s))
+        }
+    }
+}
+
+impl TryFrom<String> for ExistingLogHandler {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
     }
 }
 
@@ -39,7 +88,23 @@ pub enum ExistingLogHandlerOpenError {
     Io(io::Error),
     /// The path could not be used as a log file. This could because the path
     /// is not a file.
-    InvalidPath
+    InvalidPath,
+    /// The file already exists, and the handler is
+    /// [`ExistingLogHandler::FailIfExists`].
+    AlreadyExists
+}
+
+/// A log file that has been opened by an [`ExistingLogHandler`].
+#[derive(Debug)]
+pub struct OpenedLogFile {
+    /// The file, ready to be written to.
+    pub file: File,
+    /// Where the file actually is. This is only different from the path that
+    /// was given for [`ExistingLogHandler::NewNumbered`].
+    pub path: PathBuf,
+    /// Where the existing file was moved to by
+    /// [`ExistingLogHandler::Rename`], if it was.
+    pub archived: Option<PathBuf>
 }
 
 impl From<io::Error> for ExistingLogHandlerOpenError {
@@ -48,80 +113,304 @@ impl From<io::Error> for ExistingLogHandlerOpenError {
     }
 }
 
+/// Everything other than the path that can change how a log file is opened.
+#[derive(Debug, Clone)]
+pub struct OpenSettings {
+    /// The current time, used for the names of renamed files.
+    pub now: SystemTime,
+    /// The directory that renamed files are moved to. If none, they stay in
+    /// the same directory as the log file. The directory must already exist.
+    pub archive_directory: Option<PathBuf>,
+    /// Whether the file is always opened for appending, so that every write
+    /// goes to the end of the file even when other processes write to it
+    /// too. A file that is overwritten is emptied first.
+    pub append_only: bool
+}
+
+impl Default for OpenSettings {
+    /// The current time, no archive directory, and not append only.
+    fn default() -> Self {
+        OpenSettings {
+            now: SystemTime::now(),
+            archive_directory: None,
+            append_only: false
+        }
+    }
+}
+
 impl ExistingLogHandler {
     /// Open a file at the given path, and using the defined method if the file
     /// already exists
     pub fn open_file<P: AsRef<Path>>(&self, path: P) -> Result<File, ExistingLogHandlerOpenError> {
-        // TODO: This method is a wee bit of a mess
-        match self {
-            ExistingLogHandler::Append => {
-                if path.as_ref().exists() {
-                    match File::options().append(true).open(path) {
-                        Ok(file) => Ok(file),
-                        Err(e) => Err(ExistingLogHandlerOpenError::Io(e))
-                    }
-                } else {
-                    match File::create(path) {
-                        Ok(file) => Ok(file),
-                        Err(e) => Err(ExistingLogHandlerOpenError::Io(e))
-                    }
+        Ok(self.open_file_with(path, &OpenSettings::default())?.file)
+    }
+
+    /// The same as [`open_file`](Self::open_file), but using the given time
+    /// as the current time, such as for the name of a renamed file.
+    pub fn open_file_at<P: AsRef<Path>>(
+        &self,
+        path: P,
+        now: SystemTime
+    ) -> Result<File, ExistingLogHandlerOpenError> {
+        let settings = OpenSettings {
+            now,
+            ..OpenSettings::default()
+        };
+        Ok(self.open_file_with(path, &settings)?.file)
+    }
+
+    /// The same as [`open_file`](Self::open_file), but with all the
+    /// settings given. Also returns where the file actually is, which may not
+    /// be the given path.
+    pub fn open_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        settings: &OpenSettings
+    ) -> Result<OpenedLogFile, ExistingLogHandlerOpenError> {
+        let path = path.as_ref();
+        let mut archived = None;
+
+        let file = match self {
+            ExistingLogHandler::Append => File::options().create(true).append(true).open(path)?,
+            ExistingLogHandler::Overwrite => create_empty(path, settings)?,
+            ExistingLogHandler::Rename => {
+                if path.exists() {
+                    archived = archive_file(path, settings)?;
                 }
+
+                create_empty(path, settings)?
             }
-            // TODO: Overwrite should handle new file creation.
-            ExistingLogHandler::Overwrite => match File::create(path) {
-                Ok(file) => Ok(file),
-                Err(e) => Err(ExistingLogHandlerOpenError::Io(e))
-            },
-            ExistingLogHandler::Rename => {
-                let path_buf = PathBuf::from(path.as_ref());
-                if path_buf.exists() {
-                    // Find the existing extension
-                    let existing_extension = match path_buf.extension() {
-                        Some(extension) => format!(".{}", extension.to_string_lossy()),
-                        None => String::new()
-                    };
-
-                    // Add the date before the extension
-                    let new_path = path_buf.with_extension(&format!(
-                        "{}{}",
-                        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
-                        existing_extension
-                    ));
-
-                    // Rename the file
-                    let mut new_file = match File::create(new_path.as_path()) {
-                        Ok(file) => file,
-                        Err(err) => return Err(ExistingLogHandlerOpenError::Io(err))
-                    };
-
-                    // Write the old file to the new file
-                    let old_file_content = match std::fs::read_to_string(path_buf.as_path()) {
-                        Ok(content) => content,
-                        Err(err) => return Err(ExistingLogHandlerOpenError::Io(err))
-                    };
-                    match new_file.write_all(old_file_content.as_bytes()) {
-                        Ok(_) => (),
-                        Err(err) => return Err(ExistingLogHandlerOpenError::Io(err))
-                    };
-
-                    // Erase the base log file and open it
-                    let res = File::options()
-                        .create(true)
-                        .write(true)
-                        .truncate(true)
-                        .open(path_buf.as_path());
-
-                    match res {
-                        Ok(file) => Ok(file),
-                        Err(err) => Err(ExistingLogHandlerOpenError::Io(err))
-                    }
-                } else {
-                    match File::create(path) {
-                        Ok(file) => Ok(file),
-                        Err(err) => Err(ExistingLogHandlerOpenError::Io(err))
-                    }
+            ExistingLogHandler::FailIfExists => match create_new_options(settings).open(path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    return Err(ExistingLogHandlerOpenError::AlreadyExists)
                 }
+                Err(e) => return Err(e.into())
+            },
+            ExistingLogHandler::NewNumbered => return open_new_numbered(path, settings),
+            ExistingLogHandler::AppendWithSessionHeader => {
+                let mut file = File::options().create(true).append(true).open(path)?;
+                file.write_all(session_header(settings.now).as_bytes())?;
+                file
+            }
+        };
+
+        Ok(OpenedLogFile {
+            file,
+            path: path.to_path_buf(),
+            archived
+        })
+    }
+}
+
+/// Create an empty file at the path, replacing any file that is there.
+fn create_empty(path: &Path, settings: &OpenSettings) -> io::Result<File> {
+    if settings.append_only {
+        // A file can not be truncated while it is opened for appending
+        let file = File::options().create(true).append(true).open(path)?;
+        file.set_len(0)?;
+        Ok(file)
+    } else {
+        File::create(path)
+    }
+}
+
+/// The options for creating a file that must not exist yet.
+fn create_new_options(settings: &OpenSettings) -> fs::OpenOptions {
+    let mut options = File::options();
+    if settings.append_only {
+        options.append(true);
+    } else {
+        options.write(true);
+    }
+    options.create_new(true);
+
+    options
+}
+
+/// Split a file name into the stem and the extension, including the dot. For
+/// example `server.log` is `("server", ".log")`.
+pub(crate) fn split_file_name(file_name: &OsStr) -> (String, String) {
+    let file_path = Path::new(file_name);
+    let stem = file_path
+        .file_stem()
+        .unwrap_or(file_name)
+        .to_string_lossy()
+        .to_string();
+    let extension = match file_path.extension() {
+        Some(extension) => format!(".{}", extension.to_string_lossy()),
+        None => String::new()
+    };
+
+    (stem, extension)
+}
+
+/// Create a new numbered log file, such as `app.3.log` for `app.log` when
+/// `app.1.log` and `app.2.log` already exist.
+///
+/// If another process creates the file with that number first, the next
+/// number is tried.
+fn open_new_numbered(
+    path: &Path,
+    settings: &OpenSettings
+) -> Result<OpenedLogFile, ExistingLogHandlerOpenError> {
+    let file_name = path
+        .file_name()
+        .ok_or(ExistingLogHandlerOpenError::InvalidPath)?;
+    let (stem, extension) = split_file_name(file_name);
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from(".")
+    };
+
+    let prefix = format!("{stem}.");
+    let highest = fs::read_dir(&directory)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_prefix(&prefix)?
+                .strip_suffix(&extension)?
+                .parse::<u64>()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut number = highest + 1;
+    loop {
+        let numbered_path = directory.join(format!("{stem}.{number}{extension}"));
+        match create_new_options(settings).open(&numbered_path) {
+            Ok(file) => {
+                return Ok(OpenedLogFile {
+                    file,
+                    path: numbered_path,
+                    archived: None
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => number += 1,
+            Err(e) => return Err(e.into())
+        }
+    }
+}
+
+/// Parse a Unix permission mode for created directories, written in octal
+/// with or without a leading `0o`, such as `750` or `0o750`.
+pub fn parse_directory_mode(value: &str) -> Result<u32, String> {
+    let value = value.trim();
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if !digits.is_empty() && mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("Invalid directory mode: {}", value))
+    }
+}
+
+/// Create a directory and all of its missing parents. On Unix the created
+/// directories get the given permission mode, before the umask is applied.
+/// Without a mode, or on other platforms, the default permissions are used.
+pub(crate) fn create_directories(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    builder.create(path)
+}
+
+/// The line written at the start of a session by
+/// [`ExistingLogHandler::AppendWithSessionHeader`].
+fn session_header(now: SystemTime) -> String {
+    let executable = env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    format!(
+        "===== Session started {} | pid {} | {} =====\n",
+        chrono::DateTime::<chrono::Local>::from(now)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        process::id(),
+        executable
+    )
+}
+
+/// Move an existing log file out of the way, to a name such as
+/// `server.2022-03-12_18-14-09.log`, without reading it into memory. Returns
+/// where it was moved to, or none if another process moved it away first.
+///
+/// If that name is already taken, for example by another session that started
+/// in the same second, a number is added: `server.2022-03-12_18-14-09.1.log`,
+/// then `.2`, and so on. Each name is claimed atomically, so two processes
+/// can never move their files to the same name.
+fn archive_file(
+    path: &Path,
+    settings: &OpenSettings
+) -> Result<Option<PathBuf>, ExistingLogHandlerOpenError> {
+    let file_name = path
+        .file_name()
+        .ok_or(ExistingLogHandlerOpenError::InvalidPath)?;
+    let directory = match settings.archive_directory {
+        Some(ref directory) => directory.clone(),
+        None => path.parent().map(Path::to_path_buf).unwrap_or_default()
+    };
+    let (stem, extension) = split_file_name(file_name);
+    let stamp = chrono::DateTime::<chrono::Local>::from(settings.now).format("%Y-%m-%d_%H-%M-%S");
+
+    let mut candidate = directory.join(format!("{stem}.{stamp}{extension}"));
+    let mut number = 1;
+    loop {
+        match move_file(path, &candidate, |from, to| fs::rename(from, to)) {
+            Ok(true) => return Ok(Some(candidate)),
+            Ok(false) => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                candidate = directory.join(format!("{stem}.{stamp}.{number}{extension}"));
+                number += 1;
             }
+            Err(e) => return Err(e.into())
         }
     }
 }
+
+/// Move a file to a path that must not exist yet, without reading it into
+/// memory. Fails with [`io::ErrorKind::AlreadyExists`] if the path is taken.
+/// Returns false if the file was no longer there to be moved.
+///
+/// The path is claimed first by creating an empty file there, which fails
+/// instead of replacing a file, and the file is then renamed over it with
+/// `rename`. If renaming fails, such as between file systems or on ones that
+/// can not rename over a file, the file is copied into the claimed file and
+/// then removed instead.
+fn move_file<R>(from: &Path, to: &Path, rename: R) -> io::Result<bool>
+where
+    R: Fn(&Path, &Path) -> io::Result<()>
+{
+    let mut target = File::options().write(true).create_new(true).open(to)?;
+
+    let copied = match rename(from, to) {
+        Ok(()) => return Ok(true),
+        // Another process moved the same file away at the same time
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(e),
+        Err(_) => File::open(from).and_then(|mut source| io::copy(&mut source, &mut target))
+    };
+    if let Err(e) = copied {
+        drop(target);
+        let _ = fs::remove_file(to);
+        return match e.kind() {
+            io::ErrorKind::NotFound => Ok(false),
+            _ => Err(e)
+        };
+    }
+
+    match fs::remove_file(from) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+        result => result.map(|()| true)
+    }
+}
@@ -1,4 +1,6 @@
 use super::*;
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
 use tempfile::tempdir;
 
 #[test]
@@ -21,7 +23,7 @@ fn test_append() -> Result<(), ExistingLogHandlerOpenError> {
     drop(file);
 
     // Check the file contents
-    let actual_len = std::fs::read_to_string(log_path)?.as_bytes().len();
+    let actual_len = std::fs::read_to_string(log_path)?.len();
     assert_eq!(expected_len, actual_len);
 
     Ok(())
@@ -47,7 +49,7 @@ fn test_overwrite() -> Result<(), ExistingLogHandlerOpenError> {
     drop(file);
 
     // Check the file contents
-    let actual_len = std::fs::read_to_string(log_path)?.as_bytes().len();
+    let actual_len = std::fs::read_to_string(log_path)?.len();
     assert_eq!(expected_len, actual_len);
 
     Ok(())
@@ -74,7 +76,7 @@ fn test_rename() -> Result<(), ExistingLogHandlerOpenError> {
 
     // The file should not be overwritten and only have the new message
     let actual_text = std::fs::read_to_string(log_path)?;
-    let actual_len = actual_text.as_bytes().len();
+    let actual_len = actual_text.len();
     assert_eq!(expected_len, actual_len, "actual_text: {:?}", actual_text);
 
     // There should be a new file with the original name with a date/time stamp
@@ -94,3 +96,360 @@ fn test_rename() -> Result<(), ExistingLogHandlerOpenError> {
 
     Ok(())
 }
+
+#[test]
+fn test_from_str() {
+    assert_eq!(Ok(ExistingLogHandler::Append), "append".parse());
+    assert_eq!(Ok(ExistingLogHandler::Overwrite), "Overwrite".parse());
+    assert_eq!(Ok(ExistingLogHandler::Rename), "RENAME".parse());
+    assert_eq!(
+        Ok(ExistingLogHandler::FailIfExists),
+        "fail_if_exists".parse()
+    );
+    assert_eq!(Ok(ExistingLogHandler::NewNumbered), "new-numbered".parse());
+    assert_eq!(
+        Ok(ExistingLogHandler::AppendWithSessionHeader),
+        "AppendWithSessionHeader".parse()
+    );
+    assert!("".parse::<ExistingLogHandler>().is_err());
+    assert!("shred".parse::<ExistingLogHandler>().is_err());
+}
+
+#[test]
+fn test_rename_uses_the_given_time() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "old session")?;
+    let now = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+
+    drop(ExistingLogHandler::Rename.open_file_at(log_path.as_path(), now)?);
+
+    let stamp = chrono::DateTime::<chrono::Local>::from(now).format("%Y-%m-%d_%H-%M-%S");
+    let renamed_path = dir.path().join(format!("test.{stamp}.log"));
+    assert_eq!("old session", std::fs::read_to_string(renamed_path)?);
+    assert_eq!("", std::fs::read_to_string(log_path)?);
+
+    Ok(())
+}
+
+/// The name a renamed `test.log` gets at the given time, without a number.
+fn renamed_name(now: SystemTime) -> String {
+    let stamp = chrono::DateTime::<chrono::Local>::from(now).format("%Y-%m-%d_%H-%M-%S");
+    format!("test.{stamp}.log")
+}
+
+#[test]
+fn test_rename_keeps_non_utf8_content() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    let content = [0xff, 0xfe, 0x00, b'\n', 0x80];
+    std::fs::write(&log_path, content)?;
+    let now = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+
+    drop(ExistingLogHandler::Rename.open_file_at(log_path.as_path(), now)?);
+
+    let renamed = std::fs::read(dir.path().join(renamed_name(now)))?;
+    assert_eq!(renamed, content);
+
+    Ok(())
+}
+
+#[test]
+fn test_rename_adds_numbers_for_the_same_second() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    let now = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+
+    for session in 0..4 {
+        let mut file = ExistingLogHandler::Rename.open_file_at(log_path.as_path(), now)?;
+        write!(file, "session {session}")?;
+    }
+
+    let name = renamed_name(now);
+    let numbered = |n: u32| name.replace(".log", &format!(".{n}.log"));
+    assert_eq!(
+        "session 0",
+        std::fs::read_to_string(dir.path().join(&name))?
+    );
+    assert_eq!(
+        "session 1",
+        std::fs::read_to_string(dir.path().join(numbered(1)))?
+    );
+    assert_eq!(
+        "session 2",
+        std::fs::read_to_string(dir.path().join(numbered(2)))?
+    );
+    assert_eq!("session 3", std::fs::read_to_string(log_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_rename_to_archive_directory() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let archive = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "old session")?;
+    let settings = OpenSettings {
+        now: UNIX_EPOCH + Duration::from_secs(1_234_567_890),
+        archive_directory: Some(archive.path().to_path_buf()),
+        ..OpenSettings::default()
+    };
+
+    let opened = ExistingLogHandler::Rename.open_file_with(log_path.as_path(), &settings)?;
+
+    let archived = archive.path().join(renamed_name(settings.now));
+    assert_eq!(opened.archived, Some(archived.clone()));
+    assert_eq!("old session", std::fs::read_to_string(archived)?);
+    assert_eq!("", std::fs::read_to_string(&log_path)?);
+    assert_eq!(1, std::fs::read_dir(dir.path())?.count());
+
+    Ok(())
+}
+
+#[test]
+fn test_rename_to_missing_archive_directory_fails() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "old session")?;
+    let settings = OpenSettings {
+        archive_directory: Some(dir.path().join("missing")),
+        ..OpenSettings::default()
+    };
+
+    let result = ExistingLogHandler::Rename.open_file_with(log_path.as_path(), &settings);
+
+    assert!(matches!(result, Err(ExistingLogHandlerOpenError::Io(_))));
+    // The existing log is left where it was
+    assert_eq!("old session", std::fs::read_to_string(&log_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_fail_if_exists() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+
+    let mut file = ExistingLogHandler::FailIfExists.open_file(log_path.as_path())?;
+    file.write_all(b"first session")?;
+    drop(file);
+
+    let result = ExistingLogHandler::FailIfExists.open_file(log_path.as_path());
+    assert!(
+        matches!(result, Err(ExistingLogHandlerOpenError::AlreadyExists)),
+        "{:?}",
+        result
+    );
+    assert_eq!("first session", std::fs::read_to_string(log_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_new_numbered() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "not numbered")?;
+
+    let first =
+        ExistingLogHandler::NewNumbered.open_file_with(&log_path, &OpenSettings::default())?;
+    assert_eq!(first.path, dir.path().join("test.1.log"));
+    drop(first);
+
+    // Gaps are not filled, the number is always one more than the highest
+    std::fs::write(dir.path().join("test.5.log"), "")?;
+    std::fs::write(dir.path().join("test.notanumber.log"), "")?;
+    let next =
+        ExistingLogHandler::NewNumbered.open_file_with(&log_path, &OpenSettings::default())?;
+    assert_eq!(next.path, dir.path().join("test.6.log"));
+    assert!(next.path.exists());
+
+    assert_eq!("not numbered", std::fs::read_to_string(log_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_append_with_session_header() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "old session\n")?;
+    let now = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+
+    let mut file = ExistingLogHandler::AppendWithSessionHeader.open_file_at(&log_path, now)?;
+    file.write_all(b"new session\n")?;
+    drop(file);
+
+    let contents = std::fs::read_to_string(log_path)?;
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 3, "{:?}", contents);
+    assert_eq!(lines[0], "old session");
+    assert_eq!(lines[2], "new session");
+
+    let time = chrono::DateTime::<chrono::Local>::from(now)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+    let expected_start = format!(
+        "===== Session started {} | pid {} | ",
+        time,
+        std::process::id()
+    );
+    assert!(lines[1].starts_with(&expected_start), "{:?}", lines[1]);
+    assert!(lines[1].ends_with(" ====="), "{:?}", lines[1]);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_directory_mode() {
+    assert_eq!(parse_directory_mode("750"), Ok(0o750));
+    assert_eq!(parse_directory_mode("0750"), Ok(0o750));
+    assert_eq!(parse_directory_mode("0o700"), Ok(0o700));
+    assert!(parse_directory_mode("").is_err());
+    assert!(parse_directory_mode("0o").is_err());
+    assert!(parse_directory_mode("789").is_err());
+    assert!(parse_directory_mode("17777").is_err());
+}
+
+#[test]
+fn test_move_file_never_replaces() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let from = dir.path().join("from.log");
+    let to = dir.path().join("to.log");
+    std::fs::write(&from, "moving")?;
+    std::fs::write(&to, "already there")?;
+
+    let error = move_file(&from, &to, |from, to| std::fs::rename(from, to)).unwrap_err();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!("moving", std::fs::read_to_string(&from)?);
+    assert_eq!("already there", std::fs::read_to_string(&to)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_file_copies_when_renaming_fails() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let from = dir.path().join("from.log");
+    let to = dir.path().join("to.log");
+    std::fs::write(&from, "moving")?;
+    // Like renaming between file systems
+    let rename = |_: &Path, _: &Path| Err(std::io::Error::from_raw_os_error(18));
+
+    assert!(move_file(&from, &to, rename)?);
+
+    assert!(!from.exists());
+    assert_eq!("moving", std::fs::read_to_string(&to)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_file_of_a_missing_file_claims_nothing() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let from = dir.path().join("from.log");
+    let to = dir.path().join("to.log");
+
+    assert!(!move_file(&from, &to, |from, to| std::fs::rename(
+        from, to
+    ))?);
+    assert!(!to.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_renames_of_one_log_archive_it_once() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    std::fs::write(&log_path, "old session")?;
+    let sessions = 8;
+    let barrier = std::sync::Barrier::new(sessions);
+
+    std::thread::scope(|scope| {
+        for _ in 0..sessions {
+            scope.spawn(|| {
+                barrier.wait();
+                ExistingLogHandler::Rename
+                    .open_file_with(&log_path, &OpenSettings::default())
+                    .unwrap();
+            });
+        }
+    });
+
+    let copies = std::fs::read_dir(dir.path())?
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .filter(|contents| contents == "old session")
+        .count();
+    assert_eq!(copies, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_renames_keep_every_log() -> Result<(), ExistingLogHandlerOpenError> {
+    let archive = tempdir()?;
+    let now = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+    let sessions = 8;
+    let dirs: Vec<_> = (0..sessions).map(|_| tempdir()).collect::<Result<_, _>>()?;
+    let barrier = std::sync::Barrier::new(sessions);
+
+    std::thread::scope(|scope| {
+        for (session, dir) in dirs.iter().enumerate() {
+            let barrier = &barrier;
+            let archive = archive.path().to_path_buf();
+            scope.spawn(move || {
+                let log_path = dir.path().join("test.log");
+                std::fs::write(&log_path, format!("session {session}")).unwrap();
+                let settings = OpenSettings {
+                    now,
+                    archive_directory: Some(archive),
+                    ..OpenSettings::default()
+                };
+                barrier.wait();
+                ExistingLogHandler::Rename
+                    .open_file_with(&log_path, &settings)
+                    .unwrap();
+            });
+        }
+    });
+
+    let mut archived: Vec<String> = std::fs::read_dir(archive.path())?
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    archived.sort();
+    let expected: Vec<String> = (0..sessions).map(|s| format!("session {s}")).collect();
+    assert_eq!(archived, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_new_numbered_files_are_all_new() -> Result<(), ExistingLogHandlerOpenError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join("test.log");
+    let sessions = 8;
+    let barrier = std::sync::Barrier::new(sessions);
+
+    let mut paths: Vec<PathBuf> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..sessions)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    ExistingLogHandler::NewNumbered
+                        .open_file_with(&log_path, &OpenSettings::default())
+                        .unwrap()
+                        .path
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    paths.sort();
+    paths.dedup();
+
+    assert_eq!(paths.len(), sessions);
+
+    Ok(())
+}
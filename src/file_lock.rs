@@ -0,0 +1,38 @@
+//! Advisory locking of the log file, so that processes sharing a log file
+//! take turns writing to it.
+//!
+//! This module is not included unless the `file_lock` feature is enabled,
+//! and only on Unix.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+/// An exclusive `flock` on a file, which is unlocked when this is dropped.
+#[derive(Debug)]
+pub(crate) struct FileLock<'a> {
+    file: &'a File
+}
+
+impl<'a> FileLock<'a> {
+    /// Wait until the file can be locked, then lock it.
+    pub(crate) fn lock(file: &'a File) -> io::Result<FileLock<'a>> {
+        // Safety: the file descriptor is valid for as long as the file is
+        // borrowed.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(FileLock { file })
+    }
+}
+
+impl Drop for FileLock<'_> {
+    fn drop(&mut self) {
+        // Safety: see `lock`. The lock is also released when the file is
+        // closed, so an error here is not a problem.
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! This module contains [`FileOutput`], which describes an extra log file
+//! that only gets some levels of messages, such as an `errors.log` next to
+//! the full log.
+//!
+//! ```no_run
+//! use pokey_logger::file_output::FileOutput;
+//! use pokey_logger::{Level, LOGGER};
+//!
+//! LOGGER.set_log_path("log/server.log").unwrap();
+//! LOGGER
+//!     .add_log_file(FileOutput::new("log/errors.log").min_level(Level::Error))
+//!     .unwrap();
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::existing_log_handler::{ExistingLogHandler, ExistingLogHandlerOpenError, OpenSettings};
+use crate::flush_policy::FlushState;
+use crate::{file_id, reopen_log_file, FileId, Level};
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// An extra log file, with its own path, existing log handler, range of
+/// levels and color setting. Add it to a logger with
+/// [`Logger::add_log_file`](crate::Logger::add_log_file).
+///
+/// Messages are still filtered by the level of the logger first. Each file is
+/// written to like the log file, with the flush policy, syncing of errors,
+/// atomic appends, file locking and write error policy of the logger, and is
+/// reopened along with it.
+///
+/// In a config file, extra files are a list under `log_files`:
+/// ```yaml
+/// log_files:
+///   - path: "./log/errors.log"
+///     existing_log_handler: Append
+///     min_level: Error
+///   - path: "./log/debug.log"
+///     max_level: Debug
+///     color: true
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct FileOutput {
+    /// Where the file is.
+    pub path: PathBuf,
+    /// How the file is handled if it already exists.
+    #[cfg_attr(feature = "config", serde(default = "ExistingLogHandler::default"))]
+    pub existing_log_handler: ExistingLogHandler,
+    /// The lowest level of messages written to the file.
+    #[cfg_attr(feature = "config", serde(default = "default_min_level"))]
+    pub min_level: Level,
+    /// The highest level of messages written to the file.
+    #[cfg_attr(feature = "config", serde(default = "default_max_level"))]
+    pub max_level: Level,
+    /// Whether or not to write color codes to the file.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub color: bool
+}
+
+impl FileOutput {
+    /// A file at the given path that gets messages of every level, without
+    /// color, and is appended to if it exists.
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileOutput {
+        FileOutput {
+            path: path.into(),
+            existing_log_handler: ExistingLogHandler::default(),
+            min_level: default_min_level(),
+            max_level: default_max_level(),
+            color: false
+        }
+    }
+
+    /// Set how the file is handled if it already exists.
+    pub fn existing_log_handler(mut self, handler: ExistingLogHandler) -> FileOutput {
+        self.existing_log_handler = handler;
+        self
+    }
+
+    /// Set the lowest level of messages written to the file.
+    pub fn min_level(mut self, level: Level) -> FileOutput {
+        self.min_level = level;
+        self
+    }
+
+    /// Set the highest level of messages written to the file.
+    pub fn max_level(mut self, level: Level) -> FileOutput {
+        self.max_level = level;
+        self
+    }
+
+    /// Set whether or not to write color codes to the file.
+    pub fn color(mut self, color: bool) -> FileOutput {
+        self.color = color;
+        self
+    }
+
+    /// Whether or not messages of the level are written to the file.
+    pub fn accepts(&self, level: Level) -> bool {
+        level >= self.min_level && level <= self.max_level
+    }
+}
+
+fn default_min_level() -> Level {
+    Level::Debug
+}
+
+fn default_max_level() -> Level {
+    Level::Error
+}
+
+/// A [`FileOutput`] that has been opened and is being written to.
+///
+/// It is written to, reopened and recovered by the logger the same way as the
+/// main log file.
+#[derive(Debug)]
+pub(crate) struct OpenFileOutput {
+    pub(crate) output: FileOutput,
+    /// Where the file actually is, which is only different from the path of
+    /// the output for [`ExistingLogHandler::NewNumbered`].
+    pub(crate) path: PathBuf,
+    /// None while writing to the file is failing.
+    pub(crate) writer: Option<BufWriter<File>>,
+    /// The device and inode of the file being written to.
+    pub(crate) id: Option<FileId>,
+    pub(crate) flush_state: FlushState,
+    /// Messages kept in memory while writing to the file is failing.
+    pub(crate) outage: Option<Vec<u8>>
+}
+
+impl OpenFileOutput {
+    /// Open the file with its existing log handler.
+    pub(crate) fn open(
+        output: FileOutput,
+        settings: &OpenSettings
+    ) -> Result<OpenFileOutput, ExistingLogHandlerOpenError> {
+        let opened = output
+            .existing_log_handler
+            .open_file_with(&output.path, settings)?;
+
+        Ok(OpenFileOutput {
+            output,
+            path: opened.path,
+            id: file_id(opened.file.metadata()),
+            writer: Some(BufWriter::new(opened.file)),
+            flush_state: FlushState::new(settings.now),
+            outage: None
+        })
+    }
+
+    /// Whether or not the file at the path is no longer the file being
+    /// written to, because it was moved, deleted, or replaced.
+    pub(crate) fn changed(&self) -> bool {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => self.id.is_some() && self.id != file_id(Ok(metadata)),
+            Err(_) => self.writer.is_some()
+        }
+    }
+
+    /// Flush the file, then open the file at the same path again, appending
+    /// to it. See [`Logger::reopen`](crate::Logger::reopen).
+    ///
+    /// Nothing is done while writing is failing, as the file is opened again
+    /// with the next message anyway.
+    pub(crate) fn reopen(&mut self) -> io::Result<()> {
+        let old = match self.writer.as_mut() {
+            Some(old) => old,
+            None => return Ok(())
+        };
+        let (writer, id) = reopen_log_file(&self.path, &[])?;
+        let flushed = old.flush();
+        self.writer = Some(writer);
+        self.id = id;
+
+        flushed
+    }
+
+    /// Write everything that is buffered to the file.
+    pub(crate) fn flush(&mut self, now: SystemTime) -> io::Result<()> {
+        self.flush_state.flushed(now);
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(())
+        }
+    }
+}
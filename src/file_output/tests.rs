@@ -0,0 +1,50 @@
+use super::*;
+
+#[test]
+fn test_defaults() {
+    let output = FileOutput::new("log/all.log");
+
+    assert_eq!(PathBuf::from("log/all.log"), output.path);
+    assert_eq!(ExistingLogHandler::Append, output.existing_log_handler);
+    assert_eq!(Level::Debug, output.min_level);
+    assert_eq!(Level::Error, output.max_level);
+    assert!(!output.color);
+}
+
+#[test]
+fn test_accepts() {
+    let errors = FileOutput::new("errors.log").min_level(Level::Error);
+    assert!(!errors.accepts(Level::Warn));
+    assert!(errors.accepts(Level::Error));
+
+    let debug = FileOutput::new("debug.log").max_level(Level::Debug);
+    assert!(debug.accepts(Level::Debug));
+    assert!(!debug.accepts(Level::Info));
+
+    let middle = FileOutput::new("middle.log")
+        .min_level(Level::Info)
+        .max_level(Level::Warn);
+    assert!(!middle.accepts(Level::Debug));
+    assert!(middle.accepts(Level::Info));
+    assert!(middle.accepts(Level::Warn));
+    assert!(!middle.accepts(Level::Error));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_deserialize() {
+    let outputs: Vec<FileOutput> = serde_yaml::from_str(
+        "- path: errors.log\n  min_level: Error\n  existing_log_handler: Overwrite\n- path: all.log\n  color: true\n"
+    )
+    .unwrap();
+
+    assert_eq!(
+        vec![
+            FileOutput::new("errors.log")
+                .min_level(Level::Error)
+                .existing_log_handler(ExistingLogHandler::Overwrite),
+            FileOutput::new("all.log").color(true)
+        ],
+        outputs
+    );
+}
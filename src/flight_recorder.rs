@@ -0,0 +1,149 @@
+//! This module contains [`FlightRecorder`], which keeps the last messages in
+//! memory, including ones below the level of the logger, and writes them out
+//! when something goes wrong.
+//!
+//! ```
+//! use pokey_logger::flight_recorder::{DumpTarget, FlightRecorder};
+//! use pokey_logger::{Level, Logger};
+//!
+//! let logger = Logger::new();
+//! logger.set_level(Level::Warn);
+//! logger.set_flight_recorder(Some(
+//!     FlightRecorder::new(500).dump_to(DumpTarget::Stderr)
+//! ));
+//!
+//! // Not printed, but kept
+//! logger.debug("connecting to the database");
+//! assert_eq!(logger.flight_recorder_snapshot().len(), 1);
+//!
+//! // Printed, and then the debug message above is dumped to stderr
+//! logger.error("could not connect");
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::sink::Record;
+use crate::Level;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Where the messages of a flight recorder are written when it is dumped.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub enum DumpTarget {
+    /// The log file of the logger, or stderr if there is no log file.
+    #[default]
+    LogFile,
+    /// Standard error.
+    Stderr,
+    /// A file of its own, which is appended to.
+    File(PathBuf)
+}
+
+/// Settings for keeping the last messages in memory, which is turned on with
+/// [`Logger::set_flight_recorder`](crate::Logger::set_flight_recorder).
+///
+/// Every message is kept, whatever the level of the logger, until there are
+/// `capacity` of them, and then the oldest are forgotten. When a message at
+/// the `trigger` level or above is logged, or the [`PanicHook`] sees a panic,
+/// the kept messages are written to `dump_to` and forgotten.
+///
+/// [`PanicHook`]: crate::PanicHook
+///
+/// In a config file:
+/// ```yaml
+/// flight_recorder:
+///   capacity: 500
+///   trigger: Error
+///   dump_to:
+///     File: "./log/crash.log"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct FlightRecorder {
+    /// The most messages kept.
+    pub capacity: usize,
+    /// The lowest level of messages that cause a dump. `None` never causes
+    /// one, so the messages are only dumped on a panic or by
+    /// [`Logger::dump_flight_recorder`](crate::Logger::dump_flight_recorder).
+    #[cfg_attr(feature = "config", serde(default = "default_trigger"))]
+    pub trigger: Level,
+    /// Where the messages are written.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub dump_to: DumpTarget
+}
+
+impl FlightRecorder {
+    /// Keep the given number of messages, and dump them to the log file
+    /// when an error is logged.
+    pub fn new(capacity: usize) -> FlightRecorder {
+        FlightRecorder {
+            capacity,
+            trigger: default_trigger(),
+            dump_to: DumpTarget::default()
+        }
+    }
+
+    /// Set the lowest level of messages that cause a dump.
+    pub fn trigger(mut self, level: Level) -> FlightRecorder {
+        self.trigger = level;
+        self
+    }
+
+    /// Set where the messages are written.
+    pub fn dump_to(mut self, target: DumpTarget) -> FlightRecorder {
+        self.dump_to = target;
+        self
+    }
+}
+
+fn default_trigger() -> Level {
+    Level::Error
+}
+
+/// A [`FlightRecorder`] that is keeping messages.
+#[derive(Debug)]
+pub(crate) struct Recording {
+    pub(crate) settings: FlightRecorder,
+    records: VecDeque<Record>
+}
+
+impl Recording {
+    pub(crate) fn new(settings: FlightRecorder) -> Recording {
+        Recording {
+            records: VecDeque::with_capacity(settings.capacity.min(1024)),
+            settings
+        }
+    }
+
+    /// Keep a record, forgetting the oldest if there are too many. If the
+    /// record triggers a dump, all the records are taken and given back,
+    /// this one last.
+    pub(crate) fn record(&mut self, record: &Record) -> Option<Vec<Record>> {
+        if self.settings.capacity > 0 {
+            if self.records.len() == self.settings.capacity {
+                self.records.pop_front();
+            }
+            self.records.push_back(record.clone());
+        }
+
+        if self.settings.trigger != Level::None && record.level >= self.settings.trigger {
+            Some(self.take())
+        } else {
+            None
+        }
+    }
+
+    /// A copy of the records kept, oldest first.
+    pub(crate) fn snapshot(&self) -> Vec<Record> {
+        self.records.iter().cloned().collect()
+    }
+
+    /// Take all the records kept, oldest first.
+    pub(crate) fn take(&mut self) -> Vec<Record> {
+        self.records.drain(..).collect()
+    }
+}
@@ -0,0 +1,195 @@
+use super::*;
+use crate::Logger;
+use std::time::SystemTime;
+
+fn record(level: Level, message: &str) -> Record {
+    Record::new(level, message, SystemTime::now())
+}
+
+fn messages(records: &[Record]) -> Vec<&str> {
+    records
+        .iter()
+        .map(|record| record.message.as_str())
+        .collect()
+}
+
+#[test]
+fn test_keeps_the_last_records() {
+    let mut recording = Recording::new(FlightRecorder::new(2));
+
+    assert!(recording.record(&record(Level::Debug, "one")).is_none());
+    assert!(recording.record(&record(Level::Info, "two")).is_none());
+    assert!(recording.record(&record(Level::Warn, "three")).is_none());
+
+    assert_eq!(messages(&recording.snapshot()), ["two", "three"]);
+    // A snapshot does not take the records
+    assert_eq!(recording.snapshot().len(), 2);
+}
+
+#[test]
+fn test_trigger_takes_the_records() {
+    let mut recording = Recording::new(FlightRecorder::new(10).trigger(Level::Warn));
+
+    recording.record(&record(Level::Debug, "one"));
+    let dump = recording.record(&record(Level::Warn, "two")).unwrap();
+
+    assert_eq!(messages(&dump), ["one", "two"]);
+    assert!(recording.snapshot().is_empty());
+    assert!(recording.record(&record(Level::Error, "three")).is_some());
+}
+
+#[test]
+fn test_trigger_none_never_dumps() {
+    let mut recording = Recording::new(FlightRecorder::new(10).trigger(Level::None));
+
+    assert!(recording.record(&record(Level::Error, "one")).is_none());
+    assert_eq!(messages(&recording.take()), ["one"]);
+    assert!(recording.take().is_empty());
+}
+
+#[test]
+fn test_logger_keeps_messages_below_the_level() {
+    let logger = Logger::new();
+    logger.set_level(Level::Warn);
+    let capture = logger.capture();
+    logger.set_flight_recorder(Some(FlightRecorder::new(10).trigger(Level::None)));
+
+    logger.debug("debug");
+    logger.info("info");
+    logger.warn("warn");
+
+    assert_eq!(
+        messages(&logger.flight_recorder_snapshot()),
+        ["debug", "info", "warn"]
+    );
+    assert_eq!(capture.len(), 1);
+    assert_eq!(
+        logger.get_flight_recorder(),
+        Some(FlightRecorder::new(10).trigger(Level::None))
+    );
+
+    logger.set_flight_recorder(None);
+    logger.debug("debug");
+    assert!(logger.flight_recorder_snapshot().is_empty());
+    assert_eq!(logger.get_flight_recorder(), None);
+}
+
+#[test]
+fn test_logger_dumps_on_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("crash.log");
+    let logger = Logger::new();
+    logger.set_level(Level::Info);
+    logger.set_flight_recorder(Some(
+        FlightRecorder::new(10).dump_to(DumpTarget::File(path.clone()))
+    ));
+
+    logger.debug("connecting");
+    logger.info("connected");
+    assert!(!path.exists());
+    logger.error("connection lost");
+
+    let dump = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 5, "{dump}");
+    assert_eq!(lines[0], "----- flight recorder: last 3 messages -----");
+    assert!(lines[1].ends_with("[DEBUG] connecting"), "{dump}");
+    assert!(lines[2].ends_with("[INFO] connected"), "{dump}");
+    assert!(lines[3].ends_with("[ERROR] connection lost"), "{dump}");
+    assert_eq!(lines[4], "----- end of flight recorder -----");
+    assert!(logger.flight_recorder_snapshot().is_empty());
+}
+
+#[test]
+fn test_logger_dumps_on_demand() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("crash.log");
+    let logger = Logger::new();
+    logger.set_flight_recorder(Some(
+        FlightRecorder::new(10)
+            .trigger(Level::None)
+            .dump_to(DumpTarget::File(path.clone()))
+    ));
+
+    // Nothing kept, nothing written
+    logger.dump_flight_recorder().unwrap();
+    assert!(!path.exists());
+
+    logger.error("not a trigger");
+    logger.dump_flight_recorder().unwrap();
+
+    let dump = std::fs::read_to_string(&path).unwrap();
+    assert!(dump.contains("[ERROR] not a trigger\n"), "{dump}");
+    assert!(logger.flight_recorder_snapshot().is_empty());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_logger_dumps_to_the_log_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let logger = Logger::new();
+    logger.set_level(Level::Error);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.set_flight_recorder(Some(FlightRecorder::new(10)));
+
+    logger.debug("hidden");
+    logger.error("broken");
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 5, "{log}");
+    // The error is logged as usual, then the dump follows
+    assert!(lines[0].ends_with("[ERROR] broken"), "{log}");
+    assert!(lines[2].ends_with("[DEBUG] hidden"), "{log}");
+    assert!(lines[3].ends_with("[ERROR] broken"), "{log}");
+}
+
+#[test]
+fn test_logger_logs_a_failed_dump() {
+    let dir = tempfile::tempdir().unwrap();
+    let logger = Logger::new();
+    let capture = logger.capture();
+    logger.set_flight_recorder(Some(
+        FlightRecorder::new(10).dump_to(DumpTarget::File(dir.path().join("missing/crash.log")))
+    ));
+
+    logger.error("connection lost");
+
+    assert_eq!(capture.len(), 2);
+    assert!(capture.contains(Level::Error, "connection lost"));
+    let failure = &capture.records()[1];
+    assert_eq!(failure.level, Level::Error);
+    assert!(
+        failure
+            .message
+            .starts_with("Could not dump the flight recorder: "),
+        "{}",
+        failure.message
+    );
+    assert!(logger.flight_recorder_snapshot().is_empty());
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_dump_to_the_log_file_keeps_to_the_max_log_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let logger = Logger::new();
+    logger.set_color(false);
+    logger.set_level(Level::Error);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.set_max_log_bytes(Some(300));
+    logger.set_flight_recorder(Some(FlightRecorder::new(10)));
+
+    for _ in 0..5 {
+        logger.debug(&"x".repeat(100));
+    }
+    logger.error("broken");
+    logger.flush().unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(log.len() <= 300, "{log}");
+    assert!(log.contains("[ERROR] broken\n"), "{log}");
+    assert!(!log.contains("flight recorder"), "{log}");
+}
@@ -0,0 +1,157 @@
+//! This module contains the [`FlushPolicy`], which decides when messages
+//! written to the log file are flushed to it, rather than left in memory.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{parse_duration, Level};
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// When messages written to the log file are flushed.
+///
+/// Messages are buffered before they are written to the file. Anything still
+/// in the buffer is lost if the program crashes, so flushing more often keeps
+/// more of the last messages, at the cost of more writes.
+///
+/// As a string, such as in config files and environment variables, the
+/// policies are written as `never`, `every_message`, `level:warn`, `every:10`,
+/// and `interval:5s` or `interval:250ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub enum FlushPolicy {
+    /// Only flush when the buffer is full, or when asked to. This is the
+    /// default.
+    #[default]
+    Never,
+    /// Flush after every message.
+    EveryMessage,
+    /// Flush after messages at or above the level, which also flushes the
+    /// messages before them.
+    AtLevel(Level),
+    /// Flush after every given number of messages.
+    EveryN(u32),
+    /// Flush after a message when at least this long has passed since the
+    /// last flush. This is only checked when a message is written.
+    Interval(Duration)
+}
+
+impl Display for FlushPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlushPolicy::Never => write!(f, "never"),
+            FlushPolicy::EveryMessage => write!(f, "every_message"),
+            FlushPolicy::AtLevel(level) => write!(f, "level:{}", level.to_string().to_lowercase()),
+            FlushPolicy::EveryN(count) => write!(f, "every:{count}"),
+            FlushPolicy::Interval(interval) => write!(f, "interval:{}ms", interval.as_millis())
+        }
+    }
+}
+
+impl FromStr for FlushPolicy {
+    type Err = String;
+
+    /// Parse a policy such as `every_message` or `level:warn`. Case, dashes
+    /// and underscores in the name are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid flush policy: {}", s);
+        let (name, value) = match s.trim().split_once(':') {
+            Some((name, value)) => (name, Some(value.trim())),
+            None => (s.trim(), None)
+        };
+        let name: String = name
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+
+        match (name.as_str(), value) {
+            ("never", None) => Ok(FlushPolicy::Never),
+            ("everymessage", None) => Ok(FlushPolicy::EveryMessage),
+            ("level", Some(level)) => {
+                Ok(FlushPolicy::AtLevel(level.parse().map_err(|_| invalid())?))
+            }
+            ("every", Some(count)) => match count.parse() {
+                Ok(count) if count > 0 => Ok(FlushPolicy::EveryN(count)),
+                _ => Err(invalid())
+            },
+            ("interval", Some(interval)) => Ok(FlushPolicy::Interval(
+                parse_duration(interval).ok_or_else(invalid)?
+            )),
+            _ => Err(invalid())
+        }
+    }
+}
+
+impl TryFrom<String> for FlushPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<FlushPolicy> for String {
+    fn from(policy: FlushPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+/// What a [`FlushPolicy`] needs to remember between messages.
+#[derive(Debug)]
+pub(crate) struct FlushState {
+    /// How many messages have been written since the last flush.
+    unflushed: u32,
+    /// When the file was last flushed.
+    last_flush: SystemTime
+}
+
+impl FlushState {
+    /// Start counting from the given time, as if the file was just flushed.
+    pub(crate) fn new(now: SystemTime) -> FlushState {
+        FlushState {
+            unflushed: 0,
+            last_flush: now
+        }
+    }
+
+    /// Count a message that was just written, and decide whether the file
+    /// should be flushed now. If so, the state is reset as if it was.
+    pub(crate) fn message_written(
+        &mut self,
+        policy: FlushPolicy,
+        level: Level,
+        now: SystemTime
+    ) -> bool {
+        self.unflushed = self.unflushed.saturating_add(1);
+
+        let flush = match policy {
+            FlushPolicy::Never => false,
+            FlushPolicy::EveryMessage => true,
+            FlushPolicy::AtLevel(min_level) => level >= min_level,
+            FlushPolicy::EveryN(count) => self.unflushed >= count,
+            FlushPolicy::Interval(interval) => now
+                .duration_since(self.last_flush)
+                .map(|elapsed| elapsed >= interval)
+                .unwrap_or(false)
+        };
+        if flush {
+            self.flushed(now);
+        }
+
+        flush
+    }
+
+    /// Reset the state after the file was flushed.
+    pub(crate) fn flushed(&mut self, now: SystemTime) {
+        self.unflushed = 0;
+        self.last_flush = now;
+    }
+}
@@ -0,0 +1,97 @@
+use super::*;
+use std::time::UNIX_EPOCH;
+
+#[test]
+fn test_from_str() {
+    assert_eq!(Ok(FlushPolicy::Never), "never".parse());
+    assert_eq!(Ok(FlushPolicy::EveryMessage), "every_message".parse());
+    assert_eq!(Ok(FlushPolicy::EveryMessage), "Every-Message".parse());
+    assert_eq!(Ok(FlushPolicy::AtLevel(Level::Warn)), "level:warn".parse());
+    assert_eq!(Ok(FlushPolicy::EveryN(10)), "every:10".parse());
+    assert_eq!(
+        Ok(FlushPolicy::Interval(Duration::from_millis(250))),
+        "interval:250ms".parse()
+    );
+    assert_eq!(
+        Ok(FlushPolicy::Interval(Duration::from_secs(120))),
+        "interval: 2m".parse()
+    );
+
+    for invalid in [
+        "",
+        "always",
+        "level",
+        "level:loud",
+        "every:0",
+        "every:-1",
+        "interval:5",
+        "interval:5h",
+        "never:1"
+    ] {
+        assert!(invalid.parse::<FlushPolicy>().is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_display_round_trips() {
+    for policy in [
+        FlushPolicy::Never,
+        FlushPolicy::EveryMessage,
+        FlushPolicy::AtLevel(Level::Error),
+        FlushPolicy::EveryN(3),
+        FlushPolicy::Interval(Duration::from_secs(5))
+    ] {
+        assert_eq!(Ok(policy), policy.to_string().parse(), "{}", policy);
+    }
+}
+
+#[test]
+fn test_at_level() {
+    let mut state = FlushState::new(UNIX_EPOCH);
+    let policy = FlushPolicy::AtLevel(Level::Warn);
+
+    assert!(!state.message_written(policy, Level::Debug, UNIX_EPOCH));
+    assert!(!state.message_written(policy, Level::Info, UNIX_EPOCH));
+    assert!(state.message_written(policy, Level::Warn, UNIX_EPOCH));
+    assert!(state.message_written(policy, Level::Error, UNIX_EPOCH));
+}
+
+#[test]
+fn test_every_n() {
+    let mut state = FlushState::new(UNIX_EPOCH);
+    let policy = FlushPolicy::EveryN(3);
+
+    let flushes: Vec<bool> = (0..7)
+        .map(|_| state.message_written(policy, Level::Info, UNIX_EPOCH))
+        .collect();
+    assert_eq!(flushes, [false, false, true, false, false, true, false]);
+
+    // A manual flush starts the count again
+    state.flushed(UNIX_EPOCH);
+    assert!(!state.message_written(policy, Level::Info, UNIX_EPOCH));
+    assert!(!state.message_written(policy, Level::Info, UNIX_EPOCH));
+    assert!(state.message_written(policy, Level::Info, UNIX_EPOCH));
+}
+
+#[test]
+fn test_interval() {
+    let mut state = FlushState::new(UNIX_EPOCH);
+    let policy = FlushPolicy::Interval(Duration::from_secs(5));
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+    assert!(!state.message_written(policy, Level::Info, at(1)));
+    assert!(!state.message_written(policy, Level::Info, at(4)));
+    assert!(state.message_written(policy, Level::Info, at(5)));
+    assert!(!state.message_written(policy, Level::Info, at(9)));
+    assert!(state.message_written(policy, Level::Info, at(10)));
+    // A clock that goes backwards does not flush
+    assert!(!state.message_written(policy, Level::Info, at(2)));
+}
+
+#[test]
+fn test_never_and_every_message() {
+    let mut state = FlushState::new(UNIX_EPOCH);
+
+    assert!(!state.message_written(FlushPolicy::Never, Level::Error, UNIX_EPOCH));
+    assert!(state.message_written(FlushPolicy::EveryMessage, Level::Debug, UNIX_EPOCH));
+}
@@ -0,0 +1,116 @@
+//! The global logger that the unprefixed macros, such as `debug!`, log to.
+//!
+//! By default this is [`static@LOGGER`], but an application can replace it
+//! once with its own logger, for example one made with a
+//! [`LoggerBuilder`](crate::LoggerBuilder).
+
+#[cfg(test)]
+mod tests;
+
+use crate::{Logger, LOGGER};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// The logger given to [`set_global_logger`], or null if it has not been set.
+///
+/// Once set it is never changed or freed, which is what makes it safe to hand
+/// out `'static` references to it.
+static GLOBAL_LOGGER: AtomicPtr<Logger> = AtomicPtr::new(ptr::null_mut());
+
+/// Get the logger that the unprefixed macros log to.
+///
+/// This is the logger given to [`set_global_logger`] if there was one, or
+/// [`static@LOGGER`] otherwise. It is a single atomic load, so there is no
+/// locking involved.
+pub fn global_logger() -> &'static Logger {
+    let logger = GLOBAL_LOGGER.load(Ordering::Acquire);
+
+    if logger.is_null() {
+        &LOGGER
+    } else {
+        // Safety: the pointer came from `Box::into_raw` in
+        // `set_global_logger`, and is never freed or replaced after that.
+        unsafe { &*logger }
+    }
+}
+
+/// Make the given logger the target of the `debug!`, `info!`, `warn!`, and
+/// `error!` macros for the rest of the program.
+///
+/// The global logger can only be set once. This should be done early in
+/// `main`, before other threads start logging. Messages logged before the
+/// call go to [`static@LOGGER`]. After the call `LOGGER` still works, but
+/// the macros no longer log to it, so the logger should be configured
+/// through [`global_logger`] instead.
+///
+/// The logger is never dropped, so it should be flushed before the program
+/// exits like `LOGGER`, such as with a [`flush_guard`].
+///
+/// # Returns
+/// The logger is given back as the error if a global logger was already set.
+///
+/// # Example
+/// ```
+/// use pokey_logger::{info, set_global_logger, Level, Logger};
+///
+/// let logger = Logger::builder().level(Level::Info).build().unwrap();
+/// set_global_logger(logger).expect("global logger should only be set once");
+///
+/// info!("This goes to the new logger");
+/// ```
+pub fn set_global_logger(logger: Logger) -> Result<(), Box<Logger>> {
+    let new = Box::into_raw(Box::new(logger));
+
+    match GLOBAL_LOGGER.compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire)
+    {
+        Ok(_) => Ok(()),
+        // Safety: the pointer was just made by `Box::into_raw` and was never
+        // shared because the exchange failed.
+        Err(_) => Err(unsafe { Box::from_raw(new) })
+    }
+}
+
+/// Flushes the global loggers when it is dropped. See [`flush_guard`].
+#[must_use = "the loggers are flushed as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct FlushGuard {
+    _private: ()
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        flush_global_loggers();
+    }
+}
+
+/// Create a guard that flushes [`global_logger`], and [`static@LOGGER`] if
+/// that is a different logger, when it is dropped.
+///
+/// Global loggers are never dropped, so anything still buffered is lost
+/// when the program exits unless they are flushed. Keeping the guard in a
+/// variable in `main` flushes them when `main` returns, including when it
+/// returns early with `?`.
+///
+/// # Example
+/// ```
+/// use pokey_logger::{flush_guard, info};
+///
+/// fn main() {
+///     let _guard = flush_guard();
+///
+///     info!("This is flushed when main returns");
+/// }
+/// ```
+pub fn flush_guard() -> FlushGuard {
+    FlushGuard { _private: () }
+}
+
+/// Flush [`global_logger`], and [`static@LOGGER`] if that is a different
+/// logger. Errors are ignored.
+pub(crate) fn flush_global_loggers() {
+    let logger = global_logger();
+    let _ = logger.flush();
+    if !ptr::eq(logger, &*LOGGER) {
+        let _ = LOGGER.flush();
+    }
+}
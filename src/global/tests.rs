@@ -0,0 +1,37 @@
+use super::*;
+use crate::Level;
+
+// The global logger can only be set once per process, so everything is
+// checked in a single test.
+#[test]
+fn test_set_global_logger() {
+    let logger = Logger::new();
+    logger.set_level(Level::Debug);
+    logger.set_color(false);
+    #[cfg(feature = "log_files")]
+    let log_path = {
+        let path = tempfile::tempdir().unwrap().into_path().join("global.log");
+        logger.set_log_path(path.to_str().unwrap()).unwrap();
+        path
+    };
+
+    assert!(set_global_logger(logger).is_ok());
+    assert!(!ptr::eq(global_logger(), &*LOGGER));
+    assert!(!global_logger().get_color());
+
+    // A second logger is refused and given back
+    let second = Logger::new();
+    second.set_level(Level::Warn);
+    let returned = set_global_logger(second).unwrap_err();
+    assert_eq!(returned.get_level(), Level::Warn);
+
+    let guard = flush_guard();
+    crate::info!("test_set_global_logger message");
+    drop(guard);
+
+    #[cfg(feature = "log_files")]
+    {
+        let contents = std::fs::read_to_string(log_path).unwrap();
+        assert!(contents.contains("test_set_global_logger message"));
+    }
+}
@@ -0,0 +1,601 @@
+//! A [`Sink`] that sends records in batches to an HTTP endpoint, such as
+//! Grafana Loki, Elasticsearch, or any collector that takes JSON.
+//!
+//! This module is not included unless the `http` feature is enabled.
+//!
+//! ```no_run
+//! use pokey_logger::http::{BatchFormat, HttpSink};
+//! use pokey_logger::LOGGER;
+//!
+//! let sink = HttpSink::builder("http://127.0.0.1:3100/loki/api/v1/push")
+//!     .format(BatchFormat::Loki)
+//!     .label("app", "my_server")
+//!     .build()
+//!     .expect("invalid url");
+//! LOGGER.add_sink(sink);
+//!
+//! // Send what is left before the program exits
+//! LOGGER.flush().unwrap();
+//! ```
+//!
+//! Records are sent by a thread owned by the sink, so logging never waits
+//! for the network. Only plain `http://` URLs are supported, which suits a
+//! collector or agent running on the same machine or network.
+
+#[cfg(test)]
+mod tests;
+
+use crate::json;
+use crate::sink::{executable_name, Record, Sink};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The body of each request, holding a batch of records. Each record is a
+/// JSON object with its `time`, `level`, `message`, `thread`, `pid`, and
+/// `file` and `line` when they are known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchFormat {
+    /// A JSON array of the records.
+    #[default]
+    JsonArray,
+    /// A [Loki push](https://grafana.com/docs/loki/latest/reference/loki-http-api/#ingest-logs)
+    /// body, with one stream that has the labels of the builder, and each
+    /// record as a JSON line.
+    Loki,
+    /// An [Elasticsearch bulk](https://www.elastic.co/guide/en/elasticsearch/reference/current/docs-bulk.html)
+    /// body, indexing each record into the index of the builder.
+    ElasticsearchBulk
+}
+
+impl BatchFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            BatchFormat::JsonArray | BatchFormat::Loki => "application/json",
+            BatchFormat::ElasticsearchBulk => "application/x-ndjson"
+        }
+    }
+}
+
+/// Settings for an [`HttpSink`], which is created with
+/// [`build`](HttpSinkBuilder::build).
+#[derive(Debug, Clone)]
+pub struct HttpSinkBuilder {
+    url: String,
+    format: BatchFormat,
+    labels: Vec<(String, String)>,
+    index: String,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    interval: Duration,
+    capacity: usize,
+    retries: u32,
+    retry_backoff: Duration,
+    timeout: Duration,
+    flush_timeout: Duration
+}
+
+impl HttpSinkBuilder {
+    /// Send JSON arrays to the given URL, such as
+    /// `"http://127.0.0.1:8080/logs"`, in batches of up to 100 records at
+    /// least every second.
+    pub fn new<U: Into<String>>(url: U) -> HttpSinkBuilder {
+        HttpSinkBuilder {
+            url: url.into(),
+            format: BatchFormat::default(),
+            labels: Vec::new(),
+            index: "logs".to_string(),
+            headers: Vec::new(),
+            batch_size: 100,
+            interval: Duration::from_secs(1),
+            capacity: 10_000,
+            retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(5),
+            flush_timeout: Duration::from_secs(5)
+        }
+    }
+
+    /// Set the body of the requests.
+    pub fn format(mut self, format: BatchFormat) -> HttpSinkBuilder {
+        self.format = format;
+        self
+    }
+
+    /// Add a label to the Loki stream. Without any labels, the stream has a
+    /// `job` label with the name of the executable.
+    pub fn label<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> HttpSinkBuilder {
+        self.labels.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the Elasticsearch index the records go into. The default is
+    /// `logs`.
+    pub fn index<S: Into<String>>(mut self, index: S) -> HttpSinkBuilder {
+        self.index = index.into();
+        self
+    }
+
+    /// Add a header to every request, such as `Authorization`.
+    pub fn header<N: Into<String>, V: Into<String>>(
+        mut self,
+        name: N,
+        value: V
+    ) -> HttpSinkBuilder {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set how many records are sent at once. A batch is sent as soon as it
+    /// is full.
+    pub fn batch_size(mut self, records: usize) -> HttpSinkBuilder {
+        self.batch_size = records.max(1);
+        self
+    }
+
+    /// Set the longest time records wait before being sent when the batch
+    /// is not full.
+    pub fn interval(mut self, interval: Duration) -> HttpSinkBuilder {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the most records waiting to be sent. When it is full, the oldest
+    /// records are dropped.
+    pub fn capacity(mut self, records: usize) -> HttpSinkBuilder {
+        self.capacity = records;
+        self
+    }
+
+    /// Set how many more times a batch is sent when it fails, and how long
+    /// to wait before the first retry. The wait doubles after each retry, up
+    /// to 30 seconds.
+    ///
+    /// Only connection errors, `429 Too Many Requests` and `5xx` responses
+    /// are retried. Batches that still fail are dropped.
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> HttpSinkBuilder {
+        self.retries = retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Set how long to wait for connecting, sending and the response.
+    pub fn timeout(mut self, timeout: Duration) -> HttpSinkBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the longest time [`flush`](Sink::flush) waits for the records to
+    /// be sent before it returns [`io::ErrorKind::TimedOut`]. The default is
+    /// 5 seconds.
+    ///
+    /// Sending a batch to a collector that is down can take the timeout for
+    /// every retry, plus the waits between them, which is much longer.
+    pub fn flush_timeout(mut self, timeout: Duration) -> HttpSinkBuilder {
+        self.flush_timeout = timeout;
+        self
+    }
+
+    /// Create the sink and start its thread. Fails if the URL is not a
+    /// valid `http://` URL.
+    pub fn build(mut self) -> io::Result<HttpSink> {
+        let endpoint = Endpoint::parse(&self.url)?;
+        if self.labels.is_empty() {
+            self.labels.push(("job".to_string(), executable_name()));
+        }
+
+        let shared = Arc::new(Shared::new());
+        let capacity = self.capacity;
+        let batch_size = self.batch_size;
+        let flush_timeout = self.flush_timeout;
+        let worker = Worker {
+            shared: shared.clone(),
+            endpoint,
+            settings: self
+        };
+        let thread = thread::Builder::new()
+            .name("pokey_logger http".to_string())
+            .spawn(move || worker.run())?;
+
+        Ok(HttpSink {
+            shared,
+            thread: Some(thread),
+            capacity,
+            batch_size,
+            flush_timeout
+        })
+    }
+}
+
+/// A sink that sends records in batches to an HTTP endpoint, from its own
+/// thread.
+///
+/// Logging only adds the record to a queue of limited size. Batches are
+/// sent when full, after the interval, or when the sink is flushed.
+/// [`flush`](Sink::flush) waits until everything logged before it has been
+/// sent or dropped, up to the
+/// [flush timeout](HttpSinkBuilder::flush_timeout), and dropping the sink
+/// sends what is left first. If the thread of the sink has stopped, such as
+/// after a panic, flushing returns an error instead.
+#[derive(Debug)]
+pub struct HttpSink {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+    capacity: usize,
+    batch_size: usize,
+    flush_timeout: Duration
+}
+
+impl HttpSink {
+    /// Start building a sink that sends to the given URL. See
+    /// [`HttpSinkBuilder`].
+    pub fn builder<U: Into<String>>(url: U) -> HttpSinkBuilder {
+        HttpSinkBuilder::new(url)
+    }
+
+    /// How many records were sent.
+    pub fn sent(&self) -> u64 {
+        self.shared.sent.load(Ordering::Relaxed)
+    }
+
+    /// How many records were dropped, because the queue was full or the
+    /// batch could not be sent.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// How many records are waiting to be sent.
+    pub fn queue_len(&self) -> usize {
+        self.shared.lock().entries.len()
+    }
+}
+
+impl Sink for HttpSink {
+    fn log(&self, record: &Record) {
+        let entry = Entry {
+            time: record.time,
+            object: json::record_object(record)
+        };
+        let mut queue = self.shared.lock();
+
+        queue.entries.push_back(entry);
+        while queue.entries.len() > self.capacity {
+            queue.entries.pop_front();
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        if queue.entries.len() >= self.batch_size {
+            self.shared.work.notify_one();
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut queue = self.shared.lock();
+        queue.flush_requested += 1;
+        let target = queue.flush_requested;
+        let deadline = Instant::now() + self.flush_timeout;
+        self.shared.work.notify_one();
+
+        while queue.flushed < target && !queue.shutdown {
+            if queue.worker_gone {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "the thread of the HTTP sink has stopped"
+                ));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the HTTP sink did not send its records in time"
+                ));
+            }
+            queue = self
+                .shared
+                .done
+                .wait_timeout(queue, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for HttpSink {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The longest wait between retries.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The state shared by the sink and its thread.
+#[derive(Debug)]
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Wakes the thread when there is a full batch, a flush, or a shutdown.
+    work: Condvar,
+    /// Wakes flushes when the thread has caught up.
+    done: Condvar,
+    sent: AtomicU64,
+    dropped: AtomicU64
+}
+
+impl Shared {
+    fn new() -> Shared {
+        Shared {
+            queue: Mutex::new(Queue {
+                entries: VecDeque::new(),
+                flush_requested: 0,
+                flushed: 0,
+                shutdown: false,
+                worker_gone: false
+            }),
+            work: Condvar::new(),
+            done: Condvar::new(),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0)
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug)]
+struct Queue {
+    entries: VecDeque<Entry>,
+    /// How many flushes were asked for.
+    flush_requested: u64,
+    /// How many of those flushes are done.
+    flushed: u64,
+    shutdown: bool,
+    /// Whether or not the thread has stopped, so flushes would never be
+    /// done.
+    worker_gone: bool
+}
+
+/// A record, already written as a JSON object.
+#[derive(Debug, Clone)]
+struct Entry {
+    time: SystemTime,
+    object: String
+}
+
+/// The thread that sends the batches.
+struct Worker {
+    shared: Arc<Shared>,
+    endpoint: Endpoint,
+    settings: HttpSinkBuilder
+}
+
+/// Tells waiting flushes when the thread stops, even if it panics.
+struct WorkerGone(Arc<Shared>);
+
+impl Drop for WorkerGone {
+    fn drop(&mut self) {
+        self.0.lock().worker_gone = true;
+        self.0.done.notify_all();
+    }
+}
+
+impl Worker {
+    fn run(self) {
+        let _gone = WorkerGone(self.shared.clone());
+        loop {
+            let (batch, flush_target, finished) = self.next_batch();
+
+            if !batch.is_empty() {
+                let len = batch.len() as u64;
+                let body = body(&batch, &self.settings);
+                if self.send_with_retries(&body) {
+                    self.shared.sent.fetch_add(len, Ordering::Relaxed);
+                } else {
+                    self.shared.dropped.fetch_add(len, Ordering::Relaxed);
+                }
+            }
+            if let Some(target) = flush_target {
+                let mut queue = self.shared.lock();
+                queue.flushed = queue.flushed.max(target);
+                self.shared.done.notify_all();
+            }
+            if finished {
+                return;
+            }
+        }
+    }
+
+    /// Wait until a batch should be sent, then take it from the queue. Also
+    /// gives the flush that is done once the batch is sent, if the queue is
+    /// now empty, and whether the thread should stop after this batch.
+    fn next_batch(&self) -> (Vec<Entry>, Option<u64>, bool) {
+        let mut queue = self.shared.lock();
+        let deadline = Instant::now() + self.settings.interval;
+
+        while queue.entries.len() < self.settings.batch_size
+            && queue.flush_requested == queue.flushed
+            && !queue.shutdown
+        {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            queue = self
+                .shared
+                .work
+                .wait_timeout(queue, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+
+        let len = queue.entries.len().min(self.settings.batch_size);
+        let batch: Vec<Entry> = queue.entries.drain(..len).collect();
+        let empty = queue.entries.is_empty();
+        let flush_target = if empty && queue.flush_requested > queue.flushed {
+            Some(queue.flush_requested)
+        } else {
+            None
+        };
+
+        (batch, flush_target, empty && queue.shutdown)
+    }
+
+    /// Send a body, retrying if that may help. Returns whether it was sent.
+    fn send_with_retries(&self, body: &str) -> bool {
+        let mut backoff = self.settings.retry_backoff;
+        for attempt in 0..=self.settings.retries {
+            if attempt > 0 {
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2).min(MAX_RETRY_BACKOFF);
+            }
+            match self.post(body) {
+                Ok(status) if (200..300).contains(&status) => return true,
+                Ok(status) if status == 429 || status >= 500 => {}
+                Ok(_) => return false,
+                Err(_) => {}
+            }
+        }
+
+        false
+    }
+
+    /// Make a single request and give the status code of the response.
+    fn post(&self, body: &str) -> io::Result<u16> {
+        let timeout = self.settings.timeout;
+        let mut stream = connect(&self.endpoint.address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.endpoint.path,
+            self.endpoint.host,
+            self.settings.format.content_type(),
+            body.len()
+        );
+        for (name, value) in self.settings.headers.iter() {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes())?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response"))
+    }
+}
+
+/// The parts of an `http://` URL needed to send a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Endpoint {
+    /// The host and port to connect to.
+    address: String,
+    /// The host as given in the URL, for the `Host` header.
+    host: String,
+    /// The path and query, starting with `/`.
+    path: String
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> io::Result<Endpoint> {
+        let invalid = |message: &str| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{message}: {url}"))
+        };
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("Only http:// URLs are supported"))?;
+        let (host, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/")
+        };
+        if host.is_empty() {
+            return Err(invalid("The URL has no host"));
+        }
+        // A port is given after the last colon, unless that is inside an IPv6
+        // address such as [::1]
+        let address = match host.rfind(':') {
+            Some(colon) if colon > host.rfind(']').unwrap_or(0) => host.to_string(),
+            _ => format!("{host}:80")
+        };
+
+        Ok(Endpoint {
+            address,
+            host: host.to_string(),
+            path: path.to_string()
+        })
+    }
+}
+
+/// Connect to the first address that works.
+fn connect(address: &str, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "no addresses found");
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e
+        }
+    }
+
+    Err(last_error)
+}
+
+/// The body of the request for a batch.
+fn body(batch: &[Entry], settings: &HttpSinkBuilder) -> String {
+    match settings.format {
+        BatchFormat::JsonArray => {
+            let objects: Vec<&str> = batch.iter().map(|entry| entry.object.as_str()).collect();
+            format!("[{}]", objects.join(","))
+        }
+        BatchFormat::Loki => {
+            let labels: Vec<String> = settings
+                .labels
+                .iter()
+                .map(|(name, value)| format!("{}:{}", json::string(name), json::string(value)))
+                .collect();
+            let values: Vec<String> = batch
+                .iter()
+                .map(|entry| {
+                    let nanos = entry
+                        .time
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_nanos();
+                    format!("[\"{nanos}\",{}]", json::string(&entry.object))
+                })
+                .collect();
+            format!(
+                "{{\"streams\":[{{\"stream\":{{{}}},\"values\":[{}]}}]}}",
+                labels.join(","),
+                values.join(",")
+            )
+        }
+        BatchFormat::ElasticsearchBulk => {
+            let action = format!(
+                "{{\"index\":{{\"_index\":{}}}}}\n",
+                json::string(&settings.index)
+            );
+            let mut body = String::new();
+            for entry in batch {
+                body.push_str(&action);
+                body.push_str(&entry.object);
+                body.push('\n');
+            }
+            body
+        }
+    }
+}
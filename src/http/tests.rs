@@ -0,0 +1,331 @@
+use super::*;
+use crate::Level;
+use std::io::Read;
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+
+/// A request received by the server stand-in.
+#[derive(Debug)]
+struct Request {
+    head: String,
+    body: String
+}
+
+/// An HTTP server stand-in that answers each request with the next status,
+/// and 200 once there are none left. Gives its URL and the requests.
+fn server(statuses: Vec<u16>) -> (String, Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/logs?app=test", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut statuses = statuses.into_iter();
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let len: usize = head
+                .lines()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+
+            let status = statuses.next().unwrap_or(200);
+            let response = format!("HTTP/1.1 {status} Status\r\nContent-Length: 0\r\n\r\n");
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            let request = Request {
+                head,
+                body: String::from_utf8(body).unwrap()
+            };
+            if sender.send(request).is_err() {
+                return;
+            }
+        }
+    });
+
+    (url, receiver)
+}
+
+fn receive(receiver: &Receiver<Request>) -> Request {
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+fn record(message: &str) -> Record {
+    let mut record = Record::new(
+        Level::Info,
+        message,
+        UNIX_EPOCH + Duration::from_millis(1_647_108_849_500)
+    );
+    record.thread = "main".to_string();
+    record.process_id = 1234;
+    record
+}
+
+fn entry(message: &str) -> Entry {
+    Entry {
+        time: record(message).time,
+        object: json::record_object(&record(message))
+    }
+}
+
+#[test]
+fn test_endpoint_parse() {
+    assert_eq!(
+        Endpoint::parse("http://localhost:3100/loki/api/v1/push").unwrap(),
+        Endpoint {
+            address: "localhost:3100".to_string(),
+            host: "localhost:3100".to_string(),
+            path: "/loki/api/v1/push".to_string()
+        }
+    );
+    assert_eq!(
+        Endpoint::parse("http://collector").unwrap(),
+        Endpoint {
+            address: "collector:80".to_string(),
+            host: "collector".to_string(),
+            path: "/".to_string()
+        }
+    );
+    assert_eq!(
+        Endpoint::parse("http://[::1]/logs").unwrap().address,
+        "[::1]:80"
+    );
+    assert_eq!(
+        Endpoint::parse("http://[::1]:9200/").unwrap().address,
+        "[::1]:9200"
+    );
+    assert!(Endpoint::parse("https://collector/logs").is_err());
+    assert!(Endpoint::parse("http:///logs").is_err());
+    assert!(HttpSink::builder("collector:80").build().is_err());
+}
+
+#[test]
+fn test_bodies() {
+    let batch = vec![entry("one"), entry("two")];
+    let settings = HttpSinkBuilder::new("http://localhost")
+        .label("app", "test")
+        .index("app-logs");
+
+    assert_eq!(
+        body(&batch, &settings),
+        format!("[{},{}]", batch[0].object, batch[1].object)
+    );
+    assert_eq!(
+        body(&batch, &settings.clone().format(BatchFormat::Loki)),
+        format!(
+            "{{\"streams\":[{{\"stream\":{{\"app\":\"test\"}},\"values\":[[\"1647108849500000000\",{}],[\"1647108849500000000\",{}]]}}]}}",
+            json::string(&batch[0].object),
+            json::string(&batch[1].object)
+        )
+    );
+    assert_eq!(
+        body(&batch, &settings.format(BatchFormat::ElasticsearchBulk)),
+        format!(
+            "{{\"index\":{{\"_index\":\"app-logs\"}}}}\n{}\n{{\"index\":{{\"_index\":\"app-logs\"}}}}\n{}\n",
+            batch[0].object, batch[1].object
+        )
+    );
+}
+
+#[test]
+fn test_sends_full_batches() {
+    let (url, requests) = server(Vec::new());
+    let sink = HttpSink::builder(url)
+        .batch_size(2)
+        .interval(Duration::from_secs(60))
+        .header("Authorization", "Bearer token")
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    sink.log(&record("two"));
+    sink.log(&record("three"));
+
+    let request = receive(&requests);
+    assert!(request.head.starts_with("POST /logs?app=test HTTP/1.1\r\n"));
+    assert!(request.head.contains("Content-Type: application/json\r\n"));
+    assert!(request.head.contains("Authorization: Bearer token\r\n"));
+    assert_eq!(
+        request.body,
+        format!("[{},{}]", entry("one").object, entry("two").object)
+    );
+    assert_eq!(sink.queue_len(), 1);
+}
+
+#[test]
+fn test_sends_after_the_interval() {
+    let (url, requests) = server(Vec::new());
+    let sink = HttpSink::builder(url)
+        .interval(Duration::from_millis(20))
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+
+    assert_eq!(
+        receive(&requests).body,
+        format!("[{}]", entry("one").object)
+    );
+}
+
+#[test]
+fn test_flush_waits_until_sent() {
+    let (url, requests) = server(Vec::new());
+    let sink = HttpSink::builder(url)
+        .format(BatchFormat::ElasticsearchBulk)
+        .interval(Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    sink.log(&record("two"));
+    sink.flush().unwrap();
+
+    assert_eq!(sink.sent(), 2);
+    assert_eq!(sink.queue_len(), 0);
+    let request = receive(&requests);
+    assert!(request
+        .head
+        .contains("Content-Type: application/x-ndjson\r\n"));
+    assert_eq!(request.body.lines().count(), 4);
+}
+
+#[test]
+fn test_retries_server_errors() {
+    let (url, requests) = server(vec![503, 429]);
+    let sink = HttpSink::builder(url)
+        .interval(Duration::from_secs(60))
+        .retries(2, Duration::from_millis(5))
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    sink.flush().unwrap();
+
+    assert_eq!(sink.sent(), 1);
+    assert_eq!(sink.dropped(), 0);
+    for _ in 0..3 {
+        assert_eq!(
+            receive(&requests).body,
+            format!("[{}]", entry("one").object)
+        );
+    }
+}
+
+#[test]
+fn test_drops_batches_that_fail() {
+    let (url, requests) = server(vec![500, 500, 400]);
+    let sink = HttpSink::builder(url)
+        .interval(Duration::from_secs(60))
+        .retries(1, Duration::from_millis(5))
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    sink.flush().unwrap();
+    assert_eq!(sink.dropped(), 1);
+
+    // Client errors are not retried
+    sink.log(&record("two"));
+    sink.flush().unwrap();
+    assert_eq!(sink.dropped(), 2);
+    assert_eq!(sink.sent(), 0);
+    assert_eq!(requests.try_iter().count(), 3);
+}
+
+#[test]
+fn test_drops_oldest_when_full() {
+    // Nothing is listening on this port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/logs", listener.local_addr().unwrap());
+    drop(listener);
+    let sink = HttpSink::builder(url)
+        .capacity(2)
+        .interval(Duration::from_secs(60))
+        .retries(0, Duration::ZERO)
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    sink.log(&record("two"));
+    sink.log(&record("three"));
+    assert_eq!(sink.dropped(), 1);
+    assert_eq!(sink.queue_len(), 2);
+
+    sink.flush().unwrap();
+    assert_eq!(sink.dropped(), 3);
+}
+
+#[test]
+fn test_drop_sends_the_rest() {
+    let (url, requests) = server(Vec::new());
+    let logger = crate::Logger::new();
+    logger.add_sink(
+        HttpSink::builder(url)
+            .interval(Duration::from_secs(60))
+            .build()
+            .unwrap()
+    );
+
+    logger.warn("last words");
+    drop(logger);
+
+    let body = receive(&requests).body;
+    assert!(body.contains("\"level\":\"WARN\",\"message\":\"last words\""));
+}
+
+#[test]
+fn test_flush_does_not_hang_after_the_thread_panics() {
+    let shared = Arc::new(Shared::new());
+    let gone = WorkerGone(shared.clone());
+    let thread = thread::spawn(move || {
+        let _gone = gone;
+        thread::sleep(Duration::from_millis(50));
+        panic!("the thread of the sink panicked");
+    });
+    let sink = HttpSink {
+        shared,
+        thread: Some(thread),
+        capacity: 10,
+        batch_size: 10,
+        flush_timeout: Duration::from_secs(60)
+    };
+
+    sink.log(&record("never sent"));
+
+    let error = sink.flush().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    assert!(sink.flush().is_err());
+}
+
+#[test]
+fn test_flush_times_out() {
+    // The collector takes the connection but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/logs", listener.local_addr().unwrap());
+    let sink = HttpSink::builder(url)
+        .interval(Duration::from_secs(60))
+        .retries(0, Duration::ZERO)
+        .timeout(Duration::from_millis(500))
+        .flush_timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    sink.log(&record("one"));
+    let start = Instant::now();
+    let error = sink.flush().unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_millis(500));
+    drop(listener);
+}
@@ -0,0 +1,225 @@
+//! A [`Sink`] that sends messages to the systemd journal with its native
+//! protocol, so that the level of each message is kept as its priority.
+//!
+//! This module is not included unless the `journald` feature is enabled, and
+//! only on Unix.
+//!
+//! ```
+//! use pokey_logger::journald::JournaldSink;
+//! use pokey_logger::LOGGER;
+//!
+//! // Does nothing when the program is not running under systemd
+//! LOGGER.add_sink(JournaldSink::builder().field("COMPONENT", "api").build());
+//! ```
+//!
+//! Every message is sent with these fields, followed by the extra fields
+//! given to the builder:
+//!
+//! | Field | Value |
+//! |-------|-------|
+//! | `MESSAGE` | The message |
+//! | `PRIORITY` | The syslog severity of the level, such as `3` for `Error` |
+//! | `SYSLOG_IDENTIFIER` | The name of the program |
+//! | `CODE_FILE`, `CODE_LINE` | Where the message was logged, if known |
+//! | `THREAD` | The name or ID of the thread that logged the message |
+
+#[cfg(test)]
+mod tests;
+
+use crate::sink::{executable_name, Record, Sink};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Where the journal listens for native messages.
+pub const DEFAULT_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// Settings for a [`JournaldSink`], which is created with
+/// [`connect`](JournaldBuilder::connect) or [`build`](JournaldBuilder::build).
+#[derive(Debug, Clone)]
+pub struct JournaldBuilder {
+    path: PathBuf,
+    identifier: Option<String>,
+    fields: Vec<(String, String)>
+}
+
+impl JournaldBuilder {
+    /// Send to the usual journal socket, with the name of the executable as
+    /// the identifier and no extra fields.
+    pub fn new() -> JournaldBuilder {
+        JournaldBuilder {
+            path: PathBuf::from(DEFAULT_SOCKET_PATH),
+            identifier: None,
+            fields: Vec::new()
+        }
+    }
+
+    /// Set the path of the journal socket.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> JournaldBuilder {
+        self.path = path.into();
+        self
+    }
+
+    /// Set the `SYSLOG_IDENTIFIER` of the messages, which `journalctl -t`
+    /// filters by. By default this is the name of the executable.
+    pub fn identifier<S: Into<String>>(mut self, identifier: S) -> JournaldBuilder {
+        self.identifier = Some(identifier.into());
+        self
+    }
+
+    /// Add a field that is sent with every message.
+    ///
+    /// The journal only accepts names of uppercase letters, digits and
+    /// underscores that do not start with an underscore or a digit, so
+    /// other names are changed to fit, such as `request-id` to `REQUEST_ID`.
+    pub fn field<N: AsRef<str>, V: Into<String>>(mut self, name: N, value: V) -> JournaldBuilder {
+        if let Some(name) = field_name(name.as_ref()) {
+            self.fields.push((name, value.into()));
+        }
+        self
+    }
+
+    /// Create the sink, failing if there is no journal socket at the path.
+    pub fn connect(self) -> io::Result<JournaldSink> {
+        let socket = UnixDatagram::unbound()?;
+        // Only to check that the journal is there, each message is sent to
+        // the path so that a restarted journal is found again.
+        UnixDatagram::unbound()?.connect(&self.path)?;
+
+        Ok(self.sink(Some(socket)))
+    }
+
+    /// Create the sink. If there is no journal socket at the path, such as
+    /// when the program is not run by systemd, the sink does nothing, so the
+    /// same program can run with or without the journal.
+    pub fn build(self) -> JournaldSink {
+        match self.clone().connect() {
+            Ok(sink) => sink,
+            Err(_) => self.sink(None)
+        }
+    }
+
+    fn sink(self, socket: Option<UnixDatagram>) -> JournaldSink {
+        JournaldSink {
+            socket,
+            path: self.path,
+            identifier: self.identifier.unwrap_or_else(executable_name),
+            fields: self.fields,
+            dropped: AtomicU64::new(0)
+        }
+    }
+}
+
+impl Default for JournaldBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A sink that sends every record to the systemd journal.
+///
+/// Messages that can not be sent, such as ones that are too big for a
+/// single datagram, are dropped and counted.
+#[derive(Debug)]
+pub struct JournaldSink {
+    /// None when the journal was not found.
+    socket: Option<UnixDatagram>,
+    path: PathBuf,
+    identifier: String,
+    fields: Vec<(String, String)>,
+    /// How many messages could not be sent.
+    dropped: AtomicU64
+}
+
+impl JournaldSink {
+    /// Start building a sink. See [`JournaldBuilder`].
+    pub fn builder() -> JournaldBuilder {
+        JournaldBuilder::new()
+    }
+
+    /// Connect to the journal with the default settings, failing if it is
+    /// not there.
+    pub fn connect() -> io::Result<JournaldSink> {
+        JournaldBuilder::new().connect()
+    }
+
+    /// Whether or not the journal was found. If not, the sink does nothing.
+    pub fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// How many messages could not be sent to the journal.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Encode a record in the native journal protocol, or none for
+    /// `Level::None`.
+    pub fn encode(&self, record: &Record) -> Option<Vec<u8>> {
+        let priority = record.level.syslog_severity()?;
+        let mut data = Vec::new();
+
+        push_field(&mut data, "MESSAGE", &record.message);
+        push_field(&mut data, "PRIORITY", &priority.to_string());
+        push_field(&mut data, "SYSLOG_IDENTIFIER", &self.identifier);
+        if let Some(location) = record.location {
+            push_field(&mut data, "CODE_FILE", location.file());
+            push_field(&mut data, "CODE_LINE", &location.line().to_string());
+        }
+        push_field(&mut data, "THREAD", &record.thread);
+        for (name, value) in self.fields.iter() {
+            push_field(&mut data, name, value);
+        }
+
+        Some(data)
+    }
+}
+
+impl Sink for JournaldSink {
+    fn log(&self, record: &Record) {
+        let socket = match self.socket {
+            Some(ref socket) => socket,
+            None => return
+        };
+        if let Some(data) = self.encode(record) {
+            if socket.send_to(&data, &self.path).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Add a field to a native journal message. Values with a new line in them
+/// are written with their length in front instead of after an `=`.
+fn push_field(data: &mut Vec<u8>, name: &str, value: &str) {
+    data.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        data.push(b'\n');
+        data.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        data.push(b'=');
+    }
+    data.extend_from_slice(value.as_bytes());
+    data.push(b'\n');
+}
+
+/// Change a field name into one the journal accepts, or none if nothing is
+/// left of it.
+fn field_name(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => c.to_ascii_uppercase(),
+            'A'..='Z' | '0'..='9' => c,
+            _ => '_'
+        })
+        .collect();
+    let name = name.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit());
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
@@ -0,0 +1,144 @@
+use super::*;
+use crate::{Level, Logger};
+use std::time::{Duration, SystemTime};
+
+/// A datagram socket in a temporary directory standing in for the journal.
+fn journal() -> (tempfile::TempDir, PathBuf, UnixDatagram) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("socket");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    (dir, path, socket)
+}
+
+/// Read one message from the journal stand-in and decode its fields.
+fn receive(socket: &UnixDatagram) -> Vec<(String, String)> {
+    let mut buffer = vec![0; 65536];
+    let len = socket.recv(&mut buffer).unwrap();
+    decode(&buffer[..len])
+}
+
+/// Decode a native journal message, the way journald does.
+fn decode(mut data: &[u8]) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let end = data.iter().position(|b| *b == b'\n').unwrap();
+        let line = &data[..end];
+        match line.iter().position(|b| *b == b'=') {
+            Some(equals) => {
+                fields.push((
+                    String::from_utf8_lossy(&line[..equals]).to_string(),
+                    String::from_utf8_lossy(&line[equals + 1..]).to_string()
+                ));
+                data = &data[end + 1..];
+            }
+            None => {
+                let name = String::from_utf8_lossy(line).to_string();
+                let rest = &data[end + 1..];
+                let len = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+                let value = String::from_utf8_lossy(&rest[8..8 + len]).to_string();
+                assert_eq!(rest[8 + len], b'\n');
+                fields.push((name, value));
+                data = &rest[8 + len + 1..];
+            }
+        }
+    }
+
+    fields
+}
+
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn test_field_name() {
+    assert_eq!(field_name("COMPONENT"), Some("COMPONENT".to_string()));
+    assert_eq!(field_name("request-id"), Some("REQUEST_ID".to_string()));
+    assert_eq!(field_name("_PID"), Some("PID".to_string()));
+    assert_eq!(field_name("2FA"), Some("FA".to_string()));
+    assert_eq!(field_name("__"), None);
+}
+
+#[test]
+fn test_encode() {
+    let (_dir, path, _socket) = journal();
+    let sink = JournaldSink::builder()
+        .path(&path)
+        .identifier("app")
+        .field("component", "api")
+        .connect()
+        .unwrap();
+    let record = Record::new(Level::Warn, "two\nlines", SystemTime::now());
+
+    let fields = decode(&sink.encode(&record).unwrap());
+
+    assert_eq!(field(&fields, "MESSAGE"), Some("two\nlines"));
+    assert_eq!(field(&fields, "PRIORITY"), Some("4"));
+    assert_eq!(field(&fields, "SYSLOG_IDENTIFIER"), Some("app"));
+    assert_eq!(field(&fields, "THREAD"), Some(record.thread.as_str()));
+    assert_eq!(field(&fields, "COMPONENT"), Some("api"));
+    assert_eq!(field(&fields, "CODE_FILE"), None);
+    assert!(sink
+        .encode(&Record::new(Level::None, "never", SystemTime::now()))
+        .is_none());
+}
+
+#[test]
+fn test_sends_to_the_journal() {
+    let (_dir, path, socket) = journal();
+    let sink = JournaldSink::builder()
+        .path(&path)
+        .identifier("app")
+        .build();
+    assert!(sink.is_connected());
+    let logger = Logger::new();
+    logger.add_sink(sink);
+
+    let line = line!() + 1;
+    logger.error("failed");
+    logger.debug("details");
+
+    let error = receive(&socket);
+    assert_eq!(field(&error, "MESSAGE"), Some("failed"));
+    assert_eq!(field(&error, "PRIORITY"), Some("3"));
+    assert_eq!(field(&error, "CODE_FILE"), Some(file!()));
+    assert_eq!(field(&error, "CODE_LINE"), Some(line.to_string().as_str()));
+    let debug = receive(&socket);
+    assert_eq!(field(&debug, "MESSAGE"), Some("details"));
+    assert_eq!(field(&debug, "PRIORITY"), Some("7"));
+}
+
+#[test]
+fn test_falls_back_without_a_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("socket");
+
+    assert!(JournaldSink::builder().path(&path).connect().is_err());
+    let sink = JournaldSink::builder().path(&path).build();
+    assert!(!sink.is_connected());
+
+    sink.log(&Record::new(
+        Level::Error,
+        "nowhere to go",
+        SystemTime::now()
+    ));
+    assert_eq!(sink.dropped(), 0);
+}
+
+#[test]
+fn test_counts_dropped_messages() {
+    let (_dir, path, socket) = journal();
+    let sink = JournaldSink::builder().path(&path).build();
+
+    drop(socket);
+    std::fs::remove_file(&path).unwrap();
+    sink.log(&Record::new(Level::Info, "lost", SystemTime::now()));
+
+    assert_eq!(sink.dropped(), 1);
+}
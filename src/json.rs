@@ -0,0 +1,73 @@
+//! Just enough JSON writing for the network and HTTP sinks, so that they do
+//! not need `serde`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::sink::Record;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+/// Write a string as a JSON string, with the quotes.
+pub(crate) fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+/// The time of a record in RFC 3339 format, in UTC with milliseconds.
+pub(crate) fn rfc3339(record: &Record) -> String {
+    DateTime::<Utc>::from(record.time).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// The time of a record in seconds since the Unix epoch, with milliseconds.
+pub(crate) fn unix_seconds(record: &Record) -> String {
+    let since_epoch = record.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}.{:03}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    )
+}
+
+/// A record as a JSON object, such as
+/// `{"time":"2022-03-12T18:14:09.500Z","level":"INFO","message":"hello",
+/// "thread":"main","pid":1234,"file":"src/main.rs","line":4}`. The file and
+/// line are left out when the location is not known.
+pub(crate) fn record_object(record: &Record) -> String {
+    let mut object = format!(
+        "{{\"time\":{},\"level\":{},\"message\":{},\"thread\":{},\"pid\":{}",
+        string(&rfc3339(record)),
+        string(&record.level.to_string()),
+        string(&record.message),
+        string(&record.thread),
+        record.process_id
+    );
+    if let Some(location) = record.location {
+        let _ = write!(
+            object,
+            ",\"file\":{},\"line\":{}",
+            string(location.file()),
+            location.line()
+        );
+    }
+    object.push('}');
+
+    object
+}
@@ -0,0 +1,35 @@
+use super::*;
+use crate::Level;
+use std::time::Duration;
+
+#[test]
+fn test_string() {
+    assert_eq!(string("plain"), "\"plain\"");
+    assert_eq!(string("say \"hi\"\\"), "\"say \\\"hi\\\"\\\\\"");
+    assert_eq!(string("a\nb\tc\r"), "\"a\\nb\\tc\\r\"");
+    assert_eq!(string("\u{1b}[0m"), "\"\\u001b[0m\"");
+    assert_eq!(string("ünïcode ✓"), "\"ünïcode ✓\"");
+}
+
+#[test]
+fn test_record_object() {
+    let mut record = Record::new(
+        Level::Warn,
+        "disk \"full\"",
+        UNIX_EPOCH + Duration::from_millis(1_647_108_849_500)
+    );
+    record.thread = "main".to_string();
+    record.process_id = 1234;
+
+    assert_eq!(
+        record_object(&record),
+        "{\"time\":\"2022-03-12T18:14:09.500Z\",\"level\":\"WARN\",\"message\":\"disk \\\"full\\\"\",\"thread\":\"main\",\"pid\":1234}"
+    );
+    assert_eq!(unix_seconds(&record), "1647108849.500");
+
+    let line = line!() + 1;
+    let record = record.with_location(std::panic::Location::caller());
+    assert!(
+        record_object(&record).ends_with(&format!(",\"file\":\"{}\",\"line\":{line}}}", file!()))
+    );
+}
@@ -12,7 +12,7 @@ use std::str::FromStr;
 #[cfg(feature = "config")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 #[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
 /// The log level.
 ///
@@ -35,7 +35,9 @@ use serde::{Deserialize, Serialize};
 pub enum Level {
     /// Basic messages that shouldn't be shown to an end user in most cases.
     Debug = 0,
-    /// Informational messages that may or may not be important.
+    /// Informational messages that may or may not be important. This is the
+    /// default.
+    #[default]
     Info = 1,
     /// Something has gone wrong, but the program can still continue.
     Warn = 2,
@@ -57,6 +59,18 @@ impl Level {
             Level::None => Reset
         }
     }
+
+    /// Returns the syslog severity of the level, which is also used by the
+    /// systemd journal. `None` is never logged, so it has no severity.
+    pub fn syslog_severity(&self) -> Option<u8> {
+        match self {
+            Level::Debug => Some(7),
+            Level::Info => Some(6),
+            Level::Warn => Some(4),
+            Level::Error => Some(3),
+            Level::None => None
+        }
+    }
 }
 
 impl Display for Level {
@@ -85,9 +99,3 @@ impl FromStr for Level {
         }
     }
 }
-
-impl Default for Level {
-    fn default() -> Self {
-        Level::Info
-    }
-}
@@ -41,10 +41,14 @@
 //! ```
 //!
 //! As of version 0.2.0 of the library a log file can be added. It should be noted
-//! that the library will **never** create directories, but it will create log files
-//! if they don't exist. For example in the above example program, the logger would
-//! not create the logs directory, but if the logs directory existed and the file
-//! did not, it would be able to create the `server.log` file.
+//! that by default the library will **not** create directories, but it will create
+//! log files if they don't exist. For example in the above example program, the
+//! logger would not create the logs directory, but if the logs directory existed
+//! and the file did not, it would be able to create the `server.log` file.
+//!
+//! To have the missing directories created as well, turn on
+//! [`Logger::set_create_log_directories`] before setting the log path, and
+//! optionally choose their permissions with [`Logger::set_log_directory_mode`].
 //!
 //! It is also valuable to note that `LOGGER` is a global static instance of the
 //! `Logger`. It is thread safe to use, but one should be careful about configuring
@@ -52,6 +56,9 @@
 //! and instances, the `Logger` struct itself can be instantiated and passed around
 //! as the developer sees fit.
 //!
+//! The macros log to `LOGGER` unless another logger is made the global logger
+//! with [`set_global_logger`], which can be done once, early in `main`.
+//!
 //! # Configuration
 //!
 //! There are three main ways to configure the logger.
@@ -65,6 +72,10 @@
 //! command to find all the functions that can be called to configure the
 //! logger at run time.
 //!
+//! A new logger can also be created with a [`LoggerBuilder`], which checks all
+//! of the settings at once when it is built, and can start from environment
+//! variables or a config file.
+//!
 //! ## Config File
 //!
 //! After starting your program, you can tell the logger to load a yaml config
@@ -74,8 +85,28 @@
 //! color: true
 //! time_stamp: true
 //! file_color: false
+//! timestamp_style: Iso8601Millis
+//! timestamp_utc: true
+//! show_thread: true
+//! show_process_id: false
 //! log_file_path: "./log/development.log"
-//! existing_log_handler: Append
+//! existing_log_handler: Rename
+//! log_archive_directory: "./log/archive"
+//! create_log_directories: true
+//! log_directory_mode: "750"
+//! max_log_bytes: 1073741824
+//! prune_log_archives: true
+//! flush_policy: "level:warn"
+//! sync_on_error: true
+//! write_error_policy: "retry:3:100ms"
+//! log_files:
+//!   - path: "./log/errors.log"
+//!     min_level: Error
+//! flight_recorder:
+//!   capacity: 500
+//!   trigger: Error
+//! rate_limit: "100/1s"
+//! collapse_duplicates: true
 //! ```
 //!
 //! To load it, see the [`Logger::load_config_file()`] method.
@@ -102,6 +133,12 @@
 //! PL_LEVEL=info PL_COLOR=false some_program
 //! ```
 //!
+//! With the `config` feature, the `PL_CONFIG` variable can point to a config
+//! file that is loaded before the other variables are applied.
+//!
+//! Variables with invalid values are reported back by
+//! [`Logger::load_env_vars`], and are logged as warnings for the global logger.
+//!
 //! # Features
 //!
 //! There are a few features that can be turned off when using the crate to
@@ -116,6 +153,20 @@
 //! ## Optional Features
 //!
 //! - **config** - Allows loading of a .yml config file. Includes `serde`.
+//! - **signal** - Allows reopening the log file on `SIGHUP` with
+//!   [`Logger::reopen_on_sighup`]. Unix only. Includes `signal-hook`.
+//! - **file_lock** - Allows locking the log file while writing to it with
+//!   [`Logger::set_file_locking`]. Unix only. Includes `libc`.
+//! - **syslog** - Adds a sink that sends messages to the local syslog daemon,
+//!   in the `syslog` module. Unix only.
+//! - **journald** - Adds a sink that sends messages to the systemd journal,
+//!   in the `journald` module. Unix only.
+//! - **disk_space** - Allows pausing file logging when the disk is low on
+//!   space with `Logger::set_min_free_space`. Unix only. Includes `libc`.
+//! - **network** - Adds sinks that send messages to a log collector over TCP
+//!   or UDP, as text, JSON or GELF, in the `network` module.
+//! - **http** - Adds a sink that sends messages in batches to an HTTP
+//!   endpoint, such as Loki or Elasticsearch, in the `http` module.
 
 #![allow(dead_code)]
 // Allow needless doctest main function because example above makes more sense
@@ -126,26 +177,69 @@
 mod tests;
 #[macro_use]
 pub mod logging_macros;
+pub mod builder;
+pub mod capture;
+pub mod clock;
 pub mod color;
 #[cfg(feature = "log_files")]
+mod disk_guard;
+#[cfg(feature = "log_files")]
 pub mod existing_log_handler;
+#[cfg(all(feature = "file_lock", unix))]
+mod file_lock;
+#[cfg(feature = "log_files")]
+pub mod file_output;
+pub mod flight_recorder;
+#[cfg(feature = "log_files")]
+pub mod flush_policy;
 #[cfg(feature = "time")]
 pub mod time;
+#[cfg(feature = "log_files")]
+pub mod write_error_policy;
 
 #[cfg(feature = "config")]
 mod config_file;
 #[cfg(feature = "env")]
 pub mod environment;
+mod global;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(all(feature = "journald", unix))]
+pub mod journald;
+#[cfg(any(feature = "network", feature = "http"))]
+mod json;
 mod level; // not public because level is reexported
 mod log_message;
+#[cfg(feature = "network")]
+pub mod network;
+mod panic_hook;
+pub mod rate_limit;
+pub mod sink;
+#[cfg(all(feature = "syslog", unix))]
+pub mod syslog;
 
+pub use builder::{BuildError, LoggerBuilder};
 #[cfg(feature = "config")]
-pub use config_file::ConfigFileLoadError;
+pub use config_file::{ConfigFile, ConfigFileLoadError};
+#[cfg(feature = "time")]
+pub use time::{InvalidTimestampFormat, TimestampStyle};
 
+pub use global::{flush_guard, global_logger, set_global_logger, FlushGuard};
 pub use level::Level;
+pub use panic_hook::{install_panic_hook, PanicHook};
 
 #[cfg(feature = "log_files")]
-use crate::existing_log_handler::ExistingLogHandler;
+use crate::disk_guard::DiskGuardState;
+#[cfg(feature = "log_files")]
+use crate::existing_log_handler::{
+    ExistingLogHandler, ExistingLogHandlerOpenError, OpenSettings, OpenedLogFile
+};
+#[cfg(feature = "log_files")]
+use crate::file_output::{FileOutput, OpenFileOutput};
+#[cfg(feature = "log_files")]
+use crate::flush_policy::{FlushPolicy, FlushState};
+#[cfg(feature = "log_files")]
+use crate::write_error_policy::WriteErrorPolicy;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 #[cfg(feature = "log_files")]
@@ -153,29 +247,53 @@ use std::fs::File;
 #[cfg(feature = "log_files")]
 use std::io::{prelude::*, BufWriter};
 #[cfg(feature = "log_files")]
-use std::path::PathBuf;
-
-#[cfg(feature = "config")]
-use config_file::ConfigFile;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "log_files")]
+use std::thread;
 
+use capture::Capture;
+use clock::{Clock, SystemClock};
 use color::TermColor;
+use flight_recorder::{DumpTarget, FlightRecorder, Recording};
 use lazy_static::lazy_static;
 use log_message::LogMessage;
+use rate_limit::{Duplicates, RateLimit, RateLimiter};
+use sink::{Record, Sink};
+use std::cell::Cell;
+use std::panic::Location;
+#[cfg(feature = "log_files")]
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
 
 lazy_static!(
-    /// The global logger.
+    /// The default global logger. The unprefixed macros log to this unless
+    /// it has been replaced with [`set_global_logger`].
     pub static ref LOGGER: Logger = {
-        let mut logger = Logger::new();
+        let logger = Logger::new();
 
+        // There is nobody to return problems to here, so they are logged.
         #[cfg(feature = "env")]
-        environment::configure(&mut logger);
+        for invalid in environment::configure(&logger) {
+            logger.warn(&invalid.to_string());
+        }
 
         logger
     };
 );
 
+/// The level of a new logger.
+pub(crate) const DEFAULT_LEVEL: Level = Level::Debug;
+/// Whether or not a new logger prints colors.
+pub(crate) const DEFAULT_COLOR: bool = true;
+/// Whether or not a new logger shows the time.
+#[cfg(feature = "time")]
+pub(crate) const DEFAULT_SHOW_TIME: bool = true;
+/// How a new logger handles an existing log file.
+#[cfg(feature = "log_files")]
+pub(crate) const DEFAULT_EXISTING_LOG_HANDLER: ExistingLogHandler = ExistingLogHandler::Overwrite;
+
 // TODO: Scoped references that are basically references for certain files and
 //       store the scope name and point to the logger. Then in that scope
 //       the scoped ref can be used and it will just log with the scope name
@@ -238,13 +356,116 @@ pub struct Logger {
     /// An internal cache for a file writer. May or may not be set.
     #[cfg(feature = "log_files")]
     log_writer: Mutex<Option<BufWriter<File>>>,
+    /// Where the file of the log writer actually is, while it is set.
+    #[cfg(feature = "log_files")]
+    active_log_path: Mutex<Option<PathBuf>>,
+    /// The device and inode of the file of the log writer, while it is set.
+    /// Always none on platforms other than Unix.
+    #[cfg(feature = "log_files")]
+    log_file_id: Mutex<Option<FileId>>,
+    /// Whether or not to reopen the log file when the file at the log path
+    /// is no longer the one being written to.
+    #[cfg(feature = "log_files")]
+    reopen_on_file_change: AtomicBool,
+    /// Set to reopen the log file before the next message is written. This
+    /// is shared with signal handlers.
+    #[cfg(feature = "log_files")]
+    reopen_requested: Arc<AtomicBool>,
     /// Setting for how existing log files should be handled. Such as appending,
     /// rewriting, or renaming.
     #[cfg(feature = "log_files")]
     existing_log_handler: Mutex<ExistingLogHandler>,
+    /// Where existing log files are moved to when they are renamed.
+    #[cfg(feature = "log_files")]
+    log_archive_directory: Mutex<Option<PathBuf>>,
+    /// When messages written to the log file are flushed.
+    #[cfg(feature = "log_files")]
+    flush_policy: Mutex<FlushPolicy>,
+    /// What the flush policy needs to remember between messages.
+    #[cfg(feature = "log_files")]
+    flush_state: Mutex<FlushState>,
+    /// Whether or not error messages are synced to the disk.
+    #[cfg(feature = "log_files")]
+    sync_on_error: AtomicBool,
+    /// Whether or not each message is written to the log file with a single
+    /// write, instead of being buffered.
+    #[cfg(feature = "log_files")]
+    atomic_appends: AtomicBool,
+    /// Whether or not the log file is locked while a message is written.
+    #[cfg(feature = "file_lock")]
+    file_locking: AtomicBool,
+    /// What to do when writing to the log file fails.
+    #[cfg(feature = "log_files")]
+    write_error_policy: Mutex<WriteErrorPolicy>,
+    /// Messages kept in memory while writing to the log file is failing. None
+    /// when writing works.
+    #[cfg(feature = "log_files")]
+    log_file_outage: Mutex<Option<Vec<u8>>>,
+    /// How many times writing to the log file has failed.
+    #[cfg(feature = "log_files")]
+    write_error_count: AtomicU64,
+    /// Called when writing to the log file starts failing.
+    #[cfg(feature = "log_files")]
+    write_error_callback: Mutex<Option<WriteErrorCallback>>,
+    /// Whether or not missing parent directories of the log file are created.
+    #[cfg(feature = "log_files")]
+    create_log_directories: AtomicBool,
+    /// The permission mode of created log directories, if not the default.
+    #[cfg(feature = "log_files")]
+    log_directory_mode: Mutex<Option<u32>>,
+    /// The most bytes the log file and its rotated files may take up.
+    #[cfg(feature = "log_files")]
+    max_log_bytes: Mutex<Option<u64>>,
+    /// Whether or not renamed logs are deleted to stay under the size limit.
+    #[cfg(feature = "log_files")]
+    prune_log_archives: AtomicBool,
+    /// The renamed logs made by this logger, oldest first. Only these are
+    /// deleted for the size limit.
+    #[cfg(feature = "log_files")]
+    log_archives: Mutex<Vec<PathBuf>>,
+    /// The least free space on the disk for file logging to continue.
+    #[cfg(feature = "disk_space")]
+    min_free_space: Mutex<Option<u64>>,
+    /// What the disk limits need to remember between messages.
+    #[cfg(feature = "log_files")]
+    disk_guard: Mutex<DiskGuardState>,
+    /// Extra log files that each get a range of levels.
+    #[cfg(feature = "log_files")]
+    log_files: Mutex<Vec<OpenFileOutput>>,
     /// The format for the timestamp, if defined.
     #[cfg(feature = "time")]
-    timestamp_format: Mutex<Option<String>>
+    timestamp_format: Mutex<Option<String>>,
+    /// How the timestamp is written.
+    #[cfg(feature = "time")]
+    timestamp_style: Mutex<TimestampStyle>,
+    /// Whether the timestamp is in UTC rather than the local timezone.
+    #[cfg(feature = "time")]
+    timestamp_utc: AtomicBool,
+    /// Whether or not to include the current thread in log entries.
+    show_thread: AtomicBool,
+    /// Whether or not to include the process ID in log entries.
+    show_process_id: AtomicBool,
+    /// When the logger started, for elapsed timestamps.
+    #[cfg(feature = "time")]
+    start_time: Mutex<SystemTime>,
+    /// Extra outputs that are given every message that is logged.
+    sinks: Mutex<Vec<Arc<dyn Sink>>>,
+    /// The last messages, kept to be dumped when something goes wrong.
+    flight_recorder: Mutex<Option<Recording>>,
+    /// Whether or not there is a flight recorder, so that messages below the
+    /// level can be skipped without locking it.
+    flight_recording: AtomicBool,
+    /// The most messages logged from each call site in an interval.
+    rate_limit: Mutex<Option<RateLimit>>,
+    /// How many messages each call site logged recently.
+    rate_limiter: Mutex<RateLimiter>,
+    /// Whether or not repeats of the last message are counted instead of
+    /// logged.
+    collapse_duplicates: AtomicBool,
+    /// The last message logged, and how many times it was repeated.
+    duplicates: Mutex<Duplicates>,
+    /// Where the current time comes from.
+    clock: Mutex<Arc<dyn Clock>>
 }
 
 impl Logger {
@@ -253,40 +474,116 @@ impl Logger {
     /// or mutex reference.
     pub fn new() -> Logger {
         Logger {
-            level: Mutex::new(Level::Debug),
-            color: AtomicBool::new(true),
+            level: Mutex::new(DEFAULT_LEVEL),
+            color: AtomicBool::new(DEFAULT_COLOR),
             #[cfg(feature = "log_files")]
             log_file_color: AtomicBool::new(false),
             #[cfg(feature = "time")]
-            show_time: AtomicBool::new(true),
+            show_time: AtomicBool::new(DEFAULT_SHOW_TIME),
             #[cfg(feature = "log_files")]
             log_path: Mutex::new(None),
             #[cfg(feature = "log_files")]
             log_writer: Mutex::new(None),
             #[cfg(feature = "log_files")]
-            existing_log_handler: Mutex::new(ExistingLogHandler::Overwrite),
+            active_log_path: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            log_file_id: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            reopen_on_file_change: AtomicBool::new(false),
+            #[cfg(feature = "log_files")]
+            reopen_requested: Arc::new(AtomicBool::new(false)),
+            #[cfg(feature = "log_files")]
+            existing_log_handler: Mutex::new(DEFAULT_EXISTING_LOG_HANDLER),
+            #[cfg(feature = "log_files")]
+            log_archive_directory: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            flush_policy: Mutex::new(FlushPolicy::default()),
+            #[cfg(feature = "log_files")]
+            flush_state: Mutex::new(FlushState::new(SystemTime::now())),
+            #[cfg(feature = "log_files")]
+            sync_on_error: AtomicBool::new(false),
+            #[cfg(feature = "log_files")]
+            atomic_appends: AtomicBool::new(false),
+            #[cfg(feature = "file_lock")]
+            file_locking: AtomicBool::new(false),
+            #[cfg(feature = "log_files")]
+            write_error_policy: Mutex::new(WriteErrorPolicy::default()),
+            #[cfg(feature = "log_files")]
+            log_file_outage: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            write_error_count: AtomicU64::new(0),
+            #[cfg(feature = "log_files")]
+            write_error_callback: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            create_log_directories: AtomicBool::new(false),
+            #[cfg(feature = "log_files")]
+            log_directory_mode: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            max_log_bytes: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            prune_log_archives: AtomicBool::new(false),
+            #[cfg(feature = "log_files")]
+            log_archives: Mutex::new(Vec::new()),
+            #[cfg(feature = "disk_space")]
+            min_free_space: Mutex::new(None),
+            #[cfg(feature = "log_files")]
+            disk_guard: Mutex::new(DiskGuardState::new()),
+            #[cfg(feature = "log_files")]
+            log_files: Mutex::new(Vec::new()),
+            #[cfg(feature = "time")]
+            timestamp_format: Mutex::new(None),
+            #[cfg(feature = "time")]
+            timestamp_style: Mutex::new(TimestampStyle::default()),
+            #[cfg(feature = "time")]
+            timestamp_utc: AtomicBool::new(false),
+            show_thread: AtomicBool::new(false),
+            show_process_id: AtomicBool::new(false),
             #[cfg(feature = "time")]
-            timestamp_format: Mutex::new(None)
+            start_time: Mutex::new(SystemTime::now()),
+            sinks: Mutex::new(Vec::new()),
+            flight_recorder: Mutex::new(None),
+            flight_recording: AtomicBool::new(false),
+            rate_limit: Mutex::new(None),
+            rate_limiter: Mutex::new(RateLimiter::new()),
+            collapse_duplicates: AtomicBool::new(false),
+            duplicates: Mutex::new(Duplicates::new()),
+            clock: Mutex::new(Arc::new(SystemClock))
         }
     }
 
+    /// Start building a new Logger, with all the settings checked at once.
+    /// See [`LoggerBuilder`].
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::new()
+    }
+
     /// Load all the environment variables available for the logger.
     ///
-    /// This is done automatically on the global instance.
+    /// This is done automatically on the global instance. Any variables that
+    /// are set to invalid values are returned. See [`environment::configure`].
+    #[cfg(feature = "env")]
+    pub fn load_env_vars(&self) -> Vec<environment::InvalidEnvVar> {
+        environment::configure(self)
+    }
+
+    /// Load all the environment variables available for the logger, using
+    /// a prefix other than `PL_` for the variable names.
+    ///
+    /// See [`environment::configure_with_prefix`].
     #[cfg(feature = "env")]
-    pub fn load_env_vars(&self) {
-        environment::configure(self);
+    pub fn load_env_vars_with_prefix(&self, prefix: &str) -> Vec<environment::InvalidEnvVar> {
+        environment::configure_with_prefix(self, prefix)
     }
 
     /// Set the log level. Only logs with a level equal to or higher than the
     /// set level will be logged.
     pub fn set_level(&self, level: Level) {
-        *self.level.lock().unwrap() = level;
+        *lock(&self.level) = level;
     }
 
     /// Get the current log level.
     pub fn get_level(&self) -> Level {
-        *self.level.lock().unwrap()
+        *lock(&self.level)
     }
 
     // TODO: I don't know enough about the ordering of the atomic bool to
@@ -322,13 +619,249 @@ impl Logger {
     /// Set how existing log files should be handled.
     #[cfg(feature = "log_files")]
     pub fn set_existing_log_handler(&self, handler: ExistingLogHandler) {
-        *self.existing_log_handler.lock().unwrap() = handler;
+        *lock(&self.existing_log_handler) = handler;
     }
 
     /// Get how existing log files should be handled.
     #[cfg(feature = "log_files")]
     pub fn get_existing_log_handler(&self) -> ExistingLogHandler {
-        *self.existing_log_handler.lock().unwrap()
+        *lock(&self.existing_log_handler)
+    }
+
+    /// Set the directory that existing log files are moved to by
+    /// [`ExistingLogHandler::Rename`]. If none, which is the default, they
+    /// stay next to the log file.
+    ///
+    /// The directory is not created, and a log file can not be opened if it
+    /// does not exist.
+    #[cfg(feature = "log_files")]
+    pub fn set_log_archive_directory<P: Into<PathBuf>>(&self, directory: Option<P>) {
+        *lock(&self.log_archive_directory) = directory.map(Into::into);
+    }
+
+    /// Get the directory that existing log files are moved to when renamed.
+    #[cfg(feature = "log_files")]
+    pub fn get_log_archive_directory(&self) -> Option<PathBuf> {
+        lock(&self.log_archive_directory).clone()
+    }
+
+    /// Set when messages written to the log file are flushed. The default is
+    /// [`FlushPolicy::Never`], which only flushes when the buffer is full or
+    /// [`flush`](Self::flush) is called.
+    #[cfg(feature = "log_files")]
+    pub fn set_flush_policy(&self, policy: FlushPolicy) {
+        *lock(&self.flush_policy) = policy;
+        lock(&self.flush_state).flushed(self.now());
+    }
+
+    /// Get when messages written to the log file are flushed.
+    #[cfg(feature = "log_files")]
+    pub fn get_flush_policy(&self) -> FlushPolicy {
+        *lock(&self.flush_policy)
+    }
+
+    /// Set whether or not the log file is flushed and synced to the disk,
+    /// with `fsync`, after every error message. This keeps error messages
+    /// even if the whole system crashes, but is slow. Off by default.
+    #[cfg(feature = "log_files")]
+    pub fn set_sync_on_error(&self, sync: bool) {
+        self.sync_on_error.store(sync, Ordering::Relaxed);
+    }
+
+    /// Get whether or not error messages are synced to the disk.
+    #[cfg(feature = "log_files")]
+    pub fn get_sync_on_error(&self) -> bool {
+        self.sync_on_error.load(Ordering::Relaxed)
+    }
+
+    /// Set whether or not each message is written to the log file with a
+    /// single write, instead of being buffered. Off by default.
+    ///
+    /// This is for log files that are shared between processes. While it is
+    /// on, the log file is always opened for appending, whatever the existing
+    /// log handler, so every message is added to the end of the file as a
+    /// whole and the lines of different processes never mix. Handlers such as
+    /// [`ExistingLogHandler::Overwrite`] still empty the file when it is
+    /// opened. A file that is already open is reopened before the next
+    /// message. The flush policy has no effect while this is on, as nothing
+    /// is buffered.
+    #[cfg(feature = "log_files")]
+    pub fn set_atomic_appends(&self, atomic: bool) {
+        let was_atomic = self.atomic_appends.swap(atomic, Ordering::Relaxed);
+        if atomic && !was_atomic {
+            self.request_reopen();
+        }
+    }
+
+    /// Get whether or not each message is written with a single write.
+    #[cfg(feature = "log_files")]
+    pub fn get_atomic_appends(&self) -> bool {
+        self.atomic_appends.load(Ordering::Relaxed)
+    }
+
+    /// Set whether or not the log file is locked with an advisory `flock`
+    /// while each message is written, for when other processes writing to the
+    /// file lock it too. This only has an effect with
+    /// [`set_atomic_appends`](Self::set_atomic_appends), and only on Unix.
+    /// Off by default.
+    #[cfg(feature = "file_lock")]
+    pub fn set_file_locking(&self, locking: bool) {
+        self.file_locking.store(locking, Ordering::Relaxed);
+    }
+
+    /// Get whether or not the log file is locked while writing to it.
+    #[cfg(feature = "file_lock")]
+    pub fn get_file_locking(&self) -> bool {
+        self.file_locking.load(Ordering::Relaxed)
+    }
+
+    /// Set what happens when writing to the log file fails. The default is
+    /// [`WriteErrorPolicy::Disable`].
+    #[cfg(feature = "log_files")]
+    pub fn set_write_error_policy(&self, policy: WriteErrorPolicy) {
+        *lock(&self.write_error_policy) = policy;
+    }
+
+    /// Get what happens when writing to the log file fails.
+    #[cfg(feature = "log_files")]
+    pub fn get_write_error_policy(&self) -> WriteErrorPolicy {
+        *lock(&self.write_error_policy)
+    }
+
+    /// Set a function that is called with the error when writing to the log
+    /// file, or one of the extra log files, fails. It is not called again for
+    /// messages while the file is still failing, only once it worked in
+    /// between.
+    ///
+    /// The function must not log to this logger.
+    #[cfg(feature = "log_files")]
+    pub fn set_write_error_callback<F>(&self, callback: F)
+    where
+        F: Fn(&std::io::Error) + Send + Sync + 'static
+    {
+        *lock(&self.write_error_callback) = Some(WriteErrorCallback(Arc::new(callback)));
+    }
+
+    /// Remove the function set with
+    /// [`set_write_error_callback`](Self::set_write_error_callback).
+    #[cfg(feature = "log_files")]
+    pub fn remove_write_error_callback(&self) {
+        *lock(&self.write_error_callback) = None;
+    }
+
+    /// Get how many times writing to the log file or the extra log files has
+    /// failed, including every attempt to write to them again.
+    #[cfg(feature = "log_files")]
+    pub fn get_write_error_count(&self) -> u64 {
+        self.write_error_count.load(Ordering::Relaxed)
+    }
+
+    /// Whether or not writing to the log file is currently failing, and
+    /// messages are handled by the write error policy instead.
+    #[cfg(feature = "log_files")]
+    pub fn is_log_file_failing(&self) -> bool {
+        lock(&self.log_file_outage).is_some()
+    }
+
+    /// Set whether or not [`set_log_path`](Self::set_log_path) creates the
+    /// missing parent directories of the log file. This is off by default.
+    #[cfg(feature = "log_files")]
+    pub fn set_create_log_directories(&self, create: bool) {
+        self.create_log_directories.store(create, Ordering::Relaxed);
+    }
+
+    /// Get whether or not missing parent directories of the log file are
+    /// created.
+    #[cfg(feature = "log_files")]
+    pub fn get_create_log_directories(&self) -> bool {
+        self.create_log_directories.load(Ordering::Relaxed)
+    }
+
+    /// Set the Unix permission mode of log directories that are created, such
+    /// as `0o750`. The umask still applies. If none, which is the default,
+    /// the usual permissions are used. This has no effect on other platforms.
+    #[cfg(feature = "log_files")]
+    pub fn set_log_directory_mode(&self, mode: Option<u32>) {
+        *lock(&self.log_directory_mode) = mode;
+    }
+
+    /// Get the permission mode of log directories that are created.
+    #[cfg(feature = "log_files")]
+    pub fn get_log_directory_mode(&self) -> Option<u32> {
+        *lock(&self.log_directory_mode)
+    }
+
+    /// Set the most bytes that the log file and the files rotated or renamed
+    /// from it may take up together, or none for no limit. When they go
+    /// over, file logging is paused, with one warning, until there is room
+    /// again, such as after old files are removed. No files are deleted
+    /// unless [`set_prune_log_archives`](Self::set_prune_log_archives) is on.
+    ///
+    /// The rotated files are found next to the log file and in the log
+    /// archive directory, such as `server.3.log` or `server.log.1` for
+    /// `server.log`. Extra log files added with
+    /// [`add_log_file`](Self::add_log_file) are not limited.
+    ///
+    /// The disk is looked at about once a second, so the limit can be
+    /// passed by what is buffered and not yet written.
+    #[cfg(feature = "log_files")]
+    pub fn set_max_log_bytes(&self, max_bytes: Option<u64>) {
+        *lock(&self.max_log_bytes) = max_bytes;
+        lock(&self.disk_guard).check_soon();
+    }
+
+    /// Get the most bytes that the log file and its rotated files may take
+    /// up together.
+    #[cfg(feature = "log_files")]
+    pub fn get_max_log_bytes(&self) -> Option<u64> {
+        *lock(&self.max_log_bytes)
+    }
+
+    /// Set whether or not the oldest renamed logs are deleted when the log
+    /// files go over [`set_max_log_bytes`](Self::set_max_log_bytes). This is
+    /// off by default.
+    ///
+    /// Only logs that this logger moved away with
+    /// [`ExistingLogHandler::Rename`] are deleted. Files rotated by other
+    /// tools, and logs renamed before the program started, are never
+    /// deleted, so file logging can still pause because of them.
+    #[cfg(feature = "log_files")]
+    pub fn set_prune_log_archives(&self, prune: bool) {
+        self.prune_log_archives.store(prune, Ordering::Relaxed);
+        lock(&self.disk_guard).check_soon();
+    }
+
+    /// Get whether or not the oldest renamed logs are deleted to stay under
+    /// the size limit.
+    #[cfg(feature = "log_files")]
+    pub fn get_prune_log_archives(&self) -> bool {
+        self.prune_log_archives.load(Ordering::Relaxed)
+    }
+
+    /// Set the least free space, in bytes, that the disk of the log file
+    /// must have, or none for no limit. Below it, file logging is paused,
+    /// with one warning, and it starts again by itself once there is space.
+    /// Messages are still given to the other outputs while paused.
+    ///
+    /// Only does anything on Unix.
+    #[cfg(feature = "disk_space")]
+    pub fn set_min_free_space(&self, min_bytes: Option<u64>) {
+        *lock(&self.min_free_space) = min_bytes;
+        lock(&self.disk_guard).check_soon();
+    }
+
+    /// Get the least free space that the disk of the log file must have.
+    #[cfg(feature = "disk_space")]
+    pub fn get_min_free_space(&self) -> Option<u64> {
+        *lock(&self.min_free_space)
+    }
+
+    /// Whether or not file logging is paused because of
+    /// [`set_max_log_bytes`](Self::set_max_log_bytes) or
+    /// `set_min_free_space`.
+    #[cfg(feature = "log_files")]
+    pub fn is_log_file_paused(&self) -> bool {
+        lock(&self.disk_guard).is_paused()
     }
 
     /// Set whether or not the logger should show the timestamp. True means
@@ -345,59 +878,151 @@ impl Logger {
         self.show_time.load(Ordering::Relaxed)
     }
 
+    /// Set whether or not the logger should show the name of the thread that
+    /// logged each message, or its ID if it has no name. Off by default.
+    pub fn set_should_show_thread(&self, show_thread: bool) {
+        self.show_thread.store(show_thread, Ordering::Relaxed);
+    }
+
+    /// Get whether or not the logger should show the thread of each message.
+    pub fn should_show_thread(&self) -> bool {
+        self.show_thread.load(Ordering::Relaxed)
+    }
+
+    /// Set whether or not the logger should show the process ID on each
+    /// message. Off by default.
+    pub fn set_should_show_process_id(&self, show_process_id: bool) {
+        self.show_process_id
+            .store(show_process_id, Ordering::Relaxed);
+    }
+
+    /// Get whether or not the logger should show the process ID on each
+    /// message.
+    pub fn should_show_process_id(&self) -> bool {
+        self.show_process_id.load(Ordering::Relaxed)
+    }
+
     // TODO: Consider maybe just returning a Result<(), String> type that the
     //       caller can decide what to do with that reasoning. Or even a custom
     //       error enum that implements Display
     /// Set the path to log to.
     ///
-    /// This method will never panic, but does return a boolean value of whether
-    /// or not the path was actually set. The **path is not set** under the
-    /// following conditions:
-    /// 1. If the path does not exist.
+    /// This method will never panic, but does return whether or not the path
+    /// was actually set. The **path is not set** under the following
+    /// conditions:
+    /// 1. If the directory of the file does not exist, and directories are not
+    ///    created.
     /// 1. If the path is a directory
     /// 1. If the file does not exist, and no permission to create it.
-    /// In the event of the path not being set, false will be returned and an
-    /// error message will be printed with the reasoning.
+    /// 1. If the file exists, and the existing log handler is
+    ///    [`ExistingLogHandler::FailIfExists`].
     ///
-    /// The method **will not** create directories, but it **will** create files
-    /// if they don't exist.
+    /// The method **will not** create directories unless
+    /// [`set_create_log_directories`](Self::set_create_log_directories) is
+    /// on, but it **will** create files if they don't exist. A file that is
+    /// created is opened straight away.
+    /// An existing file is opened when the first message is logged, using the
+    /// [`ExistingLogHandler`] at that time.
     ///
     /// # Returns
-    /// The return value will be `true` if the path is successfully set, or
-    /// `false` if could not set the path.
+    /// `Ok(())` if the path is successfully set, or a [`SetLogPathError`]
+    /// describing why the path could not be set.
     #[cfg(feature = "log_files")]
-    #[must_use]
     pub fn set_log_path(&self, path: &str) -> Result<(), SetLogPathError> {
-        let path_buf = PathBuf::from(path);
+        self.set_log_path_path(Path::new(path))
+    }
+
+    /// The same as [`set_log_path`](Self::set_log_path), for paths that may
+    /// not be UTF-8.
+    #[cfg(feature = "log_files")]
+    pub(crate) fn set_log_path_path(&self, path: &Path) -> Result<(), SetLogPathError> {
         self.remove_log_writer();
+        let opened = prepare_log_path(
+            path,
+            self.get_existing_log_handler(),
+            self.get_create_log_directories(),
+            self.get_log_directory_mode(),
+            &self.open_settings()
+        )?;
+        self.install_log_path(path.to_path_buf(), opened);
 
-        // Create the file if it doesn't exist, but don't touch directory
-        // structures.
-        if !path_buf.exists() && File::create(path).is_err() {
-            return Err(SetLogPathError::CouldNotCreateLogFile);
-        }
+        Ok(())
+    }
 
-        // Check that it is in fact a file.
-        if !path_buf.is_file() {
-            return Err(SetLogPathError::PathIsNotAFile);
+    /// Start logging to the given path, with the file that was opened for
+    /// it by [`prepare_log_path`], if any.
+    #[cfg(feature = "log_files")]
+    fn install_log_path(&self, path: PathBuf, opened: Option<OpenedLogFile>) {
+        self.remove_log_writer();
+        if let Some(opened) = opened {
+            self.set_log_writer(opened);
         }
-
-        *self.log_path.lock().unwrap() = Some(path_buf);
-
-        Ok(())
+        *lock(&self.log_path) = Some(path);
     }
 
     /// Remove file logging.
     #[cfg(feature = "log_files")]
     pub fn remove_log_path(&self) {
-        *self.log_path.lock().unwrap() = None;
+        *lock(&self.log_path) = None;
         self.remove_log_writer();
     }
 
+    /// Add an extra log file, such as one that only gets errors. The file is
+    /// opened straight away, with its own existing log handler, and the
+    /// missing directories are created if
+    /// [`set_create_log_directories`](Self::set_create_log_directories) is
+    /// on. See [`FileOutput`].
+    ///
+    /// The file is written to like the log file: it is reopened with
+    /// [`reopen`](Self::reopen), and atomic appends, file locking and the
+    /// write error policy apply to it. With [`WriteErrorPolicy::Disable`] a
+    /// file that can not be written to is removed and an error is logged.
+    #[cfg(feature = "log_files")]
+    pub fn add_log_file(&self, output: FileOutput) -> Result<(), SetLogPathError> {
+        let opened = prepare_log_file(
+            output,
+            self.get_create_log_directories(),
+            self.get_log_directory_mode(),
+            &self.open_settings()
+        )?;
+        lock(&self.log_files).push(opened);
+
+        Ok(())
+    }
+
+    /// Flush and remove all of the extra log files added with
+    /// [`add_log_file`](Self::add_log_file).
+    #[cfg(feature = "log_files")]
+    pub fn clear_log_files(&self) {
+        let now = self.now();
+        let mut log_files = lock(&self.log_files);
+        for log_file in log_files.iter_mut() {
+            let _ = log_file.flush(now);
+        }
+        log_files.clear();
+    }
+
+    /// Get the extra log files added with [`add_log_file`](Self::add_log_file).
+    #[cfg(feature = "log_files")]
+    pub fn get_log_files(&self) -> Vec<FileOutput> {
+        lock(&self.log_files)
+            .iter()
+            .map(|log_file| log_file.output.clone())
+            .collect()
+    }
+
     /// Get the path to the file that the logger is logging to.
     #[cfg(feature = "log_files")]
     pub fn get_log_path(&self) -> Option<PathBuf> {
-        (*self.log_path.lock().unwrap()).as_ref().cloned()
+        (*lock(&self.log_path)).as_ref().cloned()
+    }
+
+    /// Get the path of the file that is actually being written to, if it has
+    /// been opened yet. This is the log path, except for
+    /// [`ExistingLogHandler::NewNumbered`] where it is the numbered file.
+    #[cfg(feature = "log_files")]
+    pub fn get_active_log_path(&self) -> Option<PathBuf> {
+        lock(&self.active_log_path).clone()
     }
 
     /// Get the format of the timestamp on log messages.
@@ -412,171 +1037,1089 @@ impl Logger {
     }
 
     /// Set the format of the timestamp on log messages.
+    ///
+    /// The format uses the syntax of [`chrono::format::strftime`]. An invalid
+    /// format is rejected and the previous format is kept.
     #[cfg(feature = "time")]
-    pub fn set_timestamp_format(&self, value: Option<String>) {
+    pub fn set_timestamp_format(
+        &self,
+        value: Option<String>
+    ) -> Result<(), InvalidTimestampFormat> {
+        if let Some(ref format) = value {
+            time::validate_format(format)?;
+        }
+
         match self.timestamp_format.lock() {
             Ok(mut inner) => *inner = value,
-            Err(_) => error!("Could not set timestamp format.")
+            Err(_) => self.error("Could not set timestamp format.")
         }
+
+        Ok(())
     }
 
-    /// Set the file writer to write actual data to. This method should only
-    /// be called internally.
-    #[cfg(feature = "log_files")]
-    fn set_log_writer(&self, buf_writer: BufWriter<File>) {
-        *self.log_writer.lock().unwrap() = Some(buf_writer);
+    /// Set how the timestamp on log messages is written. The default is
+    /// [`TimestampStyle::Format`], which uses the timestamp format.
+    #[cfg(feature = "time")]
+    pub fn set_timestamp_style(&self, style: TimestampStyle) {
+        *lock(&self.timestamp_style) = style;
     }
 
-    /// Remove the file writer. This method should only be called internally.
-    #[cfg(feature = "log_files")]
-    fn remove_log_writer(&self) {
-        *self.log_writer.lock().unwrap() = None;
+    /// Get how the timestamp on log messages is written.
+    #[cfg(feature = "time")]
+    pub fn get_timestamp_style(&self) -> TimestampStyle {
+        *lock(&self.timestamp_style)
     }
 
-    /// Whether or not the logger writer has already been set. If this method
-    /// is false but log path is set, a log writer should be created.
-    /// See [`set_log_writer_if_not_set`](#method.set_log_writer_if_not_set)
-    #[cfg(feature = "log_files")]
-    fn has_log_writer(&self) -> bool {
-        if let Ok(lw) = self.log_writer.lock() {
-            return lw.is_some();
-        }
+    /// Set whether or not timestamps are in UTC. False means the local
+    /// timezone is used, which is the default.
+    #[cfg(feature = "time")]
+    pub fn set_timestamp_utc(&self, utc: bool) {
+        self.timestamp_utc.store(utc, Ordering::Relaxed);
+    }
 
-        false
+    /// Get whether or not timestamps are in UTC. False means the local
+    /// timezone is used.
+    #[cfg(feature = "time")]
+    pub fn get_timestamp_utc(&self) -> bool {
+        self.timestamp_utc.load(Ordering::Relaxed)
     }
 
-    /// If there is a log path set, but no log writer, create a log writer.
-    #[cfg(feature = "log_files")]
-    fn set_log_writer_if_not_set(&self) {
-        if !self.has_log_writer() {
-            if let Some(path) = self.get_log_path() {
-                let file = match self.get_existing_log_handler().open_file(&path) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        error!("Could not open log file: {:?}", e);
-                        self.remove_log_path();
-                        return;
-                    }
-                };
-                let buf_writer = BufWriter::new(file);
-                self.set_log_writer(buf_writer);
-            }
-        }
+    /// Get the time that [`TimestampStyle::Elapsed`] timestamps count from.
+    /// This is when the logger was created, or when the clock was last set.
+    #[cfg(feature = "time")]
+    pub fn get_start_time(&self) -> SystemTime {
+        *lock(&self.start_time)
     }
 
-    /// Given a log message, write it to the file. Will gracefully handle
-    /// errors.
+    /// Set where the logger gets the current time from. This is used for
+    /// timestamps, and for the names of renamed log files.
     ///
-    /// Will do nothing when the `log_files` feature is not enabled.
-    #[cfg(feature = "log_files")]
-    fn log_message_to_file(&self, log_message: &mut LogMessage) {
-        // Write to file
-        self.set_log_writer_if_not_set();
-        if let Ok(ref mut log_writer) = self.log_writer.lock() {
-            if log_writer.is_some() {
-                let formatted_message = log_message.formatted(self.get_log_file_color());
-                if let Err(e) = log_writer
-                    .as_mut()
-                    .unwrap()
-                    .write(formatted_message.as_bytes())
-                {
-                    // Remove the writer and the path, then log an error
-                    self.remove_log_writer();
-                    self.remove_log_path();
-                    self.error(&format!("Log file could not be written to: {e:?}"));
-                }
-            }
+    /// The default is the [`SystemClock`]. See the [`clock`] module. Elapsed
+    /// timestamps count from the time of the new clock when it is set.
+    pub fn set_clock<C: Clock + 'static>(&self, clock: C) {
+        self.set_clock_arc(Arc::new(clock));
+    }
+
+    /// Set the clock from an already shared clock.
+    fn set_clock_arc(&self, clock: Arc<dyn Clock>) {
+        #[cfg(feature = "time")]
+        {
+            *lock(&self.start_time) = clock.now();
         }
+        *lock(&self.clock) = clock;
     }
 
-    /// Given a log message, write it to the file. Will gracefully handle
-    /// errors.
-    ///
-    /// Will do nothing when the `log_files` feature is not enabled.
-    #[cfg(not(feature = "log_files"))]
-    fn log_message_to_file(&self, _msg: &mut LogMessage) {
-        // Intentionally do nothing when the feature is not enabled
+    /// The current time according to the logger's clock.
+    pub fn now(&self) -> SystemTime {
+        // The clock is cloned out so that the lock is not held while the
+        // clock is called.
+        let clock = lock(&self.clock).clone();
+        let _in_logger = InLogger::enter();
+        clock.now()
     }
 
-    /// Actually write the log message to the file and stdout. Should only be
-    /// called internally by the `debug`, `info`, `warn`, and `error` methods.
-    fn log_message(&self, level: Level, message: &str) {
-        let mut log_message = LogMessage::new(&self.prefix(), message, level);
+    /// Add an extra output that is given every message that passes the level
+    /// filter, after it is printed and written to the log file.
+    pub fn add_sink<S: Sink + 'static>(&self, sink: S) {
+        lock(&self.sinks).push(Arc::new(sink));
+    }
+
+    /// Remove all the sinks added with [`add_sink`](Self::add_sink) or
+    /// [`capture`](Self::capture).
+    pub fn clear_sinks(&self) {
+        let _in_logger = InLogger::enter();
+        lock(&self.sinks).clear();
+    }
+
+    /// Start keeping every message logged from now on in memory, and return
+    /// the [`Capture`] that holds them. This is mainly for checking what was
+    /// logged in tests, see the [`capture`] module.
+    pub fn capture(&self) -> Capture {
+        let capture = Capture::new();
+        self.add_sink(capture.clone());
+        capture
+    }
+
+    /// Set the most messages logged from each place in the code in an
+    /// interval, such as a loop that logs the same warning over and over. If
+    /// none, there is no limit. See [`RateLimit`].
+    ///
+    /// The limit of a call site can also be set with the `_limited` macros,
+    /// such as [`warn_limited!`], which is used instead of this one.
+    pub fn set_rate_limit(&self, limit: Option<RateLimit>) {
+        *lock(&self.rate_limit) = limit;
+    }
+
+    /// Get the most messages logged from each call site in an interval.
+    pub fn get_rate_limit(&self) -> Option<RateLimit> {
+        *lock(&self.rate_limit)
+    }
+
+    /// Set whether or not a message with the same level and text as the one
+    /// before it is counted instead of logged. The count is logged as
+    /// `last message repeated 5123 times` before the next different message,
+    /// or when the logger is flushed.
+    pub fn set_collapse_duplicates(&self, collapse: bool) {
+        self.collapse_duplicates.store(collapse, Ordering::Relaxed);
+    }
+
+    /// Get whether or not repeats of the last message are collapsed.
+    pub fn get_collapse_duplicates(&self) -> bool {
+        self.collapse_duplicates.load(Ordering::Relaxed)
+    }
+
+    /// Keep the last messages in memory, including ones below the level of
+    /// the logger, and write them out when an error is logged or the program
+    /// panics. See [`FlightRecorder`]. If none, no messages are kept.
+    ///
+    /// Any messages that were kept before are forgotten.
+    pub fn set_flight_recorder(&self, recorder: Option<FlightRecorder>) {
+        let mut flight_recorder = lock(&self.flight_recorder);
+        self.flight_recording
+            .store(recorder.is_some(), Ordering::Relaxed);
+        *flight_recorder = recorder.map(Recording::new);
+    }
+
+    /// Get the settings of the flight recorder, if there is one.
+    pub fn get_flight_recorder(&self) -> Option<FlightRecorder> {
+        lock(&self.flight_recorder)
+            .as_ref()
+            .map(|recording| recording.settings.clone())
+    }
+
+    /// A copy of the messages the flight recorder is keeping, oldest first.
+    /// Empty if there is no flight recorder.
+    pub fn flight_recorder_snapshot(&self) -> Vec<Record> {
+        match *lock(&self.flight_recorder) {
+            Some(ref recording) => recording.snapshot(),
+            None => Vec::new()
+        }
+    }
+
+    /// Write out the messages the flight recorder is keeping, and forget
+    /// them. Does nothing if there are none.
+    ///
+    /// A dump to the log file is written like any message, so it is held
+    /// back by the disk limits, and errors writing it are handled by the
+    /// write error policy instead of being returned.
+    pub fn dump_flight_recorder(&self) -> std::io::Result<()> {
+        let _in_logger = InLogger::enter();
+        let dump = match *lock(&self.flight_recorder) {
+            Some(ref mut recording) => Some((recording.take(), recording.settings.dump_to.clone())),
+            None => None
+        };
+
+        match dump {
+            Some((records, target)) => self.write_flight_recorder_dump(&records, &target),
+            None => Ok(())
+        }
+    }
+
+    /// Write messages from the flight recorder, between a header and a
+    /// footer so that they can be told apart from the messages around them.
+    fn write_flight_recorder_dump(
+        &self,
+        records: &[Record],
+        target: &DumpTarget
+    ) -> std::io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut dump = format!(
+            "----- flight recorder: last {} messages -----\n",
+            records.len()
+        );
+        for record in records {
+            let mut log_message =
+                LogMessage::new(&self.prefix(record), &record.message, record.level);
+            dump.push_str(&log_message.non_colorized());
+        }
+        dump.push_str("----- end of flight recorder -----\n");
+
+        match target {
+            DumpTarget::LogFile => {
+                // Written like any message, so that the disk limits and the
+                // write error policy apply to it
+                #[cfg(feature = "log_files")]
+                if self.get_log_path().is_some() {
+                    self.write_log_file_message(&dump, Level::Error, false);
+                    let result = match lock(&self.log_writer).as_mut() {
+                        Some(log_writer) => log_writer.flush(),
+                        None => Ok(())
+                    };
+                    if let Err(e) = result {
+                        self.handle_write_error(e, "");
+                    }
+                    return Ok(());
+                }
+                std::io::Write::write_all(&mut std::io::stderr(), dump.as_bytes())
+            }
+            DumpTarget::Stderr => {
+                std::io::Write::write_all(&mut std::io::stderr(), dump.as_bytes())
+            }
+            DumpTarget::File(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                std::io::Write::write_all(&mut file, dump.as_bytes())
+            }
+        }
+    }
+
+    /// Set the file writer to write actual data to. This method should only
+    /// be called internally.
+    #[cfg(feature = "log_files")]
+    fn set_log_writer(&self, opened: OpenedLogFile) {
+        let id = file_id(opened.file.metadata());
+        if let Some(archived) = opened.archived {
+            lock(&self.log_archives).push(archived);
+        }
+        *lock(&self.log_writer) = Some(BufWriter::new(opened.file));
+        *lock(&self.active_log_path) = Some(opened.path);
+        *lock(&self.log_file_id) = id;
+    }
+
+    /// Remove the file writer. This method should only be called internally.
+    #[cfg(feature = "log_files")]
+    fn remove_log_writer(&self) {
+        *lock(&self.log_writer) = None;
+        *lock(&self.active_log_path) = None;
+        *lock(&self.log_file_id) = None;
+        *lock(&self.log_file_outage) = None;
+    }
+
+    /// Flush the log file and the extra log files, then open the files at
+    /// the same paths again, appending to them. This is for when something
+    /// else, such as logrotate, has moved or deleted the files, which would
+    /// otherwise keep being written to. Missing files are created.
+    ///
+    /// Files that have not been opened yet are left alone. If a file can not
+    /// be opened, the old file is kept, and the first error is returned.
+    #[cfg(feature = "log_files")]
+    pub fn reopen(&self) -> std::io::Result<()> {
+        let mut result = self.reopen_main_log_file();
+        for log_file in lock(&self.log_files).iter_mut() {
+            let file_result = log_file.reopen();
+            if result.is_ok() {
+                result = file_result;
+            }
+        }
+
+        result
+    }
+
+    /// Reopen the log file, but not the extra log files. See
+    /// [`reopen`](Self::reopen).
+    #[cfg(feature = "log_files")]
+    fn reopen_main_log_file(&self) -> std::io::Result<()> {
+        let path = match self.get_active_log_path() {
+            Some(path) => path,
+            None => return Ok(())
+        };
+        let (writer, id) = reopen_log_file(&path, &[])?;
+
+        let mut log_writer = lock(&self.log_writer);
+        let flushed = match log_writer.as_mut() {
+            Some(old) => old.flush(),
+            None => Ok(())
+        };
+        *log_writer = Some(writer);
+        drop(log_writer);
+        *lock(&self.log_file_id) = id;
+
+        flushed
+    }
+
+    /// Reopen the log file before the next message is written to it. See
+    /// [`reopen`](Self::reopen).
+    ///
+    /// Unlike `reopen`, this only sets a flag, so it is safe to call from a
+    /// signal handler.
+    #[cfg(feature = "log_files")]
+    pub fn request_reopen(&self) {
+        self.reopen_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Reopen the log file whenever the process gets `SIGHUP`, which is what
+    /// logrotate and many service managers send after moving log files.
+    ///
+    /// The file is reopened before the next message is written, rather than
+    /// in the signal handler. This replaces the default action for `SIGHUP`,
+    /// which is to end the process.
+    #[cfg(all(feature = "signal", unix))]
+    pub fn reopen_on_sighup(&self) -> std::io::Result<()> {
+        signal_hook::flag::register(
+            signal_hook::consts::SIGHUP,
+            Arc::clone(&self.reopen_requested)
+        )?;
+        Ok(())
+    }
+
+    /// Set whether or not to check that the log file and the extra log files
+    /// are still at their paths before each message, and reopen them if they
+    /// were moved, deleted, or replaced. This is off by default, because it looks up the path for
+    /// every message.
+    ///
+    /// On Unix a replaced file is found by its device and inode. On other
+    /// platforms only a missing file is noticed.
+    #[cfg(feature = "log_files")]
+    pub fn set_reopen_on_file_change(&self, reopen: bool) {
+        self.reopen_on_file_change.store(reopen, Ordering::Relaxed);
+    }
+
+    /// Get whether or not the log file is reopened when it is moved, deleted,
+    /// or replaced.
+    #[cfg(feature = "log_files")]
+    pub fn get_reopen_on_file_change(&self) -> bool {
+        self.reopen_on_file_change.load(Ordering::Relaxed)
+    }
+
+    /// Whether or not the file at the active log path is no longer the file
+    /// being written to. Always false unless
+    /// [`set_reopen_on_file_change`](Self::set_reopen_on_file_change) is on.
+    #[cfg(feature = "log_files")]
+    fn log_file_changed(&self) -> bool {
+        if !self.get_reopen_on_file_change() {
+            return false;
+        }
+        let path = match self.get_active_log_path() {
+            Some(path) => path,
+            None => return false
+        };
+
+        match std::fs::metadata(path) {
+            Ok(metadata) => {
+                let id = *lock(&self.log_file_id);
+                id.is_some() && id != file_id(Ok(metadata))
+            }
+            Err(_) => true
+        }
+    }
+
+    /// Open the log file at the given path with the current existing log
+    /// handler and settings.
+    #[cfg(feature = "log_files")]
+    fn open_log_file(&self, path: &Path) -> Result<OpenedLogFile, ExistingLogHandlerOpenError> {
+        self.get_existing_log_handler()
+            .open_file_with(path, &self.open_settings())
+    }
+
+    /// The settings that log files are opened with.
+    #[cfg(feature = "log_files")]
+    fn open_settings(&self) -> OpenSettings {
+        OpenSettings {
+            now: self.now(),
+            archive_directory: self.get_log_archive_directory(),
+            append_only: self.get_atomic_appends()
+        }
+    }
+
+    /// Whether or not the logger writer has already been set. If this method
+    /// is false but log path is set, a log writer should be created.
+    /// See [`set_log_writer_if_not_set`](#method.set_log_writer_if_not_set)
+    #[cfg(feature = "log_files")]
+    fn has_log_writer(&self) -> bool {
+        if let Ok(lw) = self.log_writer.lock() {
+            return lw.is_some();
+        }
+
+        false
+    }
+
+    /// If there is a log path set, but no log writer, create a log writer.
+    #[cfg(feature = "log_files")]
+    fn set_log_writer_if_not_set(&self) {
+        if !self.has_log_writer() {
+            if let Some(path) = self.get_log_path() {
+                match self.open_log_file(&path) {
+                    Ok(opened) => self.set_log_writer(opened),
+                    Err(e) => {
+                        // The path is removed first so that logging the
+                        // error does not try to open the file again.
+                        self.remove_log_path();
+                        self.error(&format!("Could not open log file: {:?}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Given a log message, write it to the file. Will gracefully handle
+    /// errors.
+    ///
+    /// Will do nothing when the `log_files` feature is not enabled.
+    #[cfg(feature = "log_files")]
+    fn log_message_to_file(&self, log_message: &mut LogMessage, level: Level, reopen: bool) {
+        let formatted_message = log_message.formatted(self.get_log_file_color());
+        self.write_log_file_message(&formatted_message, level, reopen);
+    }
+
+    /// Write a formatted message to the log file, checking the disk limits
+    /// first and handling errors with the write error policy.
+    #[cfg(feature = "log_files")]
+    fn write_log_file_message(&self, formatted_message: &str, level: Level, reopen: bool) {
+        if reopen || self.log_file_changed() {
+            if let Err(e) = self.reopen_main_log_file() {
+                self.handle_write_error(e, formatted_message);
+                return;
+            }
+        }
+
+        // An existing log file is handled first, such as by renaming it, so
+        // that the disk limits see the files of this session
+        if !self.is_log_file_failing() {
+            self.set_log_writer_if_not_set();
+        }
+        if !self.disk_usage_allows(formatted_message.len() as u64) {
+            return;
+        }
+
+        // While writing is failing, keep trying to get the file back
+        if let Ok(mut outage) = self.log_file_outage.lock() {
+            if let Some(pending) = outage.as_mut() {
+                let mut data = std::mem::take(pending);
+                data.extend_from_slice(formatted_message.as_bytes());
+                if self.recover_log_file(&data).is_ok() {
+                    *outage = None;
+                } else {
+                    self.write_error_count.fetch_add(1, Ordering::Relaxed);
+                    data.truncate(data.len() - formatted_message.len());
+                    *pending = data;
+                    self.hold_message(pending, formatted_message.as_bytes());
+                }
+                return;
+            }
+        }
+
+        // Write to file
+        self.set_log_writer_if_not_set();
+        let mut result = Ok(());
+        if let Ok(mut log_writer) = self.log_writer.lock() {
+            if let Some(log_writer) = log_writer.as_mut() {
+                result = self.write_to_log_file(
+                    log_writer,
+                    &mut lock(&self.flush_state),
+                    formatted_message,
+                    level
+                );
+            }
+        }
+        // The writer is no longer locked, so it can be removed
+        if let Err(e) = result {
+            self.handle_write_error(e, formatted_message);
+        }
+    }
+
+    /// Check the disk limits before a message of the given size is written to
+    /// the log file, and pause or resume file logging. A warning is logged
+    /// when file logging is paused, and a message when it resumes.
+    #[cfg(feature = "log_files")]
+    fn disk_usage_allows(&self, message_len: u64) -> bool {
+        let max_bytes = self.get_max_log_bytes();
+        #[cfg(feature = "disk_space")]
+        let min_free_space = self.get_min_free_space();
+        #[cfg(not(feature = "disk_space"))]
+        let min_free_space: Option<u64> = None;
+
+        let now = self.now();
+        let mut state = lock(&self.disk_guard);
+        if max_bytes.is_none() && min_free_space.is_none() && !state.is_paused() {
+            return true;
+        }
+        if !state.check_due(max_bytes, message_len, now) {
+            return state.message_allowed(message_len);
+        }
+
+        let (total_bytes, problem) = self.disk_problem(max_bytes, min_free_space, message_len);
+        let was_paused = state.checked(now, total_bytes, problem.is_some());
+        let allowed = state.message_allowed(message_len);
+        // The state is no longer locked, so the change can be logged
+        drop(state);
+
+        match problem {
+            Some(problem) if !was_paused => {
+                self.warn(&format!("Logging to the file is paused: {problem}"));
+            }
+            None if was_paused => self.info("Logging to the file is resumed"),
+            _ => {}
+        }
+
+        allowed
+    }
+
+    /// Look at the disk for the limits, deleting old renamed log files if
+    /// pruning is on and there are too many bytes to write a message of the
+    /// given size.
+    /// Returns the total size of the log files, and why file logging should
+    /// be paused, if it should be.
+    ///
+    /// Problems looking at the disk are not a reason to pause.
+    #[cfg(feature = "log_files")]
+    fn disk_problem(
+        &self,
+        max_bytes: Option<u64>,
+        min_free_space: Option<u64>,
+        message_len: u64
+    ) -> (u64, Option<String>) {
+        let path = match self.get_log_path() {
+            Some(path) => path,
+            None => return (0, None)
+        };
+        let mut total_bytes = 0;
+        let mut problem = None;
+
+        if let Some(max_bytes) = max_bytes {
+            let archive_directory = self.get_log_archive_directory();
+            let mut archives = lock(&self.log_archives);
+            let prune = self.get_prune_log_archives();
+            if let Ok(total) = disk_guard::enforce_max_bytes(
+                &path,
+                archive_directory.as_deref(),
+                prune.then_some(&mut *archives),
+                max_bytes.saturating_sub(message_len)
+            ) {
+                total_bytes = total;
+                if total.saturating_add(message_len) > max_bytes {
+                    problem = Some(format!(
+                        "the log files take up {total} bytes, at the limit of {max_bytes}"
+                    ));
+                }
+            }
+        }
+        #[cfg(all(feature = "disk_space", unix))]
+        if let Some(min_free_space) = min_free_space {
+            let active_path = self.get_active_log_path().unwrap_or_else(|| path.clone());
+            let directory = match active_path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from(".")
+            };
+            if let Ok(free) = disk_guard::free_space(&directory) {
+                if free < min_free_space {
+                    problem = Some(format!(
+                        "only {free} bytes are free on the disk, under the minimum of {min_free_space}"
+                    ));
+                }
+            }
+        }
+        #[cfg(not(all(feature = "disk_space", unix)))]
+        let _ = min_free_space;
+
+        (total_bytes, problem)
+    }
+
+    /// Write a message to the log file or an extra log file, then flush and
+    /// sync it if the flush policy says so.
+    #[cfg(feature = "log_files")]
+    fn write_to_log_file(
+        &self,
+        log_writer: &mut BufWriter<File>,
+        flush_state: &mut FlushState,
+        formatted_message: &str,
+        level: Level
+    ) -> std::io::Result<()> {
+        if self.get_atomic_appends() {
+            // Anything buffered from before goes first
+            log_writer.flush()?;
+            let mut file = log_writer.get_ref();
+            #[cfg(all(feature = "file_lock", unix))]
+            let _lock = if self.get_file_locking() {
+                Some(file_lock::FileLock::lock(file)?)
+            } else {
+                None
+            };
+            file.write_all(formatted_message.as_bytes())?;
+        } else {
+            log_writer.write_all(formatted_message.as_bytes())?;
+        }
+
+        let now = self.now();
+        let policy = self.get_flush_policy();
+        if flush_state.message_written(policy, level, now) {
+            log_writer.flush()?;
+        }
+        if level >= Level::Error && self.get_sync_on_error() {
+            log_writer.flush()?;
+            log_writer.get_ref().sync_data()?;
+        }
+
+        Ok(())
+    }
+
+    /// Deal with a message that could not be written to the log file, using
+    /// the write error policy.
+    #[cfg(feature = "log_files")]
+    fn handle_write_error(&self, error: std::io::Error, formatted_message: &str) {
+        self.write_error_count.fetch_add(1, Ordering::Relaxed);
+        let callback = lock(&self.write_error_callback).clone();
+        if let Some(WriteErrorCallback(callback)) = callback {
+            callback(&error);
+        }
+
+        let unwritten = self.take_unwritten();
+        match self.get_write_error_policy() {
+            WriteErrorPolicy::Disable => self.disable_log_file(&error),
+            WriteErrorPolicy::Retry { attempts, backoff } => {
+                let mut data = unwritten;
+                data.extend_from_slice(formatted_message.as_bytes());
+                let mut delay = backoff;
+                for _ in 0..attempts {
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    if self.recover_log_file(&data).is_ok() {
+                        return;
+                    }
+                    self.write_error_count.fetch_add(1, Ordering::Relaxed);
+                }
+                self.disable_log_file(&error);
+            }
+            WriteErrorPolicy::FallbackToStderr | WriteErrorPolicy::Buffer { .. } => {
+                let mut pending = Vec::new();
+                self.hold_message(&mut pending, &unwritten);
+                self.hold_message(&mut pending, formatted_message.as_bytes());
+                *lock(&self.log_file_outage) = Some(pending);
+                self.error(&format!(
+                    "Log file could not be written to, trying again with the next message: {error:?}"
+                ));
+            }
+        }
+    }
+
+    /// Stop logging to the file after an error, then log the error.
+    #[cfg(feature = "log_files")]
+    fn disable_log_file(&self, error: &std::io::Error) {
+        // Remove the writer and the path, then log an error
+        self.remove_log_writer();
+        self.remove_log_path();
+        self.error(&format!("Log file could not be written to: {error:?}"));
+    }
+
+    /// Take the log writer away, and get the messages that were buffered in
+    /// it but not written yet. The active log path is kept.
+    #[cfg(feature = "log_files")]
+    fn take_unwritten(&self) -> Vec<u8> {
+        match lock(&self.log_writer).take() {
+            Some(log_writer) => unwritten(log_writer),
+            None => Vec::new()
+        }
+    }
+
+    /// Keep a message that could not be written to the log file in memory,
+    /// if the write error policy allows it and it fits. Otherwise it is
+    /// written to stderr.
+    #[cfg(feature = "log_files")]
+    fn hold_message(&self, pending: &mut Vec<u8>, message: &[u8]) {
+        match self.get_write_error_policy() {
+            WriteErrorPolicy::Buffer { max_bytes }
+                if pending.len() + message.len() <= max_bytes =>
+            {
+                pending.extend_from_slice(message);
+            }
+            _ => {
+                let _ = std::io::stderr().write_all(message);
+            }
+        }
+    }
+
+    /// Open the log file again after writing to it failed, and write the
+    /// given data to it. The new writer is only kept if that works.
+    #[cfg(feature = "log_files")]
+    fn recover_log_file(&self, data: &[u8]) -> std::io::Result<()> {
+        let path = match self.get_active_log_path().or_else(|| self.get_log_path()) {
+            Some(path) => path,
+            None => return Err(std::io::ErrorKind::NotFound.into())
+        };
+        let (log_writer, id) = reopen_log_file(&path, data)?;
+
+        *lock(&self.log_writer) = Some(log_writer);
+        *lock(&self.active_log_path) = Some(path);
+        *lock(&self.log_file_id) = id;
+
+        Ok(())
+    }
+
+    /// Write a message to each extra log file that accepts its level, the
+    /// same way as to the log file. Files are reopened first if that was
+    /// requested or they changed. Files that can not be written to are
+    /// handled by the write error policy, and errors are logged once the
+    /// files are no longer locked.
+    #[cfg(feature = "log_files")]
+    fn log_message_to_log_files(&self, log_message: &mut LogMessage, level: Level, reopen: bool) {
+        let reopen_on_file_change = self.get_reopen_on_file_change();
+        let mut problems = Vec::new();
+
+        if let Ok(mut log_files) = self.log_files.lock() {
+            log_files.retain_mut(|log_file| {
+                let reopened = if reopen || (reopen_on_file_change && log_file.changed()) {
+                    log_file.reopen()
+                } else {
+                    Ok(())
+                };
+                if !log_file.output.accepts(level) {
+                    return true;
+                }
+
+                let formatted_message = log_message.formatted(log_file.output.color);
+                let written = match reopened {
+                    Ok(()) => self.write_to_extra_log_file(log_file, &formatted_message, level),
+                    Err(e) => self.handle_extra_write_error(log_file, e, &formatted_message)
+                };
+                match written {
+                    ExtraFileWrite::Written => true,
+                    ExtraFileWrite::Failing(e) => {
+                        problems.push((log_file.output.path.clone(), e, true));
+                        true
+                    }
+                    ExtraFileWrite::Removed(e) => {
+                        problems.push((log_file.output.path.clone(), e, false));
+                        false
+                    }
+                }
+            });
+        }
+
+        for (path, e, kept) in problems {
+            if kept {
+                self.error(&format!(
+                    "Log file {path:?} could not be written to, trying again with the next message: {e:?}"
+                ));
+            } else {
+                self.error(&format!("Log file {path:?} could not be written to: {e:?}"));
+            }
+        }
+    }
+
+    /// Write a message to an extra log file. While writing to it is failing,
+    /// the file is opened again and the messages kept are written first.
+    #[cfg(feature = "log_files")]
+    fn write_to_extra_log_file(
+        &self,
+        log_file: &mut OpenFileOutput,
+        formatted_message: &str,
+        level: Level
+    ) -> ExtraFileWrite {
+        if let Some(pending) = log_file.outage.as_mut() {
+            let mut data = std::mem::take(pending);
+            data.extend_from_slice(formatted_message.as_bytes());
+            match reopen_log_file(&log_file.path, &data) {
+                Ok((writer, id)) => {
+                    log_file.writer = Some(writer);
+                    log_file.id = id;
+                    log_file.outage = None;
+                }
+                Err(_) => {
+                    self.write_error_count.fetch_add(1, Ordering::Relaxed);
+                    data.truncate(data.len() - formatted_message.len());
+                    *pending = data;
+                    self.hold_message(pending, formatted_message.as_bytes());
+                }
+            }
+            return ExtraFileWrite::Written;
+        }
+
+        let result = match log_file.writer.as_mut() {
+            Some(writer) => {
+                self.write_to_log_file(writer, &mut log_file.flush_state, formatted_message, level)
+            }
+            None => Ok(())
+        };
+        match result {
+            Ok(()) => ExtraFileWrite::Written,
+            Err(e) => self.handle_extra_write_error(log_file, e, formatted_message)
+        }
+    }
+
+    /// Deal with a message that could not be written to an extra log file,
+    /// using the write error policy, like
+    /// [`handle_write_error`](Self::handle_write_error) does for the log file.
+    #[cfg(feature = "log_files")]
+    fn handle_extra_write_error(
+        &self,
+        log_file: &mut OpenFileOutput,
+        error: std::io::Error,
+        formatted_message: &str
+    ) -> ExtraFileWrite {
+        self.write_error_count.fetch_add(1, Ordering::Relaxed);
+        let callback = lock(&self.write_error_callback).clone();
+        if let Some(WriteErrorCallback(callback)) = callback {
+            callback(&error);
+        }
+
+        let unwritten = log_file.writer.take().map(unwritten).unwrap_or_default();
+        match self.get_write_error_policy() {
+            WriteErrorPolicy::Disable => ExtraFileWrite::Removed(error),
+            WriteErrorPolicy::Retry { attempts, backoff } => {
+                let mut data = unwritten;
+                data.extend_from_slice(formatted_message.as_bytes());
+                let mut delay = backoff;
+                for _ in 0..attempts {
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    if let Ok((writer, id)) = reopen_log_file(&log_file.path, &data) {
+                        log_file.writer = Some(writer);
+                        log_file.id = id;
+                        return ExtraFileWrite::Written;
+                    }
+                    self.write_error_count.fetch_add(1, Ordering::Relaxed);
+                }
+                ExtraFileWrite::Removed(error)
+            }
+            WriteErrorPolicy::FallbackToStderr | WriteErrorPolicy::Buffer { .. } => {
+                let mut pending = Vec::new();
+                self.hold_message(&mut pending, &unwritten);
+                self.hold_message(&mut pending, formatted_message.as_bytes());
+                log_file.outage = Some(pending);
+                ExtraFileWrite::Failing(error)
+            }
+        }
+    }
+
+    /// Given a log message, write it to the file. Will gracefully handle
+    /// errors.
+    ///
+    /// Will do nothing when the `log_files` feature is not enabled.
+    #[cfg(not(feature = "log_files"))]
+    fn log_message_to_file(&self, _msg: &mut LogMessage, _level: Level, _reopen: bool) {
+        // Intentionally do nothing when the feature is not enabled
+    }
+
+    /// Log a message if its level is enabled and the rate limit and
+    /// duplicate collapsing let it through, and give it to the flight
+    /// recorder either way.
+    #[track_caller]
+    fn log_at(&self, level: Level, message: &str, limiting: Limiting) {
+        let enabled = self.get_level() <= level;
+        if !enabled && !self.flight_recording.load(Ordering::Relaxed) {
+            return;
+        }
+        let _in_logger = InLogger::enter();
+
+        let record = Record::new(level, message, self.now()).with_location(Location::caller());
+        let dump = match *lock(&self.flight_recorder) {
+            Some(ref mut recording) => recording
+                .record(&record)
+                .map(|records| (records, recording.settings.dump_to.clone())),
+            None => None
+        };
+
+        if enabled {
+            for record in self.let_through(record, limiting) {
+                self.log_message(&record);
+            }
+        }
+        if let Some((records, target)) = dump {
+            if let Err(e) = self.write_flight_recorder_dump(&records, &target) {
+                self.flight_recorder_dump_failed(&e);
+            }
+        }
+    }
+
+    /// Log that the flight recorder could not be dumped. The message is not
+    /// given to the flight recorder, since dumping it would fail again.
+    pub(crate) fn flight_recorder_dump_failed(&self, error: &std::io::Error) {
+        if self.get_level() <= Level::Error {
+            let _in_logger = InLogger::enter();
+            let record = Record::new(
+                Level::Error,
+                &format!("Could not dump the flight recorder: {error:?}"),
+                self.now()
+            );
+            for record in self.let_through(record, Limiting::Logger) {
+                self.log_message(&record);
+            }
+        }
+    }
+
+    /// Apply the rate limit and duplicate collapsing to a record. Gives the
+    /// records to log, which are none if it is held back, and otherwise the
+    /// record after any saying how many messages before it were held back.
+    fn let_through(&self, record: Record, limiting: Limiting) -> Vec<Record> {
+        let mut records = Vec::new();
+
+        let limit = match limiting {
+            Limiting::Logger => self.get_rate_limit(),
+            Limiting::CallSite(limit) => Some(limit),
+            Limiting::Never => None
+        };
+        if let (Some(limit), Some(location)) = (limit, record.location) {
+            let allowed =
+                lock(&self.rate_limiter).allow(location, record.level, limit, record.time);
+            match allowed {
+                None => return records,
+                Some(0) => {}
+                Some(suppressed) => records.push(rate_limit::summary(
+                    location,
+                    suppressed,
+                    record.level,
+                    record.time
+                ))
+            }
+        }
+
+        if self.get_collapse_duplicates() {
+            let mut duplicates = lock(&self.duplicates);
+            if !matches!(limiting, Limiting::Never) && duplicates.is_repeat(&record) {
+                return records;
+            }
+            records.extend(duplicates.replace(&record));
+        }
+
+        records.push(record);
+        records
+    }
+
+    /// Actually write the log message to the file and stdout. Should only be
+    /// called internally by the `debug`, `info`, `warn`, and `error` methods.
+    fn log_message(&self, record: &Record) {
+        let level = record.level;
+        let mut log_message = LogMessage::new(&self.prefix(record), &record.message, level);
 
         // Print to stdout
         print!("{}", log_message.formatted(self.get_color()));
 
         #[cfg(feature = "log_files")]
-        self.log_message_to_file(&mut log_message);
+        {
+            let reopen = self.reopen_requested.swap(false, Ordering::Relaxed);
+            self.log_message_to_file(&mut log_message, level, reopen);
+            self.log_message_to_log_files(&mut log_message, level, reopen);
+        }
+
+        for sink in lock(&self.sinks).iter() {
+            sink.log(record);
+        }
     }
 
     /// Print a message to the log at the `debug` level. Will only print if the
     /// `debug` level is enabled.
+    #[track_caller]
     pub fn debug(&self, message: &str) {
-        if self.get_level() <= Level::Debug {
-            self.log_message(Level::Debug, message);
-        }
+        self.log_at(Level::Debug, message, Limiting::Logger);
     }
 
     /// Print a message to the log at the `info` level. Will only print if the
     /// `info` level is enabled.
+    #[track_caller]
     pub fn info(&self, message: &str) {
-        if self.get_level() <= Level::Info {
-            self.log_message(Level::Info, message);
-        }
+        self.log_at(Level::Info, message, Limiting::Logger);
     }
 
     /// Print a message to the log at the `warn` level. Will only print if the
     /// `warn` level is enabled.
+    #[track_caller]
     pub fn warn(&self, message: &str) {
-        if self.get_level() <= Level::Warn {
-            self.log_message(Level::Warn, message);
-        }
+        self.log_at(Level::Warn, message, Limiting::Logger);
     }
 
     /// Print a message to the log at the `error` level. Will only print if the
     /// `error` level is enabled.
+    #[track_caller]
     pub fn error(&self, message: &str) {
-        if self.get_level() <= Level::Error {
-            self.log_message(Level::Error, message);
+        self.log_at(Level::Error, message, Limiting::Never);
+    }
+
+    /// Print a message to the log at the given level, with its own rate
+    /// limit for the call site instead of the one set with
+    /// [`set_rate_limit`](Self::set_rate_limit). This is what the `_limited`
+    /// macros, such as [`warn_limited!`], use.
+    #[track_caller]
+    pub fn log_limited(&self, level: Level, limit: RateLimit, message: &str) {
+        if level != Level::None {
+            self.log_at(level, message, Limiting::CallSite(limit));
         }
     }
 
-    /// The prefix to be added to all log messages. Currently this is just the
-    /// timestamp, but in the future it could be something like the scope of
-    /// the log message.
-    #[cfg(feature = "time")]
-    fn prefix(&self) -> String {
+    /// Log a panic at the `error` level. It is never held back by the rate
+    /// limit or collapsed into a repeat of the message before it, so that
+    /// every panic is logged in full.
+    #[track_caller]
+    pub(crate) fn log_panic(&self, message: &str) {
+        self.log_at(Level::Error, message, Limiting::Never);
+    }
+
+    /// The prefix to be added to all log messages. This is the timestamp,
+    /// process ID, and thread, each only if it is turned on.
+    fn prefix(&self, record: &Record) -> String {
+        let mut prefix = String::new();
+
+        #[cfg(feature = "time")]
         if self.should_show_time() {
-            time::current_time_box(self.get_timestamp_format())
-        } else {
-            "".to_string()
+            prefix.push_str(&time::styled_time_box(
+                record.time,
+                self.get_timestamp_style(),
+                self.get_timestamp_format(),
+                self.get_timestamp_utc(),
+                self.get_start_time()
+            ));
+        }
+        if self.should_show_process_id() {
+            prefix.push_str(&format!("[pid {}]", record.process_id));
+        }
+        if self.should_show_thread() {
+            prefix.push_str(&format!("[{}]", record.thread));
         }
-    }
 
-    /// The prefix to be added to all log messages. Currently this is just the
-    /// timestamp, but in the future it could be something like the scope of
-    /// the log message.
-    #[cfg(not(feature = "time"))]
-    fn prefix(&self) -> String {
-        "".to_string()
+        prefix
     }
 
-    /// Ensure all io buffers are cleared; usually before shutdown.
+    /// Ensure all io buffers are cleared; usually before shutdown. This is
+    /// also done when the logger is dropped.
+    ///
+    /// Every sink is flushed even if an earlier one fails, and the first
+    /// error is returned. Outputs that are poisoned by a panic are skipped.
+    ///
+    /// If repeats of the last message are being collapsed, how many times it
+    /// was repeated is logged first, and so is how many messages from each
+    /// call site were held back by the rate limit.
     pub fn flush(&self) -> std::io::Result<()> {
+        let _in_logger = InLogger::enter();
+        // Log how many times the last message was repeated so far, and how
+        // many messages were held back, so that the counts are not lost
+        let now = self.now();
+        let mut summaries: Vec<Record> = match self.duplicates.lock() {
+            Ok(mut duplicates) => duplicates.take_summary(now).into_iter().collect(),
+            Err(_) => Vec::new()
+        };
+        if let Ok(mut rate_limiter) = self.rate_limiter.lock() {
+            summaries.extend(rate_limiter.take_summaries(now));
+        }
+        for summary in &summaries {
+            self.log_message(summary);
+        }
+
+        let mut result = Ok(());
+
         #[cfg(feature = "log_files")]
         if let Ok(ref mut log_writer) = self.log_writer.lock() {
             if log_writer.is_some() {
-                log_writer.as_mut().unwrap().flush()
-            } else {
-                Ok(())
+                result = log_writer.as_mut().unwrap().flush();
+                if let Ok(mut flush_state) = self.flush_state.lock() {
+                    flush_state.flushed(self.now());
+                }
+            }
+        }
+        #[cfg(feature = "log_files")]
+        if let Ok(mut log_files) = self.log_files.lock() {
+            let now = self.now();
+            for log_file in log_files.iter_mut() {
+                let file_result = log_file.flush(now);
+                if result.is_ok() {
+                    result = file_result;
+                }
             }
-        } else {
-            Ok(())
         }
 
-        #[cfg(not(feature = "log_files"))]
-        Ok(())
+        // The sinks are not locked while they flush, which can take a while,
+        // so that other threads can keep logging
+        let sinks = lock(&self.sinks).clone();
+        for sink in sinks.iter() {
+            let sink_result = sink.flush();
+            if result.is_ok() {
+                result = sink_result;
+            }
+        }
+
+        result
     }
 
     /// Loads all settings from a config file.
@@ -584,6 +2127,10 @@ impl Logger {
     /// It is worth noting that this will overwrite any settings that are already
     /// set, including ones not in the config file.
     ///
+    /// The log file and the extra log files are opened before anything is
+    /// applied. If the file is invalid, or one of the log files can not be
+    /// used, an error is returned and the logger is left as it was.
+    ///
     /// # Example
     ///
     /// ```
@@ -594,27 +2141,129 @@ impl Logger {
     #[cfg(feature = "config")]
     pub fn load_config_file(&self, path: &str) -> Result<(), ConfigFileLoadError> {
         let config_file = ConfigFile::load(path)?;
+        // Validate everything and open the log files before applying
+        // anything so that an invalid file does not leave the logger half
+        // configured.
+        config_file.validate()?;
+        #[cfg(feature = "log_files")]
+        let opened = self.open_config_log_files(&config_file)?;
+
         self.set_level(config_file.level);
         self.set_color(config_file.color);
+        self.set_should_show_thread(config_file.show_thread);
+        self.set_should_show_process_id(config_file.show_process_id);
         #[cfg(feature = "time")]
         self.set_should_show_time(config_file.time_stamp);
+        #[cfg(feature = "time")]
+        self.set_timestamp_style(config_file.timestamp_style);
+        #[cfg(feature = "time")]
+        self.set_timestamp_utc(config_file.timestamp_utc);
         #[cfg(feature = "log_files")]
         self.set_log_file_color(config_file.file_color);
         #[cfg(feature = "log_files")]
         self.set_existing_log_handler(config_file.existing_log_handler);
         #[cfg(feature = "log_files")]
-        if let Some(ref log_path) = config_file.log_file_path {
-            self.set_log_path(log_path);
-        } else {
-            self.remove_log_path();
+        self.set_log_archive_directory(config_file.log_archive_directory.as_ref());
+        #[cfg(feature = "log_files")]
+        self.set_flush_policy(config_file.flush_policy);
+        #[cfg(feature = "log_files")]
+        self.set_sync_on_error(config_file.sync_on_error);
+        #[cfg(feature = "log_files")]
+        self.set_write_error_policy(config_file.write_error_policy);
+        #[cfg(feature = "log_files")]
+        self.set_create_log_directories(config_file.create_log_directories);
+        #[cfg(feature = "log_files")]
+        self.set_log_directory_mode(config_file.parsed_log_directory_mode()?);
+        #[cfg(feature = "log_files")]
+        self.set_max_log_bytes(config_file.max_log_bytes);
+        #[cfg(feature = "log_files")]
+        self.set_prune_log_archives(config_file.prune_log_archives);
+        #[cfg(feature = "disk_space")]
+        self.set_min_free_space(config_file.min_free_space);
+        self.set_flight_recorder(config_file.flight_recorder.clone());
+        self.set_rate_limit(config_file.rate_limit);
+        self.set_collapse_duplicates(config_file.collapse_duplicates);
+        #[cfg(feature = "log_files")]
+        {
+            match opened.log_path {
+                Some((path, file)) => self.install_log_path(path, file),
+                None => self.remove_log_path()
+            }
+            self.clear_log_files();
+            lock(&self.log_files).extend(opened.log_files);
         }
+        #[cfg(feature = "time")]
+        self.set_timestamp_format(config_file.timestamp_format.clone())?;
 
-        debug!("Config file loaded: {:?}", config_file);
+        self.debug(&format!("Config file loaded: {:?}", config_file));
 
         Ok(())
     }
 }
 
+#[cfg(all(feature = "config", feature = "log_files"))]
+impl Logger {
+    /// Open the log file and the extra log files of a config file with its
+    /// settings, without changing the logger.
+    fn open_config_log_files(
+        &self,
+        config_file: &ConfigFile
+    ) -> Result<ConfigLogFiles, ConfigFileLoadError> {
+        let directory_mode = config_file.parsed_log_directory_mode()?;
+        let settings = OpenSettings {
+            now: self.now(),
+            archive_directory: config_file
+                .log_archive_directory
+                .as_ref()
+                .map(PathBuf::from),
+            append_only: self.get_atomic_appends()
+        };
+
+        let log_path = match config_file.log_file_path {
+            Some(ref path) => {
+                let path = PathBuf::from(path);
+                let opened = prepare_log_path(
+                    &path,
+                    config_file.existing_log_handler,
+                    config_file.create_log_directories,
+                    directory_mode,
+                    &settings
+                )
+                .map_err(ConfigFileLoadError::InvalidLogPath)?;
+                Some((path, opened))
+            }
+            None => None
+        };
+        let log_files = config_file
+            .log_files
+            .iter()
+            .map(|output| {
+                prepare_log_file(
+                    output.clone(),
+                    config_file.create_log_directories,
+                    directory_mode,
+                    &settings
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ConfigFileLoadError::InvalidLogPath)?;
+
+        Ok(ConfigLogFiles {
+            log_path,
+            log_files
+        })
+    }
+}
+
+/// The log files of a config file, opened before it is applied.
+#[cfg(all(feature = "config", feature = "log_files"))]
+struct ConfigLogFiles {
+    /// The log path, with the file that was opened for it if it is new.
+    log_path: Option<(PathBuf, Option<OpenedLogFile>)>,
+    /// The extra log files.
+    log_files: Vec<OpenFileOutput>
+}
+
 impl Default for Logger {
     /// A standard logger with all default settings.
     fn default() -> Self {
@@ -622,19 +2271,247 @@ impl Default for Logger {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Drop for Logger {
+    /// Flush the log file and all sinks, so that nothing is lost when a
+    /// logger goes out of scope. Errors are ignored.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// How the rate limit and duplicate collapsing apply to a message.
+#[derive(Debug, Clone, Copy)]
+enum Limiting {
+    /// The rate limit of the logger applies.
+    Logger,
+    /// The rate limit for the call site applies instead.
+    CallSite(RateLimit),
+    /// Neither applies.
+    Never
+}
+
+/// The device and inode of a file.
+#[cfg(feature = "log_files")]
+pub(crate) type FileId = (u64, u64);
+
+/// What happened to a message written to an extra log file.
+#[cfg(feature = "log_files")]
+enum ExtraFileWrite {
+    /// It was written, or kept by the write error policy.
+    Written,
+    /// Writing failed, and the file is tried again with the next message.
+    Failing(std::io::Error),
+    /// Writing failed, and the file should be removed.
+    Removed(std::io::Error)
+}
+
+/// A function called with the error when writing to the log file fails.
+#[cfg(feature = "log_files")]
+#[derive(Clone)]
+struct WriteErrorCallback(Arc<dyn Fn(&std::io::Error) + Send + Sync>);
+
+#[cfg(feature = "log_files")]
+impl std::fmt::Debug for WriteErrorCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WriteErrorCallback")
+    }
+}
+
+/// Check that a log file can be used at the path, creating its directories
+/// if asked to. A file that does not exist yet is created and opened straight
+/// away, which is given back. An existing file is left alone, to be opened
+/// when the first message is logged.
+#[cfg(feature = "log_files")]
+fn prepare_log_path(
+    path: &Path,
+    handler: ExistingLogHandler,
+    create_directories: bool,
+    directory_mode: Option<u32>,
+    settings: &OpenSettings
+) -> Result<Option<OpenedLogFile>, SetLogPathError> {
+    if path.exists() {
+        // Check that it is in fact a file.
+        if !path.is_file() {
+            return Err(SetLogPathError::PathIsNotAFile);
+        }
+        if handler == ExistingLogHandler::FailIfExists {
+            return Err(SetLogPathError::AlreadyExists);
+        }
+
+        return Ok(None);
+    }
+
+    if create_directories {
+        if let Some(parent) = path.parent() {
+            existing_log_handler::create_directories(parent, directory_mode)
+                .map_err(SetLogPathError::CouldNotCreateLogDirectory)?;
+        }
+    }
+    // There is no existing file to handle, so it is opened now, which also
+    // checks that it can be created.
+    Ok(Some(handler.open_file_with(path, settings)?))
+}
+
+/// Open an extra log file, creating its directories if asked to.
+#[cfg(feature = "log_files")]
+fn prepare_log_file(
+    output: FileOutput,
+    create_directories: bool,
+    directory_mode: Option<u32>,
+    settings: &OpenSettings
+) -> Result<OpenFileOutput, SetLogPathError> {
+    if output.path.exists() && !output.path.is_file() {
+        return Err(SetLogPathError::PathIsNotAFile);
+    }
+    if create_directories {
+        if let Some(parent) = output.path.parent() {
+            existing_log_handler::create_directories(parent, directory_mode)
+                .map_err(SetLogPathError::CouldNotCreateLogDirectory)?;
+        }
+    }
+
+    Ok(OpenFileOutput::open(output, settings)?)
+}
+
+/// Open a log file again for appending, creating it if it is missing, and
+/// write the given data to it first.
+#[cfg(feature = "log_files")]
+pub(crate) fn reopen_log_file(
+    path: &Path,
+    data: &[u8]
+) -> std::io::Result<(BufWriter<File>, Option<FileId>)> {
+    let file = File::options().create(true).append(true).open(path)?;
+    let id = file_id(file.metadata());
+    let mut writer = BufWriter::new(file);
+    if !data.is_empty() {
+        writer.write_all(data)?;
+        writer.flush()?;
+    }
+
+    Ok((writer, id))
+}
+
+/// The messages buffered in a writer but not written yet.
+#[cfg(feature = "log_files")]
+fn unwritten(log_writer: BufWriter<File>) -> Vec<u8> {
+    let (_, buffer) = log_writer.into_parts();
+    buffer.unwrap_or_else(|panicked| panicked.into_inner())
+}
+
+/// Lock a mutex of a logger, even if a thread panicked while holding it, so
+/// that logging keeps working after a panic, including in the panic hook.
+pub(crate) fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+thread_local! {
+    /// Whether the current thread is running code of a logger. See
+    /// [`InLogger`].
+    static IN_LOGGER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as running code of a logger until it is dropped.
+///
+/// A panic while it is marked, such as in a sink or a clock, is not logged by
+/// the [`PanicHook`], because the logger may be holding a lock that logging
+/// would wait on forever.
+pub(crate) struct InLogger(bool);
+
+impl InLogger {
+    pub(crate) fn enter() -> InLogger {
+        InLogger(IN_LOGGER.with(|in_logger| in_logger.replace(true)))
+    }
+
+    /// Whether the current thread is running code of a logger.
+    pub(crate) fn active() -> bool {
+        IN_LOGGER.with(Cell::get)
+    }
+}
+
+impl Drop for InLogger {
+    fn drop(&mut self) {
+        IN_LOGGER.with(|in_logger| in_logger.set(self.0));
+    }
+}
+
+/// Parse a duration such as `250ms`, `5s` or `1m`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u64 = value[..split].parse().ok()?;
+
+    match &value[split..] {
+        "ms" => Some(Duration::from_millis(amount)),
+        "s" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_secs(amount * 60)),
+        _ => None
+    }
+}
+
+/// Get the device and inode of a file, which identify it even after it is
+/// moved. Always none on platforms other than Unix.
+#[cfg(feature = "log_files")]
+pub(crate) fn file_id(metadata: std::io::Result<std::fs::Metadata>) -> Option<FileId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata
+            .ok()
+            .map(|metadata| (metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+#[derive(Debug)]
 pub enum SetLogPathError {
-    CouldNotCreateLogFile,
-    PathIsNotAFile
+    /// The file could not be created, such as when its directory does not
+    /// exist or there is no permission to write to it.
+    CouldNotCreateLogFile(std::io::Error),
+    /// The missing directories of the file could not be created.
+    CouldNotCreateLogDirectory(std::io::Error),
+    /// The path is a directory or something else that is not a file, or has
+    /// no file name.
+    PathIsNotAFile,
+    /// The file already exists, and the existing log handler is
+    /// [`ExistingLogHandler::FailIfExists`].
+    AlreadyExists
+}
+
+#[cfg(feature = "log_files")]
+impl From<ExistingLogHandlerOpenError> for SetLogPathError {
+    fn from(error: ExistingLogHandlerOpenError) -> Self {
+        match error {
+            ExistingLogHandlerOpenError::Io(e) => SetLogPathError::CouldNotCreateLogFile(e),
+            ExistingLogHandlerOpenError::InvalidPath => SetLogPathError::PathIsNotAFile,
+            ExistingLogHandlerOpenError::AlreadyExists => SetLogPathError::AlreadyExists
+        }
+    }
 }
 
 impl Display for SetLogPathError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SetLogPathError::CouldNotCreateLogFile => write!(f, "Could not create log file"),
-            SetLogPathError::PathIsNotAFile => write!(f, "Log file path is not a file")
+            SetLogPathError::CouldNotCreateLogFile(e) => {
+                write!(f, "Could not create log file: {e}")
+            }
+            SetLogPathError::CouldNotCreateLogDirectory(e) => {
+                write!(f, "Could not create log directory: {e}")
+            }
+            SetLogPathError::PathIsNotAFile => write!(f, "Log file path is not a file"),
+            SetLogPathError::AlreadyExists => write!(f, "Log file already exists")
         }
     }
 }
 
-impl Error for SetLogPathError {}
+impl Error for SetLogPathError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SetLogPathError::CouldNotCreateLogFile(e) => Some(e),
+            SetLogPathError::CouldNotCreateLogDirectory(e) => Some(e),
+            _ => None
+        }
+    }
+}
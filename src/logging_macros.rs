@@ -6,7 +6,7 @@
 //! #[macro_use]
 //! extern crate pokey_logger;
 //!
-//! // Log to the generic, global logger.
+//! // Log to the global logger. See `set_global_logger` to replace it.
 //! debug!("Hello, world! {:?}", "something");
 //! ```
 //!
@@ -27,7 +27,7 @@ mod tests;
 #[macro_export]
 macro_rules! debug {
     ($($arg:tt)*) => {
-        $crate::LOGGER.debug(&format!($($arg)*))
+        $crate::global_logger().debug(&format!($($arg)*))
     }
 }
 
@@ -45,7 +45,7 @@ macro_rules! ldebug {
 /// specific logger.
 macro_rules! info {
     ($($arg:tt)*) => {
-        $crate::LOGGER.info(&format!($($arg)*))
+        $crate::global_logger().info(&format!($($arg)*))
     }
 }
 
@@ -63,7 +63,7 @@ macro_rules! linfo {
 /// a specific logger.
 macro_rules! warn {
     ($($arg:tt)*) => {
-        $crate::LOGGER.warn(&format!($($arg)*))
+        $crate::global_logger().warn(&format!($($arg)*))
     }
 }
 
@@ -81,7 +81,7 @@ macro_rules! lwarn {
 /// a specific logger.
 macro_rules! error {
     ($($arg:tt)*) => {
-        $crate::LOGGER.error(&format!($($arg)*))
+        $crate::global_logger().error(&format!($($arg)*))
     }
 }
 
@@ -93,3 +93,128 @@ macro_rules! lerror {
         $logger.error(&format!($($arg)*))
     }
 }
+
+#[macro_export]
+/// Logs a debug message on the global logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`ldebug_limited!`] for logging to a specific logger.
+macro_rules! debug_limited {
+    ($limit:expr, $($arg:tt)*) => {
+        $crate::global_logger().log_limited($crate::Level::Debug, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs a debug message to a specific logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`debug_limited!`] for logging to the global logger.
+macro_rules! ldebug_limited {
+    ($logger:expr, $limit:expr, $($arg:tt)*) => {
+        $logger.log_limited($crate::Level::Debug, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs an info message on the global logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`linfo_limited!`] for logging to a specific logger.
+macro_rules! info_limited {
+    ($limit:expr, $($arg:tt)*) => {
+        $crate::global_logger().log_limited($crate::Level::Info, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs an info message to a specific logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`info_limited!`] for logging to the global logger.
+macro_rules! linfo_limited {
+    ($logger:expr, $limit:expr, $($arg:tt)*) => {
+        $logger.log_limited($crate::Level::Info, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs a warning message on the global logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`lwarn_limited!`] for logging to a specific logger.
+macro_rules! warn_limited {
+    ($limit:expr, $($arg:tt)*) => {
+        $crate::global_logger().log_limited($crate::Level::Warn, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs a warning message to a specific logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`warn_limited!`] for logging to the global logger.
+macro_rules! lwarn_limited {
+    ($logger:expr, $limit:expr, $($arg:tt)*) => {
+        $logger.log_limited($crate::Level::Warn, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs an error message on the global logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`lerror_limited!`] for logging to a specific logger.
+macro_rules! error_limited {
+    ($limit:expr, $($arg:tt)*) => {
+        $crate::global_logger().log_limited($crate::Level::Error, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Logs an error message to a specific logger, at most as often as the given
+/// [`RateLimit`](crate::rate_limit::RateLimit) allows from this call site.
+/// See [`error_limited!`] for logging to the global logger.
+macro_rules! lerror_limited {
+    ($logger:expr, $limit:expr, $($arg:tt)*) => {
+        $logger.log_limited($crate::Level::Error, $limit, &format!($($arg)*))
+    }
+}
+
+#[macro_export]
+/// Asserts that a [`Capture`](crate::capture::Capture) has a record at the
+/// given level with a message containing the given text. Extra arguments are
+/// used to format the text, like `format!`.
+///
+/// ```
+/// use pokey_logger::{assert_logged, linfo, Level, Logger};
+///
+/// let logger = Logger::new();
+/// let capture = logger.capture();
+/// linfo!(logger, "user {} logged in", 12);
+/// assert_logged!(capture, Level::Info, "user {} logged in", 12);
+/// ```
+macro_rules! assert_logged {
+    ($capture:expr, $level:expr, $($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        if !$capture.contains($level, &text) {
+            panic!(
+                "expected a {} record containing {:?}, but captured: {:#?}",
+                $level,
+                text,
+                $capture.records()
+            );
+        }
+    }}
+}
+
+#[macro_export]
+/// Asserts that a [`Capture`](crate::capture::Capture) has no records at the
+/// given level with a message containing the given text. The opposite of
+/// [`assert_logged!`].
+macro_rules! assert_not_logged {
+    ($capture:expr, $level:expr, $($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        if $capture.contains($level, &text) {
+            panic!(
+                "expected no {} record containing {:?}, but captured: {:#?}",
+                $level,
+                text,
+                $capture.records()
+            );
+        }
+    }}
+}
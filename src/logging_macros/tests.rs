@@ -1,4 +1,4 @@
-use crate::{debug, ldebug, Logger};
+use crate::{assert_logged, assert_not_logged, debug, ldebug, Level, Logger};
 
 #[test]
 fn test_regular_macro() {
@@ -10,3 +10,24 @@ fn test_macro_with_logger() {
     let logger = Logger::new();
     ldebug!(logger, "test_macro_with_logger");
 }
+
+#[test]
+fn test_assert_logged() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+
+    crate::lwarn!(logger, "disk almost full: {}%", 95);
+
+    assert_logged!(capture, Level::Warn, "disk almost full");
+    assert_logged!(capture, Level::Warn, "{}%", 95);
+    assert_not_logged!(capture, Level::Error, "disk almost full");
+}
+
+#[test]
+#[should_panic(expected = "expected a WARN record containing \"disk almost full\"")]
+fn test_assert_logged_panics_when_missing() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+
+    assert_logged!(capture, Level::Warn, "disk almost full");
+}
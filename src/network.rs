@@ -0,0 +1,473 @@
+//! Sinks that send messages over the network, to a log collector such as
+//! Logstash, Fluentd, Vector or Graylog.
+//!
+//! This module is not included unless the `network` feature is enabled.
+//!
+//! ```no_run
+//! use pokey_logger::network::{Framing, RecordFormat, TcpSink};
+//! use pokey_logger::LOGGER;
+//!
+//! let sink = TcpSink::builder("127.0.0.1:5170")
+//!     .format(RecordFormat::Json)
+//!     .framing(Framing::Newline)
+//!     .build();
+//! LOGGER.add_sink(sink);
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::json;
+use crate::sink::{Record, Sink};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How a record is written before it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordFormat {
+    /// A line of text, such as
+    /// `2022-03-12T18:14:09.500Z WARN [main] disk almost full`.
+    #[default]
+    Text,
+    /// A JSON object with the `time`, `level`, `message`, `thread` and `pid`
+    /// of the record, and its `file` and `line` when they are known.
+    Json,
+    /// A [GELF 1.1](https://go2docs.graylog.org/current/getting_in_log_data/gelf.html)
+    /// message for Graylog, with the thread, process ID and code location as
+    /// additional fields.
+    Gelf
+}
+
+impl RecordFormat {
+    /// Write a record in this format. The host is only used by GELF.
+    pub fn format(&self, record: &Record, host: &str) -> String {
+        match self {
+            RecordFormat::Text => format!(
+                "{} {} [{}] {}",
+                json::rfc3339(record),
+                record.level,
+                record.thread,
+                record.message
+            ),
+            RecordFormat::Json => json::record_object(record),
+            RecordFormat::Gelf => {
+                let mut message = format!(
+                    "{{\"version\":\"1.1\",\"host\":{},\"short_message\":{},\"timestamp\":{},\"level\":{},\"_thread\":{},\"_pid\":{}",
+                    json::string(host),
+                    json::string(&record.message),
+                    json::unix_seconds(record),
+                    record.level.syslog_severity().unwrap_or(7),
+                    json::string(&record.thread),
+                    record.process_id
+                );
+                if let Some(location) = record.location {
+                    message.push_str(&format!(
+                        ",\"_file\":{},\"_line\":{}",
+                        json::string(location.file()),
+                        location.line()
+                    ));
+                }
+                message.push('}');
+                message
+            }
+        }
+    }
+}
+
+/// How records are separated on a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Each record ends with a new line. New lines inside the record are
+    /// sent as is, so this is best with JSON, which escapes them.
+    #[default]
+    Newline,
+    /// Each record starts with its length in bytes, as a 4 byte big endian
+    /// number.
+    LengthPrefixed,
+    /// Each record ends with a null byte, which is what GELF over TCP uses.
+    Null
+}
+
+impl Framing {
+    /// Frame a formatted record to be sent.
+    pub fn frame(&self, message: &str) -> Vec<u8> {
+        let mut framed = Vec::with_capacity(message.len() + 4);
+        match self {
+            Framing::Newline => {
+                framed.extend_from_slice(message.as_bytes());
+                framed.push(b'\n');
+            }
+            Framing::LengthPrefixed => {
+                framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+                framed.extend_from_slice(message.as_bytes());
+            }
+            Framing::Null => {
+                framed.extend_from_slice(message.as_bytes());
+                framed.push(0);
+            }
+        }
+
+        framed
+    }
+}
+
+/// Settings for a [`TcpSink`], which is created with
+/// [`build`](TcpSinkBuilder::build).
+#[derive(Debug, Clone)]
+pub struct TcpSinkBuilder {
+    address: String,
+    format: RecordFormat,
+    framing: Framing,
+    host: Option<String>,
+    backlog: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    connect_timeout: Duration,
+    write_timeout: Duration
+}
+
+impl TcpSinkBuilder {
+    /// Send text lines to the given address, such as `"127.0.0.1:5170"` or
+    /// `"collector:5170"`, keeping up to 1000 records while disconnected.
+    pub fn new<A: Into<String>>(address: A) -> TcpSinkBuilder {
+        TcpSinkBuilder {
+            address: address.into(),
+            format: RecordFormat::default(),
+            framing: Framing::default(),
+            host: None,
+            backlog: 1000,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(1),
+            write_timeout: Duration::from_millis(100)
+        }
+    }
+
+    /// Set how records are written.
+    pub fn format(mut self, format: RecordFormat) -> TcpSinkBuilder {
+        self.format = format;
+        self
+    }
+
+    /// Set how records are separated.
+    pub fn framing(mut self, framing: Framing) -> TcpSinkBuilder {
+        self.framing = framing;
+        self
+    }
+
+    /// Set the host name sent in GELF messages. By default it is the name of
+    /// this machine.
+    pub fn host<S: Into<String>>(mut self, host: S) -> TcpSinkBuilder {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Set the most records kept in memory while disconnected. When it is
+    /// full, the oldest records are dropped.
+    pub fn backlog(mut self, records: usize) -> TcpSinkBuilder {
+        self.backlog = records;
+        self
+    }
+
+    /// Set how long to wait before connecting again after the connection
+    /// fails, and the longest wait. The wait doubles after each failure.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> TcpSinkBuilder {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set how long to wait for a connection before giving up. The default
+    /// is 1 second.
+    ///
+    /// Connecting happens on the thread that logs, while the sink is locked,
+    /// so every thread that logs can wait this long each time connecting is
+    /// tried again, along with the time to look up the address. Keep it short
+    /// when logging must not stall.
+    pub fn connect_timeout(mut self, timeout: Duration) -> TcpSinkBuilder {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for the collector to take a record before the
+    /// connection is treated as lost. The default is 100 milliseconds.
+    ///
+    /// Like connecting, sending blocks the thread that logs while the sink is
+    /// locked, so this should be much shorter than the connect timeout.
+    pub fn write_timeout(mut self, timeout: Duration) -> TcpSinkBuilder {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Create the sink and try to connect. If the collector can not be
+    /// reached yet, records are kept until it can.
+    pub fn build(self) -> TcpSink {
+        let sink = TcpSink {
+            state: Mutex::new(TcpState {
+                stream: None,
+                backlog: VecDeque::new(),
+                next_attempt: None,
+                backoff: self.initial_backoff
+            }),
+            host: self.host.clone().unwrap_or_else(hostname),
+            settings: self,
+            dropped: AtomicU64::new(0)
+        };
+        sink.state.lock().unwrap().connect(&sink.settings);
+
+        sink
+    }
+}
+
+/// A sink that sends every record over a TCP connection.
+///
+/// When the connection is lost, records are kept in a backlog of limited
+/// size, and connecting again is tried on later records, waiting longer
+/// after each failure. Connecting and sending happen on the thread that
+/// logs, so a slow collector holds up logging for up to the
+/// [connect timeout](TcpSinkBuilder::connect_timeout) or
+/// [write timeout](TcpSinkBuilder::write_timeout). Use
+/// [`HttpSink`](crate::http::HttpSink) to send from a thread of its own.
+#[derive(Debug)]
+pub struct TcpSink {
+    state: Mutex<TcpState>,
+    settings: TcpSinkBuilder,
+    host: String,
+    /// How many records were dropped because the backlog was full.
+    dropped: AtomicU64
+}
+
+impl TcpSink {
+    /// Start building a sink that sends to the given address. See
+    /// [`TcpSinkBuilder`].
+    pub fn builder<A: Into<String>>(address: A) -> TcpSinkBuilder {
+        TcpSinkBuilder::new(address)
+    }
+
+    /// Whether or not the sink is connected.
+    pub fn is_connected(&self) -> bool {
+        self.lock_state().stream.is_some()
+    }
+
+    /// How many records are waiting to be sent.
+    pub fn backlog_len(&self) -> usize {
+        self.lock_state().backlog.len()
+    }
+
+    /// How many records were dropped because the backlog was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, TcpState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Sink for TcpSink {
+    fn log(&self, record: &Record) {
+        let message = self.settings.format.format(record, &self.host);
+        let mut state = self.lock_state();
+
+        state
+            .backlog
+            .push_back(self.settings.framing.frame(&message));
+        while state.backlog.len() > self.settings.backlog {
+            state.backlog.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        state.send_backlog(&self.settings);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.lock_state();
+        state.send_backlog(&self.settings);
+        match state.stream {
+            Some(ref mut stream) => stream.flush(),
+            None => Ok(())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TcpState {
+    stream: Option<TcpStream>,
+    /// Framed records that have not been sent yet.
+    backlog: VecDeque<Vec<u8>>,
+    /// When connecting may be tried again, after a failure.
+    next_attempt: Option<Instant>,
+    /// How long to wait after the next failure.
+    backoff: Duration
+}
+
+impl TcpState {
+    /// Connect if not connected, unless it is too soon after a failure.
+    fn connect(&mut self, settings: &TcpSinkBuilder) {
+        if self.stream.is_some() {
+            return;
+        }
+        if let Some(next_attempt) = self.next_attempt {
+            if Instant::now() < next_attempt {
+                return;
+            }
+        }
+
+        match connect(settings) {
+            Ok(stream) => {
+                self.stream = Some(stream);
+                self.next_attempt = None;
+                self.backoff = settings.initial_backoff;
+            }
+            Err(_) => self.failed(settings)
+        }
+    }
+
+    /// Wait longer before connecting again.
+    fn failed(&mut self, settings: &TcpSinkBuilder) {
+        self.stream = None;
+        self.next_attempt = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(settings.max_backoff);
+    }
+
+    /// Send as much of the backlog as possible, oldest first.
+    fn send_backlog(&mut self, settings: &TcpSinkBuilder) {
+        self.connect(settings);
+        while let Some(stream) = self.stream.as_mut() {
+            let data = match self.backlog.front() {
+                Some(data) => data,
+                None => return
+            };
+            if stream.write_all(data).is_ok() {
+                self.backlog.pop_front();
+            } else {
+                // A record that was partly written is sent again in full
+                self.failed(settings);
+            }
+        }
+    }
+}
+
+/// Connect to the first address that works.
+fn connect(settings: &TcpSinkBuilder) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "no addresses found");
+    for address in settings.address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, settings.connect_timeout) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(settings.write_timeout))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Settings for a [`UdpSink`], which is created with
+/// [`build`](UdpSinkBuilder::build).
+#[derive(Debug, Clone)]
+pub struct UdpSinkBuilder {
+    address: String,
+    format: RecordFormat,
+    host: Option<String>
+}
+
+impl UdpSinkBuilder {
+    /// Send text to the given address, such as `"127.0.0.1:12201"`.
+    pub fn new<A: Into<String>>(address: A) -> UdpSinkBuilder {
+        UdpSinkBuilder {
+            address: address.into(),
+            format: RecordFormat::default(),
+            host: None
+        }
+    }
+
+    /// Set how records are written.
+    pub fn format(mut self, format: RecordFormat) -> UdpSinkBuilder {
+        self.format = format;
+        self
+    }
+
+    /// Set the host name sent in GELF messages. By default it is the name of
+    /// this machine.
+    pub fn host<S: Into<String>>(mut self, host: S) -> UdpSinkBuilder {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Create the sink. Fails if the address can not be found.
+    pub fn build(self) -> io::Result<UdpSink> {
+        let address = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no addresses found"))?;
+        let local = if address.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(address)?;
+
+        Ok(UdpSink {
+            socket,
+            format: self.format,
+            host: self.host.unwrap_or_else(hostname),
+            dropped: AtomicU64::new(0)
+        })
+    }
+}
+
+/// A sink that sends every record as a single UDP datagram, without any
+/// framing.
+///
+/// UDP does not say whether records arrive, so nothing is kept or sent
+/// again. Records that are too big for one datagram are dropped, as GELF
+/// chunking is not supported.
+#[derive(Debug)]
+pub struct UdpSink {
+    socket: UdpSocket,
+    format: RecordFormat,
+    host: String,
+    /// How many records could not be sent.
+    dropped: AtomicU64
+}
+
+impl UdpSink {
+    /// Start building a sink that sends to the given address. See
+    /// [`UdpSinkBuilder`].
+    pub fn builder<A: Into<String>>(address: A) -> UdpSinkBuilder {
+        UdpSinkBuilder::new(address)
+    }
+
+    /// How many records could not be sent.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Sink for UdpSink {
+    fn log(&self, record: &Record) {
+        let message = self.format.format(record, &self.host);
+        if self.socket.send(message.as_bytes()).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The name of this machine, or `localhost` if it can not be found.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
@@ -0,0 +1,222 @@
+use super::*;
+use crate::Level;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{SocketAddr, TcpListener};
+use std::time::UNIX_EPOCH;
+
+fn record(message: &str) -> Record {
+    let mut record = Record::new(
+        Level::Warn,
+        message,
+        UNIX_EPOCH + Duration::from_millis(1_647_108_849_500)
+    );
+    record.thread = "main".to_string();
+    record.process_id = 1234;
+    record
+}
+
+fn listener() -> (TcpListener, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    (listener, address)
+}
+
+fn accept(listener: &TcpListener) -> TcpStream {
+    let (stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream
+}
+
+#[test]
+fn test_formats() {
+    let record = record("disk \"full\"");
+
+    assert_eq!(
+        RecordFormat::Text.format(&record, "host"),
+        "2022-03-12T18:14:09.500Z WARN [main] disk \"full\""
+    );
+    assert_eq!(
+        RecordFormat::Json.format(&record, "host"),
+        json::record_object(&record)
+    );
+    assert_eq!(
+        RecordFormat::Gelf.format(&record, "web-1"),
+        "{\"version\":\"1.1\",\"host\":\"web-1\",\"short_message\":\"disk \\\"full\\\"\",\"timestamp\":1647108849.500,\"level\":4,\"_thread\":\"main\",\"_pid\":1234}"
+    );
+}
+
+#[test]
+fn test_framing() {
+    assert_eq!(Framing::Newline.frame("abc"), b"abc\n");
+    assert_eq!(Framing::LengthPrefixed.frame("abc"), b"\0\0\0\x03abc");
+    assert_eq!(Framing::Null.frame("abc"), b"abc\0");
+}
+
+#[test]
+fn test_tcp_sink_sends_lines() {
+    let (listener, address) = listener();
+    let sink = TcpSink::builder(address.to_string())
+        .format(RecordFormat::Json)
+        .build();
+    let stream = accept(&listener);
+    assert!(sink.is_connected());
+
+    sink.log(&record("first"));
+    sink.log(&record("second\nline"));
+    sink.flush().unwrap();
+
+    let mut lines = BufReader::new(stream).lines();
+    assert_eq!(
+        lines.next().unwrap().unwrap(),
+        json::record_object(&record("first"))
+    );
+    assert_eq!(
+        lines.next().unwrap().unwrap(),
+        json::record_object(&record("second\nline"))
+    );
+    assert_eq!(sink.backlog_len(), 0);
+    assert_eq!(sink.dropped(), 0);
+}
+
+#[test]
+fn test_tcp_sink_length_prefixed() {
+    let (listener, address) = listener();
+    let sink = TcpSink::builder(address.to_string())
+        .format(RecordFormat::Gelf)
+        .framing(Framing::LengthPrefixed)
+        .host("web-1")
+        .build();
+    let mut stream = accept(&listener);
+
+    sink.log(&record("hello"));
+
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut message = vec![0; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message).unwrap();
+    assert_eq!(
+        String::from_utf8(message).unwrap(),
+        RecordFormat::Gelf.format(&record("hello"), "web-1")
+    );
+}
+
+#[test]
+fn test_tcp_sink_backlog_and_reconnect() {
+    // Find a free port, then stop listening on it
+    let (listener, address) = listener();
+    drop(listener);
+
+    let sink = TcpSink::builder(address.to_string())
+        .backlog(2)
+        .backoff(Duration::from_millis(20), Duration::from_millis(40))
+        .build();
+    assert!(!sink.is_connected());
+
+    sink.log(&record("one"));
+    sink.log(&record("two"));
+    sink.log(&record("three"));
+    assert_eq!(sink.backlog_len(), 2);
+    assert_eq!(sink.dropped(), 1);
+
+    let listener = TcpListener::bind(address).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    sink.log(&record("four"));
+    assert!(sink.is_connected());
+    assert_eq!(sink.backlog_len(), 0);
+    assert_eq!(sink.dropped(), 2);
+
+    let mut lines = BufReader::new(accept(&listener)).lines();
+    assert!(lines.next().unwrap().unwrap().ends_with("three"));
+    assert!(lines.next().unwrap().unwrap().ends_with("four"));
+}
+
+#[test]
+fn test_tcp_sink_waits_before_connecting_again() {
+    let (listener, address) = listener();
+    drop(listener);
+
+    let sink = TcpSink::builder(address.to_string())
+        .backoff(Duration::from_secs(60), Duration::from_secs(60))
+        .build();
+    let _listener = TcpListener::bind(address).unwrap();
+
+    // Too soon after the failure to try again
+    sink.log(&record("one"));
+    assert!(!sink.is_connected());
+    assert_eq!(sink.backlog_len(), 1);
+}
+
+#[test]
+fn test_tcp_sink_write_timeout() {
+    let (listener, address) = listener();
+    let sink = TcpSink::builder(address.to_string())
+        .write_timeout(Duration::from_millis(20))
+        .backoff(Duration::from_secs(60), Duration::from_secs(60))
+        .build();
+    // The collector takes the connection but never reads from it
+    let _stream = accept(&listener);
+    let big = "x".repeat(64 * 1024);
+
+    let start = Instant::now();
+    let mut logged = 0;
+    while sink.is_connected() {
+        assert!(logged < 10_000, "sending never timed out");
+        sink.log(&record(&big));
+        logged += 1;
+    }
+
+    assert!(sink.backlog_len() > 0);
+    assert!(
+        start.elapsed() < Duration::from_secs(5),
+        "logging was blocked for {:?}",
+        start.elapsed()
+    );
+}
+
+#[test]
+fn test_udp_sink() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let sink = UdpSink::builder(receiver.local_addr().unwrap().to_string())
+        .format(RecordFormat::Gelf)
+        .host("web-1")
+        .build()
+        .unwrap();
+
+    sink.log(&record("hello"));
+
+    let mut buffer = vec![0; 65536];
+    let len = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&buffer[..len]),
+        RecordFormat::Gelf.format(&record("hello"), "web-1")
+    );
+    assert_eq!(sink.dropped(), 0);
+}
+
+#[test]
+fn test_udp_sink_from_logger() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let logger = crate::Logger::new();
+    logger.add_sink(
+        UdpSink::builder(receiver.local_addr().unwrap().to_string())
+            .format(RecordFormat::Json)
+            .build()
+            .unwrap()
+    );
+
+    logger.error("it broke");
+
+    let mut buffer = vec![0; 65536];
+    let len = receiver.recv(&mut buffer).unwrap();
+    let message = String::from_utf8_lossy(&buffer[..len]).to_string();
+    assert!(message.contains("\"level\":\"ERROR\",\"message\":\"it broke\""));
+    assert!(message.contains(&format!("\"file\":\"{}\"", file!())));
+}
@@ -0,0 +1,120 @@
+//! A panic hook that logs panics, so that they end up in the log file and
+//! sinks along with everything else, and flushes the loggers before the
+//! program goes down.
+
+#[cfg(test)]
+mod tests;
+
+use crate::global::{self, global_logger};
+use crate::{InLogger, Logger};
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::panic::{self, Location};
+use std::thread;
+
+/// Settings for the panic hook, which is set with [`install`].
+///
+/// [`install`]: PanicHook::install
+///
+/// # Example
+/// ```
+/// use pokey_logger::PanicHook;
+///
+/// PanicHook::new().backtrace(true).install();
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PanicHook {
+    /// The logger to log to. If none, the [`global_logger`] at the time of
+    /// the panic is used.
+    logger: Option<&'static Logger>,
+    /// Whether or not to log a backtrace with the panic.
+    backtrace: bool
+}
+
+impl PanicHook {
+    /// Log to the global logger, without a backtrace.
+    pub fn new() -> PanicHook {
+        PanicHook::default()
+    }
+
+    /// Log to the given logger instead of the global logger.
+    pub fn logger(mut self, logger: &'static Logger) -> PanicHook {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Set whether or not a backtrace is logged with the panic. It is
+    /// captured whether or not `RUST_BACKTRACE` is set.
+    pub fn backtrace(mut self, backtrace: bool) -> PanicHook {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// Replace the panic hook with one that logs the panic message and
+    /// location at the `Error` level, dumps the flight recorder of the
+    /// logger if it has one, then flushes the logger, and then calls the
+    /// hook that was there before, which by default prints the panic to
+    /// stderr.
+    ///
+    /// The global loggers are flushed too, so that everything logged before
+    /// the panic is written. Panics are never held back by the rate limit
+    /// or collapsed as repeats.
+    ///
+    /// A panic inside a logger, such as in a sink or clock, is not logged,
+    /// since the logger may be holding a lock at the time. Locks left
+    /// poisoned by earlier panics do not stop the panic from being logged.
+    pub fn install(self) {
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            // A panic from inside a logger, such as in a sink, is only given
+            // to the previous hook, as the logger may be holding a lock
+            if !InLogger::active() {
+                let logger = self.logger.unwrap_or_else(global_logger);
+                logger.log_panic(&self.message(info.payload(), info.location()));
+                if let Err(e) = logger.dump_flight_recorder() {
+                    logger.flight_recorder_dump_failed(&e);
+                }
+                let _ = logger.flush();
+                global::flush_global_loggers();
+            }
+
+            previous(info);
+        }));
+    }
+
+    /// The message logged for a panic, such as
+    /// `thread 'main' panicked at src/main.rs:4:5: out of cheese`.
+    fn message(&self, payload: &(dyn Any + Send), location: Option<&Location<'_>>) -> String {
+        let reason = match payload.downcast_ref::<&str>() {
+            Some(reason) => *reason,
+            None => match payload.downcast_ref::<String>() {
+                Some(reason) => reason.as_str(),
+                None => "Box<dyn Any>"
+            }
+        };
+        let current = thread::current();
+        let thread_name = current.name().unwrap_or("<unnamed>");
+
+        let mut message = match location {
+            Some(location) => {
+                format!("thread '{thread_name}' panicked at {location}: {reason}")
+            }
+            None => format!("thread '{thread_name}' panicked: {reason}")
+        };
+        if self.backtrace {
+            message.push_str(&format!(
+                "\nstack backtrace:\n{}",
+                Backtrace::force_capture()
+            ));
+        }
+
+        message
+    }
+}
+
+/// Install the default [`PanicHook`], which logs panics to the global logger
+/// without a backtrace.
+pub fn install_panic_hook() {
+    PanicHook::new().install();
+}
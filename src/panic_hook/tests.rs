@@ -0,0 +1,198 @@
+use super::*;
+use crate::rate_limit::RateLimit;
+use crate::Level;
+use std::time::Duration;
+
+/// Set for the processes that the tests run themselves again in.
+const CHILD_VAR: &str = "POKEY_LOGGER_TEST_PANIC_HOOK";
+
+/// Run the body of a test in a process of its own. The panic hook is shared
+/// by the whole process, so changing it would affect the other tests, which
+/// run at the same time and may panic on purpose.
+fn in_child_process(test: &str, body: impl FnOnce()) {
+    if std::env::var_os(CHILD_VAR).is_some() {
+        body();
+        return;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            &format!("panic_hook::tests::{test}"),
+            "--test-threads=1"
+        ])
+        .env(CHILD_VAR, "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Install a hook for a new logger, panic with it, and return what the
+/// logger captured.
+fn captured_panic(hook: PanicHook) -> crate::Capture {
+    let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+    let capture = logger.capture();
+
+    // Keep the test output clean instead of chaining to the default hook
+    panic::set_hook(Box::new(|_| {}));
+    hook.logger(logger).install();
+    let result = panic::catch_unwind(|| panic!("out of {}", "cheese"));
+
+    assert!(result.is_err());
+    capture
+}
+
+#[test]
+fn test_logs_message_and_location() {
+    in_child_process("test_logs_message_and_location", || {
+        let capture = captured_panic(PanicHook::new());
+
+        assert_eq!(capture.len(), 1);
+        let record = &capture.records()[0];
+        assert_eq!(record.level, Level::Error);
+        assert!(
+            record.message.contains("panicked at "),
+            "{}",
+            record.message
+        );
+        assert!(record.message.contains("tests.rs:"), "{}", record.message);
+        assert!(
+            record.message.ends_with(": out of cheese"),
+            "{}",
+            record.message
+        );
+        assert!(!record.message.contains("stack backtrace"));
+    });
+}
+
+#[test]
+fn test_logs_backtrace() {
+    in_child_process("test_logs_backtrace", || {
+        let capture = captured_panic(PanicHook::new().backtrace(true));
+
+        let record = &capture.records()[0];
+        assert!(
+            record.message.contains("out of cheese\nstack backtrace:\n"),
+            "{}",
+            record.message
+        );
+    });
+}
+
+#[test]
+fn test_chains_to_previous_hook() {
+    in_child_process("test_chains_to_previous_hook", || {
+        let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+        let called = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let previous_called = called.clone();
+        panic::set_hook(Box::new(move |_| {
+            previous_called.store(true, std::sync::atomic::Ordering::SeqCst)
+        }));
+        PanicHook::new().logger(logger).install();
+        let _ = panic::catch_unwind(|| panic!("chained"));
+
+        assert!(called.load(std::sync::atomic::Ordering::SeqCst));
+    });
+}
+
+#[test]
+fn test_dumps_the_flight_recorder() {
+    in_child_process("test_dumps_the_flight_recorder", || {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crash.log");
+        let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+        logger.set_level(Level::None);
+        logger.set_flight_recorder(Some(
+            crate::flight_recorder::FlightRecorder::new(10)
+                .trigger(Level::None)
+                .dump_to(crate::flight_recorder::DumpTarget::File(path.clone()))
+        ));
+        logger.debug("before the panic");
+
+        panic::set_hook(Box::new(|_| {}));
+        PanicHook::new().logger(logger).install();
+        let _ = panic::catch_unwind(|| panic!("out of cheese"));
+
+        let dump = std::fs::read_to_string(&path).unwrap();
+        assert!(dump.contains("[DEBUG] before the panic\n"), "{dump}");
+        assert!(dump.contains(": out of cheese\n"), "{dump}");
+    });
+}
+
+/// A sink that panics on every record.
+#[derive(Debug)]
+struct PanickingSink;
+
+impl crate::sink::Sink for PanickingSink {
+    fn log(&self, _record: &crate::sink::Record) {
+        panic!("sink failed");
+    }
+}
+
+#[test]
+fn test_panic_inside_the_logger_is_not_logged() {
+    in_child_process("test_panic_inside_the_logger_is_not_logged", || {
+        let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+        let capture = logger.capture();
+        logger.add_sink(PanickingSink);
+
+        panic::set_hook(Box::new(|_| {}));
+        PanicHook::new().logger(logger).install();
+        // Logging the panic would wait forever on the lock of the sinks
+        let result = panic::catch_unwind(|| logger.info("to the sinks"));
+
+        assert!(result.is_err());
+        assert_eq!(capture.len(), 1);
+        assert!(capture.contains(Level::Info, "to the sinks"));
+    });
+}
+
+#[test]
+fn test_logs_with_poisoned_locks() {
+    in_child_process("test_logs_with_poisoned_locks", || {
+        let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+        let capture = logger.capture();
+
+        panic::set_hook(Box::new(|_| {}));
+        let _ = std::thread::spawn(move || {
+            let _level = logger.level.lock();
+            panic!("poison the level");
+        })
+        .join();
+        PanicHook::new().logger(logger).install();
+        let result = panic::catch_unwind(|| panic!("out of {}", "cheese"));
+
+        assert!(result.is_err());
+        assert!(capture.contains(Level::Error, "out of cheese"));
+    });
+}
+
+#[test]
+fn test_panics_are_not_rate_limited_or_collapsed() {
+    in_child_process("test_panics_are_not_rate_limited_or_collapsed", || {
+        let logger: &'static Logger = Box::leak(Box::new(Logger::new()));
+        let capture = logger.capture();
+        logger.set_rate_limit(Some(RateLimit::new(1, Duration::from_secs(60))));
+        logger.set_collapse_duplicates(true);
+
+        panic::set_hook(Box::new(|_| {}));
+        PanicHook::new().logger(logger).install();
+        for _ in 0..3 {
+            let _ = panic::catch_unwind(|| panic!("out of cheese"));
+        }
+
+        let panics = capture
+            .records()
+            .iter()
+            .filter(|record| record.message.ends_with(": out of cheese"))
+            .count();
+        assert_eq!(panics, 3);
+        assert_eq!(capture.len(), 3);
+    });
+}
@@ -0,0 +1,257 @@
+//! This module contains [`RateLimit`], which limits how many messages are
+//! logged from one place in the code, and the state used for it and for
+//! collapsing repeated messages.
+//!
+//! ```
+//! use pokey_logger::rate_limit::RateLimit;
+//! use pokey_logger::{lwarn_limited, Logger};
+//! use std::time::Duration;
+//!
+//! let logger = Logger::new();
+//! // Every call site logs at most 100 messages a second
+//! logger.set_rate_limit(Some(RateLimit::new(100, Duration::from_secs(1))));
+//! // Write `last message repeated 4 times` instead of the same line again
+//! logger.set_collapse_duplicates(true);
+//!
+//! for _ in 0..5 {
+//!     // This call site only logs one message a minute
+//!     lwarn_limited!(logger, RateLimit::new(1, Duration::from_secs(60)), "disk almost full");
+//! }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::parse_duration;
+use crate::sink::Record;
+use crate::Level;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// The most messages logged from one call site in each interval.
+///
+/// The intervals start at the first message from the call site. Messages
+/// over the limit are not logged, and when the next message from the call
+/// site is logged in a later interval, it comes after a message saying how
+/// many were not. The counts not said yet are logged when the logger is
+/// flushed or dropped.
+///
+/// As a string, such as in config files and environment variables, a limit
+/// is written as `<messages>/<interval>`, such as `10/1s` or `1/5m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct RateLimit {
+    /// How many messages may be logged in each interval.
+    pub messages: u32,
+    /// How long each interval is.
+    pub interval: Duration
+}
+
+impl RateLimit {
+    /// Allow the given number of messages in each interval.
+    pub fn new(messages: u32, interval: Duration) -> RateLimit {
+        RateLimit { messages, interval }
+    }
+}
+
+impl Display for RateLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}ms", self.messages, self.interval.as_millis())
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// Parse a limit such as `10/1s`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate limit: {}", s);
+        let (messages, interval) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let messages = messages.trim().parse().map_err(|_| invalid())?;
+        let interval = parse_duration(interval.trim()).ok_or_else(invalid)?;
+        if interval.is_zero() {
+            return Err(invalid());
+        }
+
+        Ok(RateLimit { messages, interval })
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RateLimit> for String {
+    fn from(limit: RateLimit) -> Self {
+        limit.to_string()
+    }
+}
+
+/// What a [`RateLimit`] needs to remember about each call site.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    sites: HashMap<&'static Location<'static>, Site>
+}
+
+#[derive(Debug)]
+struct Site {
+    /// When the current interval started.
+    interval_start: SystemTime,
+    /// How many messages were logged in the current interval.
+    logged: u32,
+    /// How many messages were not logged since the last one that was.
+    suppressed: u64,
+    /// The level of the last message that was not logged.
+    level: Level
+}
+
+impl RateLimiter {
+    pub(crate) fn new() -> RateLimiter {
+        RateLimiter::default()
+    }
+
+    /// Check whether a message at the level from the call site may be logged
+    /// at the given time. If it may, gives how many messages from the call
+    /// site were not logged since the last one that was, and otherwise none.
+    pub(crate) fn allow(
+        &mut self,
+        site: &'static Location<'static>,
+        level: Level,
+        limit: RateLimit,
+        now: SystemTime
+    ) -> Option<u64> {
+        let site = self.sites.entry(site).or_insert(Site {
+            interval_start: now,
+            logged: 0,
+            suppressed: 0,
+            level
+        });
+
+        // A clock that went backwards also starts a new interval
+        let elapsed = now.duration_since(site.interval_start);
+        if !matches!(elapsed, Ok(elapsed) if elapsed < limit.interval) {
+            site.interval_start = now;
+            site.logged = 0;
+        }
+
+        if site.logged < limit.messages {
+            site.logged += 1;
+            Some(std::mem::take(&mut site.suppressed))
+        } else {
+            site.suppressed += 1;
+            site.level = level;
+            None
+        }
+    }
+
+    /// The records that say how many messages from each call site were not
+    /// logged since the last one that was, as of the given time, for the
+    /// call sites where there were some. The counts start again afterwards.
+    pub(crate) fn take_summaries(&mut self, time: SystemTime) -> Vec<Record> {
+        let mut sites: Vec<_> = self
+            .sites
+            .iter_mut()
+            .filter(|(_, site)| site.suppressed > 0)
+            .collect();
+        sites.sort_by_key(|(location, _)| (location.file(), location.line(), location.column()));
+
+        sites
+            .into_iter()
+            .map(|(location, site)| {
+                let suppressed = std::mem::take(&mut site.suppressed);
+                summary(location, suppressed, site.level, time)
+            })
+            .collect()
+    }
+}
+
+/// The record that says how many messages from the call site were not
+/// logged.
+pub(crate) fn summary(
+    location: &'static Location<'static>,
+    suppressed: u64,
+    level: Level,
+    time: SystemTime
+) -> Record {
+    let mut summary = Record::new(
+        level,
+        &format!(
+            "{suppressed} messages from {}:{} were suppressed by the rate limit",
+            location.file(),
+            location.line()
+        ),
+        time
+    );
+    summary.location = Some(location);
+
+    summary
+}
+
+/// The last message logged, for collapsing messages that repeat it.
+#[derive(Debug, Default)]
+pub(crate) struct Duplicates {
+    last: Option<(Level, String, Option<&'static Location<'static>>)>,
+    /// How many times the last message was repeated and not logged.
+    repeated: u64
+}
+
+impl Duplicates {
+    pub(crate) fn new() -> Duplicates {
+        Duplicates::default()
+    }
+
+    /// Whether the record has the same level and message as the last one.
+    /// If it does, it is counted.
+    pub(crate) fn is_repeat(&mut self, record: &Record) -> bool {
+        let repeat = matches!(
+            self.last,
+            Some((level, ref message, _)) if level == record.level && *message == record.message
+        );
+        if repeat {
+            self.repeated += 1;
+        }
+
+        repeat
+    }
+
+    /// Remember the record as the last message. Gives the record that says
+    /// how many times the message before it was repeated, if it was.
+    pub(crate) fn replace(&mut self, record: &Record) -> Option<Record> {
+        let summary = self.take_summary(record.time);
+        self.last = Some((record.level, record.message.clone(), record.location));
+
+        summary
+    }
+
+    /// The record that says how many times the last message was repeated,
+    /// if it was, as of the given time. The count starts again afterwards.
+    pub(crate) fn take_summary(&mut self, time: SystemTime) -> Option<Record> {
+        let (level, _, location) = self.last.as_ref()?;
+        if self.repeated == 0 {
+            return None;
+        }
+
+        let mut summary = Record::new(
+            *level,
+            &format!("last message repeated {} times", self.repeated),
+            time
+        );
+        summary.location = *location;
+        self.repeated = 0;
+
+        Some(summary)
+    }
+}
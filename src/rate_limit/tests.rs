@@ -0,0 +1,335 @@
+use super::*;
+use crate::clock::ManualClock;
+use crate::Logger;
+use std::time::UNIX_EPOCH;
+
+fn messages(capture: &crate::Capture) -> Vec<String> {
+    capture
+        .records()
+        .into_iter()
+        .map(|record| record.message)
+        .collect()
+}
+
+/// A logger with a manual clock that only prints errors, so that the
+/// captures are the only thing that matters.
+fn logger() -> (Logger, ManualClock, crate::Capture) {
+    let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let logger = Logger::new();
+    logger.set_clock(clock.clone());
+    let capture = logger.capture();
+    (logger, clock, capture)
+}
+
+#[test]
+fn test_from_str() {
+    assert_eq!(
+        Ok(RateLimit::new(10, Duration::from_secs(1))),
+        "10/1s".parse()
+    );
+    assert_eq!(
+        Ok(RateLimit::new(1, Duration::from_secs(300))),
+        " 1 / 5m ".parse()
+    );
+    assert_eq!(
+        Ok(RateLimit::new(0, Duration::from_millis(250))),
+        "0/250ms".parse()
+    );
+
+    for invalid in ["", "10", "10/", "/1s", "ten/1s", "-1/1s", "10/1", "10/0s"] {
+        assert!(invalid.parse::<RateLimit>().is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_display_round_trips() {
+    for limit in [
+        RateLimit::new(10, Duration::from_secs(1)),
+        RateLimit::new(3, Duration::from_millis(250))
+    ] {
+        assert_eq!(Ok(limit), limit.to_string().parse(), "{}", limit);
+    }
+}
+
+#[test]
+fn test_rate_limiter() {
+    let mut limiter = RateLimiter::new();
+    let limit = RateLimit::new(2, Duration::from_secs(10));
+    let site = Location::caller();
+    let other_site = Location::caller();
+    let start = UNIX_EPOCH;
+
+    assert_eq!(limiter.allow(site, Level::Warn, limit, start), Some(0));
+    assert_eq!(limiter.allow(site, Level::Warn, limit, start), Some(0));
+    assert_eq!(
+        limiter.allow(site, Level::Warn, limit, start + Duration::from_secs(9)),
+        None
+    );
+    assert_eq!(
+        limiter.allow(site, Level::Warn, limit, start + Duration::from_secs(9)),
+        None
+    );
+    // Each call site has its own count
+    assert_eq!(
+        limiter.allow(other_site, Level::Warn, limit, start),
+        Some(0)
+    );
+
+    // A new interval, with the count of what was held back
+    assert_eq!(
+        limiter.allow(site, Level::Warn, limit, start + Duration::from_secs(10)),
+        Some(2)
+    );
+    assert_eq!(
+        limiter.allow(site, Level::Warn, limit, start + Duration::from_secs(11)),
+        Some(0)
+    );
+    assert_eq!(
+        limiter.allow(site, Level::Warn, limit, start + Duration::from_secs(12)),
+        None
+    );
+    // The clock going backwards starts a new interval too
+    assert_eq!(limiter.allow(site, Level::Warn, limit, start), Some(1));
+}
+
+#[test]
+fn test_rate_limiter_summaries() {
+    let mut limiter = RateLimiter::new();
+    let limit = RateLimit::new(1, Duration::from_secs(10));
+    let site = Location::caller();
+    let other_site = Location::caller();
+
+    limiter.allow(site, Level::Info, limit, UNIX_EPOCH);
+    limiter.allow(site, Level::Info, limit, UNIX_EPOCH);
+    limiter.allow(site, Level::Error, limit, UNIX_EPOCH);
+    limiter.allow(other_site, Level::Info, limit, UNIX_EPOCH);
+
+    let summaries = limiter.take_summaries(UNIX_EPOCH);
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].level, Level::Error);
+    assert_eq!(summaries[0].location, Some(site));
+    assert_eq!(
+        summaries[0].message,
+        format!(
+            "2 messages from {}:{} were suppressed by the rate limit",
+            site.file(),
+            site.line()
+        )
+    );
+    // The counts start again
+    assert!(limiter.take_summaries(UNIX_EPOCH).is_empty());
+    assert_eq!(
+        limiter.allow(
+            site,
+            Level::Info,
+            limit,
+            UNIX_EPOCH + Duration::from_secs(10)
+        ),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_duplicates() {
+    let mut duplicates = Duplicates::new();
+    let record = |level, message: &str| Record::new(level, message, UNIX_EPOCH);
+
+    assert!(!duplicates.is_repeat(&record(Level::Warn, "same")));
+    assert!(duplicates.replace(&record(Level::Warn, "same")).is_none());
+    assert!(duplicates.is_repeat(&record(Level::Warn, "same")));
+    assert!(duplicates.is_repeat(&record(Level::Warn, "same")));
+    // The same text at another level is a different message
+    assert!(!duplicates.is_repeat(&record(Level::Error, "same")));
+
+    let summary = duplicates.replace(&record(Level::Error, "same")).unwrap();
+    assert_eq!(summary.level, Level::Warn);
+    assert_eq!(summary.message, "last message repeated 2 times");
+    assert!(duplicates.take_summary(UNIX_EPOCH).is_none());
+}
+
+#[test]
+fn test_logger_rate_limit() {
+    let (logger, clock, capture) = logger();
+    logger.set_rate_limit(Some(RateLimit::new(2, Duration::from_secs(60))));
+
+    let line = line!() + 5;
+    for i in 0..6 {
+        if i == 5 {
+            clock.advance(Duration::from_secs(60));
+        }
+        logger.warn(&format!("loop {i}"));
+        if i == 4 {
+            logger.info("another call site");
+        }
+    }
+
+    assert_eq!(
+        messages(&capture),
+        [
+            "loop 0".to_string(),
+            "loop 1".to_string(),
+            "another call site".to_string(),
+            format!(
+                "3 messages from {}:{line} were suppressed by the rate limit",
+                file!()
+            ),
+            "loop 5".to_string()
+        ]
+    );
+    let summary = &capture.records()[3];
+    assert_eq!(summary.level, Level::Warn);
+    assert_eq!(summary.location.unwrap().line(), line);
+}
+
+#[test]
+fn test_limited_macros() {
+    let (logger, clock, capture) = logger();
+    let once_a_minute = RateLimit::new(1, Duration::from_secs(60));
+
+    for i in 0..3 {
+        lwarn_limited!(logger, once_a_minute, "disk {}% full", 90 + i);
+        lerror_limited!(
+            logger,
+            RateLimit::new(2, Duration::from_secs(60)),
+            "error {i}"
+        );
+    }
+    clock.advance(Duration::from_secs(30));
+    ldebug_limited!(logger, once_a_minute, "debug");
+    linfo_limited!(logger, once_a_minute, "info");
+    clock.advance(Duration::from_secs(30));
+    lwarn_limited!(logger, once_a_minute, "back to normal");
+
+    let messages = messages(&capture);
+    assert_eq!(
+        messages[..5],
+        ["disk 90% full", "error 0", "error 1", "debug", "info"]
+    );
+    // The macros in the loop and after it are different call sites
+    assert_eq!(messages[5], "back to normal");
+    assert_eq!(messages.len(), 6);
+}
+
+#[test]
+fn test_limited_macro_overrides_logger_limit() {
+    let (logger, _clock, capture) = logger();
+    logger.set_rate_limit(Some(RateLimit::new(1, Duration::from_secs(60))));
+
+    for _ in 0..3 {
+        linfo_limited!(logger, RateLimit::new(3, Duration::from_secs(60)), "info");
+    }
+
+    assert_eq!(capture.len(), 3);
+}
+
+#[test]
+fn test_logger_collapses_duplicates() {
+    let (logger, _clock, capture) = logger();
+    logger.set_collapse_duplicates(true);
+
+    for _ in 0..5124 {
+        logger.warn("connection refused");
+    }
+    logger.warn("connected");
+    logger.warn("connected");
+    logger.error("connected");
+    logger.debug("once");
+
+    assert_eq!(
+        messages(&capture),
+        [
+            "connection refused",
+            "last message repeated 5123 times",
+            "connected",
+            "last message repeated 1 times",
+            "connected",
+            "once"
+        ]
+    );
+}
+
+#[test]
+fn test_flush_logs_the_repeat_count() {
+    let (logger, _clock, capture) = logger();
+    logger.set_collapse_duplicates(true);
+
+    logger.info("same");
+    logger.info("same");
+    logger.info("same");
+    logger.flush().unwrap();
+    logger.info("same");
+    logger.flush().unwrap();
+    logger.flush().unwrap();
+
+    assert_eq!(
+        messages(&capture),
+        [
+            "same",
+            "last message repeated 2 times",
+            "last message repeated 1 times"
+        ]
+    );
+}
+
+#[test]
+fn test_flush_logs_the_suppressed_counts() {
+    let (logger, clock, capture) = logger();
+    logger.set_rate_limit(Some(RateLimit::new(1, Duration::from_secs(60))));
+
+    let line = line!() + 2;
+    for i in 0..4 {
+        logger.warn(&format!("loop {i}"));
+    }
+    logger.flush().unwrap();
+    logger.flush().unwrap();
+    clock.advance(Duration::from_secs(60));
+    logger.warn("after");
+
+    assert_eq!(
+        messages(&capture),
+        [
+            "loop 0".to_string(),
+            format!(
+                "3 messages from {}:{line} were suppressed by the rate limit",
+                file!()
+            ),
+            "after".to_string()
+        ]
+    );
+    assert_eq!(capture.records()[1].level, Level::Warn);
+}
+
+#[test]
+fn test_drop_logs_the_suppressed_counts() {
+    let (logger, _clock, capture) = logger();
+    logger.set_rate_limit(Some(RateLimit::new(1, Duration::from_secs(60))));
+
+    for _ in 0..3 {
+        logger.info("held back");
+    }
+    drop(logger);
+
+    let messages = messages(&capture);
+    assert_eq!(messages.len(), 2);
+    assert!(
+        messages[1].starts_with("2 messages from "),
+        "{}",
+        messages[1]
+    );
+}
+
+#[test]
+fn test_messages_below_the_level_are_not_counted() {
+    let (logger, _clock, capture) = logger();
+    logger.set_level(Level::Info);
+    logger.set_collapse_duplicates(true);
+    logger.set_rate_limit(Some(RateLimit::new(1, Duration::from_secs(60))));
+
+    logger.info("same");
+    for _ in 0..3 {
+        logger.debug("same");
+    }
+    logger.warn("different");
+
+    assert_eq!(messages(&capture), ["same", "different"]);
+}
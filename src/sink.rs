@@ -0,0 +1,89 @@
+//! Extra outputs for log messages, in addition to the terminal and the log
+//! file.
+//!
+//! A [`Sink`] is given a [`Record`] for every message that passes the level
+//! filter of the [`Logger`](crate::Logger) it was added to with
+//! [`add_sink`](crate::Logger::add_sink).
+
+use crate::Level;
+use std::fmt::Debug;
+use std::io;
+use std::panic::Location;
+use std::process;
+use std::thread;
+use std::time::SystemTime;
+
+/// A single log message, before it is formatted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The level the message was logged at.
+    pub level: Level,
+    /// The message itself, without any prefix.
+    pub message: String,
+    /// When the message was logged, according to the logger's
+    /// [`Clock`](crate::clock::Clock).
+    pub time: SystemTime,
+    /// The name of the thread that logged the message, or its ID if it has
+    /// no name.
+    pub thread: String,
+    /// The ID of the process that logged the message.
+    pub process_id: u32,
+    /// Where in the code the message was logged, when it was logged through
+    /// one of the logging methods or macros.
+    pub location: Option<&'static Location<'static>>
+}
+
+impl Record {
+    /// Create a record of a message logged at the given time, by the
+    /// current thread.
+    pub fn new(level: Level, message: &str, time: SystemTime) -> Record {
+        let current = thread::current();
+        let thread = match current.name() {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", current.id())
+        };
+
+        Record {
+            level,
+            message: message.to_string(),
+            time,
+            thread,
+            process_id: process::id(),
+            location: None
+        }
+    }
+
+    /// Set where in the code the message was logged.
+    pub fn with_location(mut self, location: &'static Location<'static>) -> Record {
+        self.location = Some(location);
+        self
+    }
+}
+
+/// Somewhere that log records can be sent.
+///
+/// Sinks are shared between all the threads that log to the logger, so they
+/// must handle their own synchronization. A sink should never panic, and
+/// should handle its own errors.
+pub trait Sink: Debug + Send + Sync {
+    /// Output a single record.
+    fn log(&self, record: &Record);
+
+    /// Make sure every record given so far is fully output. Called by
+    /// [`Logger::flush`](crate::Logger::flush).
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The file name of the running executable, for sinks that name the program
+/// in their messages.
+pub(crate) fn executable_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_default()
+}
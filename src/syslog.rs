@@ -0,0 +1,439 @@
+//! A [`Sink`] that sends messages to the local syslog daemon over a Unix
+//! socket, such as `/dev/log`.
+//!
+//! This module is not included unless the `syslog` feature is enabled, and
+//! only on Unix.
+//!
+//! ```no_run
+//! use pokey_logger::syslog::{Facility, SyslogSink};
+//! use pokey_logger::LOGGER;
+//!
+//! let sink = SyslogSink::builder()
+//!     .facility(Facility::Daemon)
+//!     .app_name("my_server")
+//!     .connect()
+//!     .expect("could not connect to syslog");
+//! LOGGER.add_sink(sink);
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::sink::{executable_name, Record, Sink};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Where syslog daemons usually listen.
+pub const DEFAULT_SOCKET_PATH: &str = "/dev/log";
+
+/// The longest APP-NAME of RFC 5424.
+const MAX_APP_NAME_LEN: usize = 48;
+/// The longest TAG of RFC 3164, which is where the app name goes.
+const MAX_TAG_LEN: usize = 32;
+/// The longest HOSTNAME of RFC 5424.
+const MAX_HOSTNAME_LEN: usize = 255;
+
+/// The format of the messages sent to syslog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFormat {
+    /// The current syslog protocol, with the full date and time, year
+    /// included, such as
+    /// `<30>1 2022-03-12T18:14:09.123456Z - my_server 1234 - - message`.
+    #[default]
+    Rfc5424,
+    /// The older BSD syslog format, which every daemon understands, such as
+    /// `<30>Mar 12 18:14:09 my_server[1234]: message`. The time is in the
+    /// local timezone.
+    Rfc3164
+}
+
+/// How to connect to the syslog socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogTransport {
+    /// Use a datagram socket, and a stream socket if the socket at the path
+    /// is not a datagram socket. This is what the C library does.
+    #[default]
+    Auto,
+    /// Send each message as a datagram.
+    Datagram,
+    /// Send messages over a stream, each ending with a null byte.
+    Stream
+}
+
+/// The kind of program that is logging, which syslog daemons use to decide
+/// where messages go. The default is `User`, for normal programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Facility {
+    /// Messages from the kernel.
+    Kern = 0,
+    /// Messages from normal programs.
+    #[default]
+    User = 1,
+    /// The mail system.
+    Mail = 2,
+    /// System daemons.
+    Daemon = 3,
+    /// Security and authorization messages.
+    Auth = 4,
+    /// Messages from the syslog daemon itself.
+    Syslog = 5,
+    /// The line printer system.
+    Lpr = 6,
+    /// The network news system.
+    News = 7,
+    /// The UUCP system.
+    Uucp = 8,
+    /// The clock daemon, such as cron.
+    Cron = 9,
+    /// Private security and authorization messages.
+    AuthPriv = 10,
+    /// The FTP daemon.
+    Ftp = 11,
+    /// Local use 0, which each site assigns as it likes.
+    Local0 = 16,
+    /// Local use 1.
+    Local1 = 17,
+    /// Local use 2.
+    Local2 = 18,
+    /// Local use 3.
+    Local3 = 19,
+    /// Local use 4.
+    Local4 = 20,
+    /// Local use 5.
+    Local5 = 21,
+    /// Local use 6.
+    Local6 = 22,
+    /// Local use 7.
+    Local7 = 23
+}
+
+impl Display for Facility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Facility::Kern => "kern",
+            Facility::User => "user",
+            Facility::Mail => "mail",
+            Facility::Daemon => "daemon",
+            Facility::Auth => "auth",
+            Facility::Syslog => "syslog",
+            Facility::Lpr => "lpr",
+            Facility::News => "news",
+            Facility::Uucp => "uucp",
+            Facility::Cron => "cron",
+            Facility::AuthPriv => "authpriv",
+            Facility::Ftp => "ftp",
+            Facility::Local0 => "local0",
+            Facility::Local1 => "local1",
+            Facility::Local2 => "local2",
+            Facility::Local3 => "local3",
+            Facility::Local4 => "local4",
+            Facility::Local5 => "local5",
+            Facility::Local6 => "local6",
+            Facility::Local7 => "local7"
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Facility {
+    type Err = String;
+
+    /// Parse the name of a facility, such as `daemon` or `local0`, ignoring
+    /// case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "kern" => Ok(Facility::Kern),
+            "user" => Ok(Facility::User),
+            "mail" => Ok(Facility::Mail),
+            "daemon" => Ok(Facility::Daemon),
+            "auth" => Ok(Facility::Auth),
+            "syslog" => Ok(Facility::Syslog),
+            "lpr" => Ok(Facility::Lpr),
+            "news" => Ok(Facility::News),
+            "uucp" => Ok(Facility::Uucp),
+            "cron" => Ok(Facility::Cron),
+            "authpriv" => Ok(Facility::AuthPriv),
+            "ftp" => Ok(Facility::Ftp),
+            "local0" => Ok(Facility::Local0),
+            "local1" => Ok(Facility::Local1),
+            "local2" => Ok(Facility::Local2),
+            "local3" => Ok(Facility::Local3),
+            "local4" => Ok(Facility::Local4),
+            "local5" => Ok(Facility::Local5),
+            "local6" => Ok(Facility::Local6),
+            "local7" => Ok(Facility::Local7),
+            _ => Err(format!("Invalid syslog facility: {}", s))
+        }
+    }
+}
+
+/// Settings for a [`SyslogSink`], which is created with
+/// [`connect`](SyslogBuilder::connect).
+#[derive(Debug, Clone)]
+pub struct SyslogBuilder {
+    path: PathBuf,
+    transport: SyslogTransport,
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: Option<String>,
+    hostname: Option<String>,
+    write_timeout: Duration
+}
+
+impl SyslogBuilder {
+    /// Send RFC 5424 messages with the `user` facility to `/dev/log`, named
+    /// after the executable.
+    pub fn new() -> SyslogBuilder {
+        SyslogBuilder {
+            path: PathBuf::from(DEFAULT_SOCKET_PATH),
+            transport: SyslogTransport::default(),
+            format: SyslogFormat::default(),
+            facility: Facility::default(),
+            app_name: None,
+            hostname: None,
+            write_timeout: Duration::from_millis(100)
+        }
+    }
+
+    /// Set the path of the Unix socket the daemon listens on.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> SyslogBuilder {
+        self.path = path.into();
+        self
+    }
+
+    /// Set how to connect to the socket.
+    pub fn transport(mut self, transport: SyslogTransport) -> SyslogBuilder {
+        self.transport = transport;
+        self
+    }
+
+    /// Set the format of the messages.
+    pub fn format(mut self, format: SyslogFormat) -> SyslogBuilder {
+        self.format = format;
+        self
+    }
+
+    /// Set the facility of the messages.
+    pub fn facility(mut self, facility: Facility) -> SyslogBuilder {
+        self.facility = facility;
+        self
+    }
+
+    /// Set the name of the program in the messages. By default this is the
+    /// name of the executable. Only printable ASCII is kept, cut to 48
+    /// characters, or 32 for RFC 3164.
+    pub fn app_name<S: Into<String>>(mut self, app_name: S) -> SyslogBuilder {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Set the host name in the messages. By default it is left out, and the
+    /// daemon fills it in, which is usual for a local socket. Only printable
+    /// ASCII is kept, cut to 255 characters.
+    pub fn hostname<S: Into<String>>(mut self, hostname: S) -> SyslogBuilder {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Set how long to wait for the daemon to take a message before it is
+    /// treated as lost. The default is 100 milliseconds.
+    ///
+    /// Sending blocks the thread that logs while the sink is locked, such as
+    /// when a daemon on a stream socket stops reading.
+    pub fn write_timeout(mut self, timeout: Duration) -> SyslogBuilder {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Connect to the socket and create the sink.
+    pub fn connect(self) -> io::Result<SyslogSink> {
+        let socket = SyslogSocket::connect(&self.path, self.transport, self.write_timeout)?;
+        let app_name = match self.app_name {
+            Some(app_name) => app_name,
+            None => executable_name()
+        };
+
+        Ok(SyslogSink {
+            socket: Mutex::new(Some(socket)),
+            path: self.path,
+            transport: self.transport,
+            format: self.format,
+            facility: self.facility,
+            app_name,
+            hostname: self.hostname,
+            write_timeout: self.write_timeout,
+            dropped: AtomicU64::new(0)
+        })
+    }
+}
+
+impl Default for SyslogBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A sink that sends every record to a syslog daemon over a Unix socket.
+///
+/// If sending fails, such as when the daemon was restarted, the socket is
+/// connected again and the message is sent once more. Messages that still
+/// can not be sent are dropped and counted.
+#[derive(Debug)]
+pub struct SyslogSink {
+    /// None after sending failed, until it is connected again.
+    socket: Mutex<Option<SyslogSocket>>,
+    path: PathBuf,
+    transport: SyslogTransport,
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: String,
+    hostname: Option<String>,
+    write_timeout: Duration,
+    /// How many messages could not be sent.
+    dropped: AtomicU64
+}
+
+impl SyslogSink {
+    /// Start building a sink. See [`SyslogBuilder`].
+    pub fn builder() -> SyslogBuilder {
+        SyslogBuilder::new()
+    }
+
+    /// Connect to `/dev/log` with all the default settings.
+    pub fn connect() -> io::Result<SyslogSink> {
+        SyslogBuilder::new().connect()
+    }
+
+    /// How many messages could not be sent to the daemon.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Format a record as a syslog message, or none for `Level::None`.
+    pub fn format_record(&self, record: &Record) -> Option<String> {
+        let priority = self.facility as u8 * 8 + record.level.syslog_severity()?;
+
+        Some(match self.format {
+            SyslogFormat::Rfc5424 => format!(
+                "<{priority}>1 {} {} {} {} - - {}",
+                DateTime::<Utc>::from(record.time).to_rfc3339_opts(SecondsFormat::Micros, true),
+                header_field(self.hostname.as_deref().unwrap_or(""), MAX_HOSTNAME_LEN),
+                header_field(&self.app_name, MAX_APP_NAME_LEN),
+                record.process_id,
+                record.message
+            ),
+            SyslogFormat::Rfc3164 => {
+                let time = DateTime::<Local>::from(record.time).format("%b %e %H:%M:%S");
+                let hostname = match self.hostname {
+                    Some(ref hostname) => {
+                        format!("{} ", header_field(hostname, MAX_HOSTNAME_LEN))
+                    }
+                    None => String::new()
+                };
+                format!(
+                    "<{priority}>{time} {hostname}{}[{}]: {}",
+                    header_field(&self.app_name, MAX_TAG_LEN),
+                    record.process_id,
+                    record.message
+                )
+            }
+        })
+    }
+
+    /// Send a message, connecting again and trying once more if it fails.
+    fn send(&self, message: &[u8]) -> io::Result<()> {
+        let mut socket = self.socket.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(connected) = socket.as_mut() {
+            if connected.send(message).is_ok() {
+                return Ok(());
+            }
+        }
+
+        *socket = None;
+        let mut reconnected =
+            SyslogSocket::connect(&self.path, self.transport, self.write_timeout)?;
+        reconnected.send(message)?;
+        *socket = Some(reconnected);
+
+        Ok(())
+    }
+}
+
+impl Sink for SyslogSink {
+    fn log(&self, record: &Record) {
+        if let Some(message) = self.format_record(record) {
+            if self.send(message.as_bytes()).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// A connected syslog socket.
+#[derive(Debug)]
+enum SyslogSocket {
+    Datagram(UnixDatagram),
+    Stream(UnixStream)
+}
+
+impl SyslogSocket {
+    fn connect(
+        path: &std::path::Path,
+        transport: SyslogTransport,
+        write_timeout: Duration
+    ) -> io::Result<SyslogSocket> {
+        let datagram = || -> io::Result<SyslogSocket> {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(path)?;
+            socket.set_write_timeout(Some(write_timeout))?;
+            Ok(SyslogSocket::Datagram(socket))
+        };
+        let stream = || -> io::Result<SyslogSocket> {
+            let socket = UnixStream::connect(path)?;
+            socket.set_write_timeout(Some(write_timeout))?;
+            Ok(SyslogSocket::Stream(socket))
+        };
+
+        match transport {
+            SyslogTransport::Datagram => datagram(),
+            SyslogTransport::Stream => stream(),
+            SyslogTransport::Auto => datagram().or_else(|_| stream())
+        }
+    }
+
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        match self {
+            SyslogSocket::Datagram(socket) => socket.send(message).map(|_| ()),
+            SyslogSocket::Stream(socket) => {
+                // Messages on a stream are separated by a null byte
+                let mut framed = Vec::with_capacity(message.len() + 1);
+                framed.extend_from_slice(message);
+                framed.push(0);
+                socket.write_all(&framed)
+            }
+        }
+    }
+}
+
+/// A header field with only the printable ASCII characters, which leaves out
+/// spaces, cut to the given length, and `-` for an empty value.
+fn header_field(value: &str, max_len: usize) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect();
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        value
+    }
+}
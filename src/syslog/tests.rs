@@ -0,0 +1,250 @@
+use super::*;
+use crate::{Level, Logger};
+use std::io::Read;
+use std::os::unix::net::UnixListener;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// A record logged at 2022-03-12 18:14:09.5 UTC by process 1234.
+fn record(level: Level, message: &str) -> Record {
+    let mut record = Record::new(
+        level,
+        message,
+        UNIX_EPOCH + Duration::from_millis(1_647_108_849_500)
+    );
+    record.process_id = 1234;
+    record
+}
+
+/// A datagram socket in a temporary directory for a sink to send to.
+fn listener() -> (tempfile::TempDir, PathBuf, UnixDatagram) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.sock");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    (dir, path, socket)
+}
+
+fn receive(socket: &UnixDatagram) -> String {
+    let mut buffer = [0; 4096];
+    let len = socket.recv(&mut buffer).unwrap();
+    String::from_utf8_lossy(&buffer[..len]).to_string()
+}
+
+#[test]
+fn test_facility_from_str() {
+    assert_eq!(Ok(Facility::Daemon), "daemon".parse());
+    assert_eq!(Ok(Facility::Local3), "LOCAL3".parse());
+    assert_eq!(Ok(Facility::AuthPriv), "authpriv".parse());
+    assert!("local8".parse::<Facility>().is_err());
+
+    for facility in [Facility::Kern, Facility::User, Facility::Local7] {
+        assert_eq!(Ok(facility), facility.to_string().parse());
+    }
+}
+
+#[test]
+fn test_format_rfc5424() {
+    let (_dir, path, _socket) = listener();
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .facility(Facility::Daemon)
+        .app_name("my server")
+        .connect()
+        .unwrap();
+
+    assert_eq!(
+        sink.format_record(&record(Level::Info, "hello")).unwrap(),
+        "<30>1 2022-03-12T18:14:09.500000Z - myserver 1234 - - hello"
+    );
+    assert!(sink.format_record(&record(Level::None, "never")).is_none());
+
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .facility(Facility::Local0)
+        .app_name("app")
+        .hostname("web1")
+        .connect()
+        .unwrap();
+    assert_eq!(
+        sink.format_record(&record(Level::Error, "failed")).unwrap(),
+        "<131>1 2022-03-12T18:14:09.500000Z web1 app 1234 - - failed"
+    );
+}
+
+#[test]
+fn test_format_rfc3164() {
+    let (_dir, path, _socket) = listener();
+    let builder = SyslogSink::builder()
+        .path(&path)
+        .format(SyslogFormat::Rfc3164)
+        .app_name("app");
+    let record = record(Level::Warn, "careful");
+    let time = DateTime::<Local>::from(record.time).format("%b %e %H:%M:%S");
+
+    assert_eq!(
+        builder
+            .clone()
+            .connect()
+            .unwrap()
+            .format_record(&record)
+            .unwrap(),
+        format!("<12>{time} app[1234]: careful")
+    );
+    assert_eq!(
+        builder
+            .hostname("web1")
+            .connect()
+            .unwrap()
+            .format_record(&record)
+            .unwrap(),
+        format!("<12>{time} web1 app[1234]: careful")
+    );
+}
+
+#[test]
+fn test_sends_datagrams() {
+    let (_dir, path, socket) = listener();
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .app_name("app")
+        .connect()
+        .unwrap();
+    let logger = Logger::new();
+    logger.add_sink(sink);
+
+    logger.debug("first");
+    logger.error("second");
+
+    let first = receive(&socket);
+    assert!(first.starts_with("<15>1 "), "{first}");
+    assert!(first.contains(" app "), "{first}");
+    assert!(first.ends_with("- - first"));
+    let second = receive(&socket);
+    assert!(second.starts_with("<11>1 "), "{second}");
+    assert!(second.ends_with("- - second"));
+}
+
+#[test]
+fn test_sends_to_streams() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+
+    // Auto falls back to a stream, since the socket is not a datagram socket
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .app_name("app")
+        .connect()
+        .unwrap();
+    sink.log(&record(Level::Info, "one"));
+    sink.log(&record(Level::Info, "two"));
+    drop(sink);
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut received = String::new();
+    stream.read_to_string(&mut received).unwrap();
+    let messages: Vec<&str> = received.split_terminator('\0').collect();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].ends_with("- - one"));
+    assert!(messages[1].ends_with("- - two"));
+}
+
+#[test]
+fn test_reconnects_and_counts_dropped_messages() {
+    let (_dir, path, socket) = listener();
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .transport(SyslogTransport::Datagram)
+        .connect()
+        .unwrap();
+
+    // The daemon goes away, then comes back at the same path
+    drop(socket);
+    std::fs::remove_file(&path).unwrap();
+    sink.log(&record(Level::Info, "lost"));
+    assert_eq!(sink.dropped(), 1);
+
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    sink.log(&record(Level::Info, "found"));
+
+    assert_eq!(sink.dropped(), 1);
+    assert!(receive(&socket).ends_with("- - found"));
+}
+
+#[test]
+fn test_connect_fails_without_a_socket() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert!(SyslogSink::builder()
+        .path(dir.path().join("missing.sock"))
+        .connect()
+        .is_err());
+}
+
+#[test]
+fn test_header_fields_are_printable_ascii_and_limited() {
+    let (_dir, path, _socket) = listener();
+    let builder = SyslogSink::builder()
+        .path(&path)
+        .app_name(format!("snö\tman{}", "x".repeat(60)))
+        .hostname("wéb 1");
+    let record = record(Level::Info, "hello");
+
+    let message = builder
+        .clone()
+        .connect()
+        .unwrap()
+        .format_record(&record)
+        .unwrap();
+    let app_name = format!("snman{}", "x".repeat(43));
+    assert_eq!(
+        message,
+        format!("<14>1 2022-03-12T18:14:09.500000Z wb1 {app_name} 1234 - - hello")
+    );
+
+    let message = builder
+        .format(SyslogFormat::Rfc3164)
+        .connect()
+        .unwrap()
+        .format_record(&record)
+        .unwrap();
+    assert!(
+        message.ends_with(&format!(" wb1 {}[1234]: hello", &app_name[..32])),
+        "{message}"
+    );
+
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .app_name("ünïcödé")
+        .connect()
+        .unwrap();
+    assert!(sink
+        .format_record(&record)
+        .unwrap()
+        .ends_with(" - ncd 1234 - - hello"));
+}
+
+#[test]
+fn test_stream_sends_time_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.sock");
+    // Never read from, so the stream fills up
+    let _listener = UnixListener::bind(&path).unwrap();
+    let sink = SyslogSink::builder()
+        .path(&path)
+        .transport(SyslogTransport::Stream)
+        .write_timeout(Duration::from_millis(50))
+        .connect()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    sink.log(&record(Level::Info, &"x".repeat(8 * 1024 * 1024)));
+
+    assert_eq!(sink.dropped(), 1);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -3,7 +3,7 @@ use std::str::FromStr;
 
 #[cfg(feature = "log_files")]
 mod file_tests;
-#[cfg(feature = "log_files")]
+#[cfg(feature = "time")]
 mod time_tests;
 
 #[test]
@@ -81,3 +81,149 @@ fn test_level_from_string() {
     assert!(Level::from_str("").is_err());
     assert!(Level::from_str("foobar").is_err());
 }
+
+#[test]
+fn test_records_use_the_logger_clock() {
+    use clock::ManualClock;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let start = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let clock = ManualClock::new(start);
+    let logger = Logger::new();
+    logger.set_clock(clock.clone());
+    let capture = logger.capture();
+
+    logger.info("first");
+    clock.advance(Duration::from_secs(90));
+    logger.info("second");
+
+    let times: Vec<SystemTime> = capture.records().iter().map(|r| r.time).collect();
+    assert_eq!(times, vec![start, start + Duration::from_secs(90)]);
+    assert_eq!(logger.now(), start + Duration::from_secs(90));
+}
+
+#[test]
+fn test_thread_and_process_id_prefix() {
+    let logger = Logger::new();
+    #[cfg(feature = "time")]
+    logger.set_should_show_time(false);
+    logger.set_should_show_process_id(true);
+    logger.set_should_show_thread(true);
+    assert!(logger.should_show_process_id());
+    assert!(logger.should_show_thread());
+
+    let handle = std::thread::Builder::new()
+        .name("worker-7".to_string())
+        .spawn(|| Record::new(Level::Info, "from a worker", SystemTime::now()))
+        .unwrap();
+    let record = handle.join().unwrap();
+
+    assert_eq!(record.thread, "worker-7");
+    assert_eq!(record.process_id, std::process::id());
+    assert_eq!(
+        logger.prefix(&record),
+        format!("[pid {}][worker-7]", std::process::id())
+    );
+
+    // Threads without a name use their ID
+    let record = std::thread::spawn(|| Record::new(Level::Info, "", SystemTime::now()))
+        .join()
+        .unwrap();
+    assert!(record.thread.starts_with("ThreadId("), "{}", record.thread);
+
+    logger.set_should_show_process_id(false);
+    logger.set_should_show_thread(false);
+    assert_eq!(logger.prefix(&record), "");
+}
+
+/// A sink that only remembers whether it was flushed.
+#[derive(Debug, Default)]
+struct FlushCounter {
+    flushes: Arc<std::sync::atomic::AtomicUsize>
+}
+
+impl Sink for FlushCounter {
+    fn log(&self, _record: &Record) {}
+
+    fn flush(&self) -> std::io::Result<()> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// A sink whose flush blocks until it is told to finish.
+#[derive(Debug)]
+struct SlowFlush {
+    finish: Mutex<std::sync::mpsc::Receiver<()>>
+}
+
+impl Sink for SlowFlush {
+    fn log(&self, _record: &Record) {}
+
+    fn flush(&self) -> std::io::Result<()> {
+        let _ = lock(&self.finish).recv();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_logging_is_not_blocked_by_a_slow_sink_flush() {
+    let (finish, receiver) = std::sync::mpsc::channel();
+    let logger = Logger::new();
+    logger.add_sink(SlowFlush {
+        finish: Mutex::new(receiver)
+    });
+    let capture = logger.capture();
+
+    std::thread::scope(|scope| {
+        let flushing = scope.spawn(|| logger.flush());
+        // Give the flush time to start
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        logger.info("while flushing");
+        assert!(capture.contains(Level::Info, "while flushing"));
+
+        finish.send(()).unwrap();
+        flushing.join().unwrap().unwrap();
+    });
+    // The logger flushes again when it is dropped
+    drop(finish);
+}
+
+#[test]
+fn test_syslog_severity() {
+    assert_eq!(Level::Debug.syslog_severity(), Some(7));
+    assert_eq!(Level::Info.syslog_severity(), Some(6));
+    assert_eq!(Level::Warn.syslog_severity(), Some(4));
+    assert_eq!(Level::Error.syslog_severity(), Some(3));
+    assert_eq!(Level::None.syslog_severity(), None);
+}
+
+#[test]
+fn test_records_have_the_call_location() {
+    let logger = Logger::new();
+    let capture = logger.capture();
+
+    let line = line!() + 1;
+    logger.info("here");
+    linfo!(logger, "and here");
+
+    let records = capture.records();
+    let location = records[0].location.expect("the location should be set");
+    assert_eq!(location.file(), file!());
+    assert_eq!(location.line(), line);
+    assert_eq!(records[1].location.unwrap().line(), line + 1);
+}
+
+#[test]
+fn test_drop_flushes() {
+    let sink = FlushCounter::default();
+    let flushes = sink.flushes.clone();
+    let logger = Logger::new();
+    logger.add_sink(sink);
+    logger.info("test_drop_flushes message");
+    assert_eq!(flushes.load(Ordering::SeqCst), 0);
+
+    drop(logger);
+    assert_eq!(flushes.load(Ordering::SeqCst), 1);
+}
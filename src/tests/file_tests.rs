@@ -1,23 +1,30 @@
 //! These are all the top-level tests that require the 'log_files' feature.
 
 use super::*;
+use crate::clock::ManualClock;
+use crate::file_output::FileOutput;
+use crate::flush_policy::FlushPolicy;
+use crate::write_error_policy::WriteErrorPolicy;
+use std::time::Duration;
 
 #[test]
 fn test_set_log_path() {
     let logger = Logger::new();
     // Setting a path that doesn't exist should not work
-    assert_eq!(
-        logger.set_log_path("this/path/is/not/real/file.log"),
-        Err(SetLogPathError::CouldNotCreateLogFile)
-    );
+    match logger.set_log_path("this/path/is/not/real/file.log") {
+        Err(SetLogPathError::CouldNotCreateLogFile(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::NotFound)
+        }
+        other => panic!("expected the file to not be created, got {:?}", other)
+    }
     assert!(logger.get_log_path().is_none());
     assert!(!logger.has_log_writer());
 
     // Setting a directory that exists should not work
-    assert_eq!(
+    assert!(matches!(
         logger.set_log_path("test_log_directory/"),
         Err(SetLogPathError::PathIsNotAFile)
-    );
+    ));
     assert!(logger.get_log_path().is_none());
 
     // Ensure the other.log is not actually there still
@@ -49,3 +56,932 @@ fn test_set_log_path() {
 
     logger.flush().unwrap();
 }
+
+#[test]
+fn test_rename_to_log_archive_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("archive");
+    std::fs::create_dir(&archive).unwrap();
+    let path = dir.path().join("server.log");
+    std::fs::write(&path, "old session\n").unwrap();
+
+    let logger = Logger::new();
+    logger.set_existing_log_handler(ExistingLogHandler::Rename);
+    logger.set_log_archive_directory(Some(&archive));
+    assert_eq!(logger.get_log_archive_directory(), Some(archive.clone()));
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.info("new session");
+    logger.flush().unwrap();
+
+    let archived: Vec<PathBuf> = std::fs::read_dir(&archive)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(
+        std::fs::read_to_string(&archived[0]).unwrap(),
+        "old session\n"
+    );
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("new session\n"));
+}
+
+#[test]
+fn test_fail_if_exists_log_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+
+    let logger = Logger::new();
+    logger.set_existing_log_handler(ExistingLogHandler::FailIfExists);
+    // The file does not exist yet, so it is created
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    assert!(path.exists());
+    logger.info("first session");
+    logger.flush().unwrap();
+
+    assert!(matches!(
+        logger.set_log_path(path.to_str().unwrap()),
+        Err(SetLogPathError::AlreadyExists)
+    ));
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("first session\n"));
+}
+
+#[test]
+fn test_new_numbered_log_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+
+    let logger = Logger::new();
+    logger.set_existing_log_handler(ExistingLogHandler::NewNumbered);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    assert_eq!(logger.get_log_path(), Some(path.clone()));
+    assert_eq!(
+        logger.get_active_log_path(),
+        Some(dir.path().join("server.1.log"))
+    );
+    // The base path itself is never created
+    assert!(!path.exists());
+    logger.info("first session");
+
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.info("second session");
+    logger.flush().unwrap();
+
+    assert_eq!(
+        logger.get_active_log_path(),
+        Some(dir.path().join("server.2.log"))
+    );
+    assert!(std::fs::read_to_string(dir.path().join("server.1.log"))
+        .unwrap()
+        .ends_with("first session\n"));
+    assert!(std::fs::read_to_string(dir.path().join("server.2.log"))
+        .unwrap()
+        .ends_with("second session\n"));
+}
+
+#[test]
+fn test_create_log_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs/app/server.log");
+
+    let logger = Logger::new();
+    assert!(!logger.get_create_log_directories());
+    assert!(logger.set_log_path(path.to_str().unwrap()).is_err());
+    assert!(!dir.path().join("logs").exists());
+
+    logger.set_create_log_directories(true);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    assert!(path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_log_directory_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs/server.log");
+
+    let logger = Logger::new();
+    logger.set_create_log_directories(true);
+    logger.set_log_directory_mode(Some(0o700));
+    assert_eq!(logger.get_log_directory_mode(), Some(0o700));
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    let metadata = std::fs::metadata(dir.path().join("logs")).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o700);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_log_directories_reports_the_io_error() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    std::fs::create_dir(&locked).unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o500)).unwrap();
+    // Permissions are not enforced for root, so there is nothing to test
+    if std::fs::create_dir(locked.join("probe")).is_ok() {
+        return;
+    }
+
+    let logger = Logger::new();
+    logger.set_create_log_directories(true);
+    let path = locked.join("logs/server.log");
+    match logger.set_log_path(path.to_str().unwrap()) {
+        Err(SetLogPathError::CouldNotCreateLogDirectory(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied)
+        }
+        other => panic!("expected a permission error, got {:?}", other)
+    }
+}
+
+/// Log a message to a new `server.log` in a temporary directory, then move the
+/// file to `server.log.1` like logrotate does.
+fn logger_with_rotated_file(dir: &std::path::Path) -> (Logger, PathBuf) {
+    let path = dir.join("server.log");
+    let logger = Logger::new();
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.info("before rotation");
+    logger.flush().unwrap();
+    std::fs::rename(&path, dir.join("server.log.1")).unwrap();
+
+    (logger, path)
+}
+
+#[test]
+fn test_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let (logger, path) = logger_with_rotated_file(dir.path());
+
+    logger.info("still in the moved file");
+    logger.reopen().unwrap();
+    logger.info("after rotation");
+    logger.flush().unwrap();
+
+    let rotated = std::fs::read_to_string(dir.path().join("server.log.1")).unwrap();
+    assert!(rotated.contains("before rotation"));
+    assert!(rotated.contains("still in the moved file"));
+    let current = std::fs::read_to_string(&path).unwrap();
+    assert!(current.ends_with("after rotation\n"));
+    assert!(!current.contains("before rotation"));
+}
+
+#[test]
+fn test_reopen_without_log_file() {
+    let logger = Logger::new();
+    assert!(logger.reopen().is_ok());
+}
+
+#[test]
+fn test_request_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let (logger, path) = logger_with_rotated_file(dir.path());
+
+    logger.request_reopen();
+    logger.info("after rotation");
+    logger.flush().unwrap();
+
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("after rotation\n"));
+}
+
+#[test]
+fn test_reopen_on_file_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let (logger, path) = logger_with_rotated_file(dir.path());
+    logger.set_reopen_on_file_change(true);
+    assert!(logger.get_reopen_on_file_change());
+
+    logger.info("after rotation");
+    logger.flush().unwrap();
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("after rotation\n"));
+
+    // A deleted file is created again
+    std::fs::remove_file(&path).unwrap();
+    logger.info("after deletion");
+    logger.flush().unwrap();
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("after deletion\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reopen_on_replaced_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let (logger, path) = logger_with_rotated_file(dir.path());
+    logger.set_reopen_on_file_change(true);
+    // Something else already created a new file at the path
+    std::fs::write(&path, "").unwrap();
+
+    logger.info("after rotation");
+    logger.flush().unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+}
+
+#[cfg(all(feature = "signal", unix))]
+#[test]
+fn test_reopen_on_sighup() {
+    let dir = tempfile::tempdir().unwrap();
+    let (logger, path) = logger_with_rotated_file(dir.path());
+
+    logger.reopen_on_sighup().unwrap();
+    signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+    logger.info("after rotation");
+    logger.flush().unwrap();
+
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("after rotation\n"));
+}
+
+#[test]
+fn test_flush_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+
+    let logger = Logger::new();
+    assert_eq!(logger.get_flush_policy(), FlushPolicy::Never);
+    logger.set_flush_policy(FlushPolicy::AtLevel(Level::Warn));
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("buffered");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    logger.warn("flushed");
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("buffered"));
+    assert!(contents.ends_with("flushed\n"));
+}
+
+#[test]
+fn test_flush_policy_interval_uses_the_clock() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let clock = ManualClock::new(std::time::UNIX_EPOCH);
+
+    let logger = Logger::new();
+    logger.set_clock(clock.clone());
+    logger.set_flush_policy(FlushPolicy::Interval(Duration::from_secs(10)));
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("first");
+    clock.advance(Duration::from_secs(9));
+    logger.info("second");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    clock.advance(Duration::from_secs(1));
+    logger.info("third");
+    assert!(std::fs::read_to_string(&path).unwrap().ends_with("third\n"));
+}
+
+#[test]
+fn test_sync_on_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+
+    let logger = Logger::new();
+    logger.set_sync_on_error(true);
+    assert!(logger.get_sync_on_error());
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.warn("buffered");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    logger.error("synced");
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("synced\n"));
+}
+
+#[test]
+fn test_log_files_split_by_level() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = dir.path().join("server.log");
+    let errors = dir.path().join("errors.log");
+    let logger = Logger::new();
+    logger.set_log_path(server.to_str().unwrap()).unwrap();
+    logger
+        .add_log_file(FileOutput::new(&errors).min_level(Level::Error))
+        .unwrap();
+    assert!(errors.exists(), "extra log files are opened straight away");
+
+    logger.debug("starting");
+    logger.warn("slow request");
+    logger.error("request failed");
+    logger.flush().unwrap();
+
+    let server_log = std::fs::read_to_string(&server).unwrap();
+    assert_eq!(server_log.lines().count(), 3);
+    let errors_log = std::fs::read_to_string(&errors).unwrap();
+    assert_eq!(errors_log.lines().count(), 1);
+    assert!(errors_log.contains("request failed"));
+    assert_eq!(
+        logger.get_log_files(),
+        [FileOutput::new(&errors).min_level(Level::Error)]
+    );
+}
+
+#[test]
+fn test_log_files_have_their_own_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    let debug = dir.path().join("debug.log");
+    let existing = dir.path().join("existing.log");
+    std::fs::write(&debug, "old session\n").unwrap();
+    std::fs::write(&existing, "old session\n").unwrap();
+    let logger = Logger::new();
+
+    logger
+        .add_log_file(
+            FileOutput::new(&debug)
+                .max_level(Level::Debug)
+                .existing_log_handler(ExistingLogHandler::Overwrite)
+                .color(true)
+        )
+        .unwrap();
+    assert!(matches!(
+        logger.add_log_file(
+            FileOutput::new(&existing).existing_log_handler(ExistingLogHandler::FailIfExists)
+        ),
+        Err(SetLogPathError::AlreadyExists)
+    ));
+    assert!(matches!(
+        logger.add_log_file(FileOutput::new(dir.path())),
+        Err(SetLogPathError::PathIsNotAFile)
+    ));
+
+    logger.debug("details");
+    logger.info("not for the debug log");
+    logger.flush().unwrap();
+
+    let debug_log = std::fs::read_to_string(&debug).unwrap();
+    assert!(!debug_log.contains("old session"));
+    assert!(debug_log.contains("details"));
+    assert!(
+        debug_log.contains('\u{1b}'),
+        "color codes should be written"
+    );
+    assert!(!debug_log.contains("not for the debug log"));
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old session\n");
+    assert_eq!(logger.get_log_files().len(), 1);
+}
+
+#[test]
+fn test_log_files_use_the_flush_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    let logger = Logger::new();
+    logger.set_flush_policy(FlushPolicy::AtLevel(Level::Error));
+    logger
+        .add_log_file(FileOutput::new(&errors).min_level(Level::Warn))
+        .unwrap();
+
+    logger.warn("buffered");
+    assert_eq!(std::fs::read_to_string(&errors).unwrap(), "");
+    logger.error("flushed");
+    assert_eq!(std::fs::read_to_string(&errors).unwrap().lines().count(), 2);
+}
+
+#[test]
+fn test_clear_log_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("all.log");
+    let logger = Logger::new();
+    logger.add_log_file(FileOutput::new(&path)).unwrap();
+
+    logger.info("kept");
+    logger.clear_log_files();
+    logger.info("not kept");
+
+    assert!(logger.get_log_files().is_empty());
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(log.contains("kept"));
+    assert!(!log.contains("not kept"));
+}
+
+#[test]
+fn test_log_files_are_reopened() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    let rotated = dir.path().join("errors.log.1");
+    let logger = Logger::new();
+    logger
+        .add_log_file(FileOutput::new(&errors).min_level(Level::Error))
+        .unwrap();
+    logger.error("before rotation");
+    logger.flush().unwrap();
+    std::fs::rename(&errors, &rotated).unwrap();
+
+    logger.request_reopen();
+    // Files are reopened even when the message is not for them
+    logger.info("not for the errors log");
+    logger.error("after rotation");
+    logger.flush().unwrap();
+
+    assert!(std::fs::read_to_string(&rotated)
+        .unwrap()
+        .ends_with("before rotation\n"));
+    let current = std::fs::read_to_string(&errors).unwrap();
+    assert_eq!(current.lines().count(), 1);
+    assert!(current.ends_with("after rotation\n"));
+}
+
+#[test]
+fn test_log_files_reopen_on_file_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    let logger = Logger::new();
+    logger.set_reopen_on_file_change(true);
+    logger.add_log_file(FileOutput::new(&errors)).unwrap();
+    logger.info("before rotation");
+    logger.flush().unwrap();
+
+    std::fs::rename(&errors, dir.path().join("errors.log.1")).unwrap();
+    logger.info("after rotation");
+    logger.reopen().unwrap();
+    logger.info("after reopen");
+    logger.flush().unwrap();
+
+    let current = std::fs::read_to_string(&errors).unwrap();
+    assert_eq!(current.lines().count(), 2);
+    assert!(current.contains("after rotation"));
+}
+
+#[test]
+fn test_log_files_use_atomic_appends() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("errors.log");
+    std::fs::write(&errors, "from the last session\n").unwrap();
+    let logger = Logger::new();
+    logger.set_color(false);
+    #[cfg(feature = "time")]
+    logger.set_should_show_time(false);
+    logger.set_atomic_appends(true);
+    logger
+        .add_log_file(FileOutput::new(&errors).existing_log_handler(ExistingLogHandler::Overwrite))
+        .unwrap();
+
+    logger.info("first");
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&errors)
+        .unwrap()
+        .write_all(b"from another process\n")
+        .unwrap();
+    logger.info("second");
+
+    assert_eq!(
+        std::fs::read_to_string(&errors).unwrap(),
+        "[INFO] first\nfrom another process\n[INFO] second\n"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_log_files_use_the_write_error_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let errors = dir.path().join("missing/errors.log");
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::Buffer { max_bytes: 4096 });
+    logger.set_flush_policy(FlushPolicy::EveryMessage);
+    logger
+        .add_log_file(FileOutput::new(dir.path().join("errors.log")))
+        .unwrap();
+    // Make every write fail, like a full disk
+    {
+        let mut log_files = logger.log_files.lock().unwrap();
+        let full = File::options().write(true).open("/dev/full").unwrap();
+        log_files[0].writer = Some(BufWriter::new(full));
+        log_files[0].path = errors.clone();
+    }
+
+    logger.info("first");
+    logger.info("second");
+    assert_eq!(logger.get_log_files().len(), 1);
+    std::fs::create_dir(dir.path().join("missing")).unwrap();
+    logger.info("third");
+
+    let contents = std::fs::read_to_string(&errors).unwrap();
+    let first = contents.find("first").unwrap();
+    let second = contents.find("second").unwrap();
+    let third = contents.find("third").unwrap();
+    assert!(first < second && second < third, "{}", contents);
+    assert!(logger.get_write_error_count() >= 1);
+}
+
+#[test]
+fn test_log_files_create_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs/errors.log");
+    let logger = Logger::new();
+
+    assert!(matches!(
+        logger.add_log_file(FileOutput::new(&path)),
+        Err(SetLogPathError::CouldNotCreateLogFile(_))
+    ));
+    logger.set_create_log_directories(true);
+    logger.add_log_file(FileOutput::new(&path)).unwrap();
+
+    assert!(path.exists());
+}
+
+#[test]
+fn test_max_log_bytes_pauses_and_resumes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let clock = ManualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let logger = Logger::new();
+    logger.set_clock(clock.clone());
+    #[cfg(feature = "time")]
+    logger.set_should_show_time(false);
+    logger.set_existing_log_handler(ExistingLogHandler::Append);
+    logger.set_flush_policy(FlushPolicy::EveryMessage);
+    logger.set_max_log_bytes(Some(100));
+    assert_eq!(logger.get_max_log_bytes(), Some(100));
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    let capture = logger.capture();
+
+    for i in 0..20 {
+        logger.info(&format!("message {i}"));
+    }
+
+    assert!(logger.is_log_file_paused());
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(log.len() <= 100, "the log file is {} bytes", log.len());
+    let warnings = capture
+        .records()
+        .iter()
+        .filter(|record| record.level == Level::Warn)
+        .count();
+    assert_eq!(warnings, 1, "only one warning is logged when pausing");
+    assert!(capture.contains(Level::Warn, "at the limit of 100"));
+
+    // Rotating the file makes room again, which is noticed a second later
+    std::fs::write(&path, "").unwrap();
+    logger.info("still paused");
+    clock.advance(Duration::from_secs(1));
+    logger.info("back again");
+
+    assert!(!logger.is_log_file_paused());
+    assert!(capture.contains(Level::Info, "resumed"));
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(!log.contains("still paused"));
+    assert!(log.contains("back again"));
+}
+
+#[test]
+fn test_max_log_bytes_deletes_nothing_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let rotated = dir.path().join("server.log.1");
+    std::fs::write(&path, [b'x'; 500]).unwrap();
+    std::fs::write(&rotated, [b'x'; 500]).unwrap();
+    let logger = Logger::new();
+    logger.set_existing_log_handler(ExistingLogHandler::Rename);
+    logger.set_max_log_bytes(Some(300));
+    assert!(!logger.get_prune_log_archives());
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("not written");
+    logger.flush().unwrap();
+
+    assert!(logger.is_log_file_paused());
+    assert!(rotated.exists());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+}
+
+#[test]
+fn test_prune_log_archives_deletes_only_renamed_logs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let rotated = dir.path().join("server.log.1");
+    let unrelated = dir.path().join("server.debug.log");
+    std::fs::write(&path, [b'x'; 500]).unwrap();
+    std::fs::write(&rotated, [b'x'; 100]).unwrap();
+    std::fs::write(&unrelated, [b'x'; 500]).unwrap();
+    let logger = Logger::new();
+    logger.set_existing_log_handler(ExistingLogHandler::Rename);
+    logger.set_max_log_bytes(Some(300));
+    logger.set_prune_log_archives(true);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("making room");
+    logger.flush().unwrap();
+
+    assert!(!logger.is_log_file_paused());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    assert!(rotated.exists());
+    assert!(unrelated.exists());
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("making room"));
+}
+
+#[cfg(all(feature = "disk_space", unix))]
+#[test]
+fn test_min_free_space_pauses_and_resumes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let logger = Logger::new();
+    logger.set_flush_policy(FlushPolicy::EveryMessage);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    let capture = logger.capture();
+
+    // No disk has this much space
+    logger.set_min_free_space(Some(u64::MAX));
+    assert_eq!(logger.get_min_free_space(), Some(u64::MAX));
+    logger.info("not written");
+    logger.info("not written either");
+
+    assert!(logger.is_log_file_paused());
+    assert!(capture.contains(Level::Warn, "free on the disk"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    logger.set_min_free_space(Some(1));
+    logger.info("written");
+
+    assert!(!logger.is_log_file_paused());
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(!log.contains("not written"));
+    assert!(log.contains("written"));
+}
+
+/// Make every write to the log file fail, like a full disk, by swapping the
+/// writer for `/dev/full`. The logger reopens `path` to recover.
+#[cfg(target_os = "linux")]
+fn break_log_file(logger: &Logger, path: &std::path::Path) {
+    let full = File::options().write(true).open("/dev/full").unwrap();
+    *logger.log_writer.lock().unwrap() = Some(BufWriter::new(full));
+    *logger.active_log_path.lock().unwrap() = Some(path.to_path_buf());
+    *logger.log_path.lock().unwrap() = Some(path.to_path_buf());
+    logger.set_flush_policy(FlushPolicy::EveryMessage);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_disables_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let logger = Logger::new();
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let callback_errors = errors.clone();
+    logger.set_write_error_callback(move |e| callback_errors.lock().unwrap().push(e.kind()));
+    break_log_file(&logger, &dir.path().join("server.log"));
+
+    logger.info("lost");
+    logger.info("not written");
+
+    assert_eq!(logger.get_write_error_policy(), WriteErrorPolicy::Disable);
+    assert!(logger.get_log_path().is_none());
+    assert!(!logger.is_log_file_failing());
+    assert_eq!(logger.get_write_error_count(), 1);
+    assert_eq!(*errors.lock().unwrap(), [std::io::ErrorKind::StorageFull]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_fallback_to_stderr_recovers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing/server.log");
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::FallbackToStderr);
+    let calls = Arc::new(Mutex::new(0));
+    let callback_calls = calls.clone();
+    logger.set_write_error_callback(move |_| *callback_calls.lock().unwrap() += 1);
+    break_log_file(&logger, &path);
+
+    logger.info("to stderr");
+    assert!(logger.is_log_file_failing());
+    logger.info("also to stderr");
+
+    std::fs::create_dir(dir.path().join("missing")).unwrap();
+    logger.info("back in the file");
+    assert!(!logger.is_log_file_failing());
+    logger.info("still in the file");
+    logger.flush().unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("to stderr"));
+    assert!(contents.contains("back in the file"));
+    assert!(contents.ends_with("still in the file\n"));
+    assert_eq!(*calls.lock().unwrap(), 1);
+    // The first write, then the error about it and the next message
+    assert_eq!(logger.get_write_error_count(), 3);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_buffer_keeps_messages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing/server.log");
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::Buffer { max_bytes: 4096 });
+    break_log_file(&logger, &path);
+
+    logger.info("first");
+    logger.info("second");
+    std::fs::create_dir(dir.path().join("missing")).unwrap();
+    logger.info("third");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let first = contents.find("first").unwrap();
+    let second = contents.find("second").unwrap();
+    let third = contents.find("third").unwrap();
+    assert!(first < second && second < third, "{}", contents);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_buffer_is_capped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing/server.log");
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::Buffer { max_bytes: 0 });
+    break_log_file(&logger, &path);
+
+    logger.info("dropped");
+    std::fs::create_dir(dir.path().join("missing")).unwrap();
+    logger.info("kept");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("dropped"));
+    assert!(contents.ends_with("kept\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_retry() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("server.log");
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::Retry {
+        attempts: 2,
+        backoff: Duration::from_millis(1)
+    });
+    break_log_file(&logger, &path);
+
+    logger.info("written on retry");
+
+    assert_eq!(logger.get_log_path(), Some(path.clone()));
+    assert_eq!(logger.get_write_error_count(), 1);
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("written on retry\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_error_retry_gives_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let logger = Logger::new();
+    logger.set_write_error_policy(WriteErrorPolicy::Retry {
+        attempts: 2,
+        backoff: Duration::from_millis(1)
+    });
+    break_log_file(&logger, &dir.path().join("missing/server.log"));
+
+    logger.info("lost");
+
+    assert!(logger.get_log_path().is_none());
+    assert_eq!(logger.get_write_error_count(), 3);
+}
+
+/// Set by [`test_multi_process_appends`] for the processes it starts.
+const SHARED_LOG_VAR: &str = "POKEY_LOGGER_TEST_SHARED_LOG";
+const LINES_PER_PROCESS: usize = 300;
+
+/// Not a test by itself. [`test_multi_process_appends`] runs this in other
+/// processes to write to the same log file at the same time.
+#[test]
+#[ignore]
+fn shared_log_writer() {
+    let path = match std::env::var(SHARED_LOG_VAR) {
+        Ok(path) => path,
+        Err(_) => return
+    };
+    let logger = Logger::new();
+    logger.set_color(false);
+    logger.set_existing_log_handler(ExistingLogHandler::Append);
+    logger.set_atomic_appends(true);
+    #[cfg(feature = "file_lock")]
+    logger.set_file_locking(true);
+    logger.set_log_path(&path).unwrap();
+
+    let padding = "x".repeat(500);
+    for line in 0..LINES_PER_PROCESS {
+        logger.info(&format!(
+            "pid {} line {line} {padding} end",
+            std::process::id()
+        ));
+    }
+}
+
+#[test]
+fn test_multi_process_appends() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.log");
+    let processes = 4;
+
+    let children: Vec<std::process::Child> = (0..processes)
+        .map(|_| {
+            std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "tests::file_tests::shared_log_writer",
+                    "--ignored",
+                    "--test-threads=1"
+                ])
+                .env(SHARED_LOG_VAR, &path)
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), processes * LINES_PER_PROCESS);
+    let mut next_line = std::collections::HashMap::new();
+    for line in lines {
+        assert!(
+            line.contains("[INFO] pid ") && line.ends_with(" end"),
+            "mixed line: {:?}",
+            line
+        );
+        assert_eq!(line.matches("pid ").count(), 1, "mixed line: {:?}", line);
+
+        // The lines of each process are whole and in order
+        let words: Vec<&str> = line.split(' ').collect();
+        let pid = words[words.len() - 5];
+        let number: usize = words[words.len() - 3].parse().unwrap();
+        let expected = next_line.entry(pid.to_string()).or_insert(0);
+        assert_eq!(number, *expected);
+        *expected += 1;
+    }
+    assert_eq!(next_line.len(), processes);
+}
+
+#[test]
+fn test_atomic_appends_with_the_default_handler() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.log");
+    std::fs::write(&path, "from the last session\n").unwrap();
+    let logger = Logger::new();
+    logger.set_color(false);
+    #[cfg(feature = "time")]
+    logger.set_should_show_time(false);
+    logger.set_atomic_appends(true);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("first");
+    // Another process appends to the file
+    let mut other = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    other.write_all(b"from another process\n").unwrap();
+    logger.info("second");
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[INFO] first\nfrom another process\n[INFO] second\n"
+    );
+}
+
+#[test]
+fn test_turning_on_atomic_appends_reopens_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.log");
+    let logger = Logger::new();
+    logger.set_color(false);
+    #[cfg(feature = "time")]
+    logger.set_should_show_time(false);
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+    logger.info("first");
+    logger.flush().unwrap();
+
+    logger.set_atomic_appends(true);
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"from another process\n")
+        .unwrap();
+    logger.info("second");
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[INFO] first\nfrom another process\n[INFO] second\n"
+    );
+}
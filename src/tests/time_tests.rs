@@ -7,8 +7,80 @@ fn test_get_and_set_timestamp_format() {
     let logger = Logger::new();
     let format = "%Y-%m-%d %H:%M:%S".to_string();
 
-    logger.set_timestamp_format(Some(format.clone()));
+    assert!(logger.set_timestamp_format(Some(format.clone())).is_ok());
     assert_eq!(logger.get_timestamp_format(), Some(format));
 
     logger.info("This is a test of the cool time format");
 }
+
+#[test]
+fn test_set_invalid_timestamp_format_keeps_previous() {
+    let logger = Logger::new();
+    let format = "%Y-%m-%d".to_string();
+    assert!(logger.set_timestamp_format(Some(format.clone())).is_ok());
+
+    assert_eq!(
+        logger.set_timestamp_format(Some("%Y-%Q".to_string())),
+        Err(InvalidTimestampFormat("%Y-%Q".to_string()))
+    );
+    assert_eq!(logger.get_timestamp_format(), Some(format));
+
+    assert!(logger.set_timestamp_format(None).is_ok());
+    assert_eq!(logger.get_timestamp_format(), None);
+}
+
+#[cfg(feature = "log_files")]
+#[test]
+fn test_timestamp_uses_the_logger_clock() {
+    use chrono::{DateTime, Local};
+    use clock::ManualClock;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clock.log");
+    let time = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+    let logger = Logger::new();
+    logger.set_clock(ManualClock::new(time));
+    logger
+        .set_timestamp_format(Some("%Y-%m-%d %H:%M:%S".to_string()))
+        .unwrap();
+    logger.set_log_path(path.to_str().unwrap()).unwrap();
+
+    logger.info("fixed time");
+    logger.flush().unwrap();
+
+    let expected_time = DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S");
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        format!("[{expected_time}][INFO] fixed time\n")
+    );
+}
+
+#[test]
+fn test_timestamp_style_and_utc() {
+    use clock::ManualClock;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let start = UNIX_EPOCH + Duration::from_millis(1_647_108_849_123);
+    let clock = ManualClock::new(start);
+    let logger = Logger::new();
+    logger.set_clock(clock.clone());
+    logger.set_timestamp_utc(true);
+    assert!(logger.get_timestamp_utc());
+
+    logger.set_timestamp_style(TimestampStyle::Iso8601Millis);
+    assert_eq!(logger.get_timestamp_style(), TimestampStyle::Iso8601Millis);
+    assert_eq!(
+        logger.prefix(&Record::new(Level::Info, "", logger.now())),
+        "[2022-03-12T18:14:09.123Z]"
+    );
+
+    // Elapsed counts from when the clock was set
+    logger.set_timestamp_style(TimestampStyle::Elapsed);
+    assert_eq!(logger.get_start_time(), start);
+    clock.advance(Duration::from_millis(1_500));
+    assert_eq!(
+        logger.prefix(&Record::new(Level::Info, "", logger.now())),
+        "[1.500s]"
+    );
+}
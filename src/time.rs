@@ -3,7 +3,95 @@
 #[cfg(test)]
 mod tests;
 
+use chrono::format::{Item, StrftimeItems};
 use chrono::prelude::*;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The format used for the timestamp when no other format is given.
+pub const DEFAULT_FORMAT: &str = "%H:%M:%S";
+
+/// An error for a timestamp format string that can not be understood by
+/// chrono. Contains the offending format string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTimestampFormat(pub String);
+
+impl Display for InvalidTimestampFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid timestamp format: {:?}", self.0)
+    }
+}
+
+impl Error for InvalidTimestampFormat {}
+
+/// How the timestamp on log messages is written.
+///
+/// The examples below are for the same time, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub enum TimestampStyle {
+    /// Use the timestamp format of the logger, or `%H:%M:%S` if there is none.
+    /// For example `[18:14:09]`. This is the default.
+    #[default]
+    Format,
+    /// RFC 3339 to the second. For example `[2022-03-12T18:14:09Z]`.
+    Rfc3339,
+    /// ISO 8601 with milliseconds. For example
+    /// `[2022-03-12T18:14:09.123Z]`.
+    Iso8601Millis,
+    /// ISO 8601 with microseconds. For example
+    /// `[2022-03-12T18:14:09.123456Z]`.
+    Iso8601Micros,
+    /// Seconds since the Unix epoch. For example `[1647108849]`.
+    UnixSeconds,
+    /// Milliseconds since the Unix epoch. For example `[1647108849123]`.
+    UnixMillis,
+    /// Seconds since the logger started, with milliseconds. For example
+    /// `[12.345s]`.
+    Elapsed
+}
+
+impl FromStr for TimestampStyle {
+    type Err = String;
+
+    /// Parse the name of a style, ignoring case, dashes and underscores. For
+    /// example `"iso8601_millis"` or `"Rfc3339"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+
+        match name.as_str() {
+            "format" => Ok(TimestampStyle::Format),
+            "rfc3339" => Ok(TimestampStyle::Rfc3339),
+            "iso8601millis" => Ok(TimestampStyle::Iso8601Millis),
+            "iso8601micros" => Ok(TimestampStyle::Iso8601Micros),
+            "unixseconds" => Ok(TimestampStyle::UnixSeconds),
+            "unixmillis" => Ok(TimestampStyle::UnixMillis),
+            "elapsed" => Ok(TimestampStyle::Elapsed),
+            _ => Err(format!("Invalid timestamp style: {}", s))
+        }
+    }
+}
+
+/// Check that a format string is valid for use as a timestamp format.
+///
+/// The syntax is that of [`chrono::format::strftime`]. Unknown specifiers,
+/// such as `%Q`, and incomplete specifiers, such as a trailing `%`, are
+/// rejected.
+pub fn validate_format(format_string: &str) -> Result<(), InvalidTimestampFormat> {
+    if StrftimeItems::new(format_string).any(|item| matches!(item, Item::Error)) {
+        Err(InvalidTimestampFormat(format_string.to_string()))
+    } else {
+        Ok(())
+    }
+}
 
 /// Gets the string to write to the log already formatted to be printed.
 ///
@@ -11,15 +99,84 @@ use chrono::prelude::*;
 /// format can be provided using the same syntax as what chrono offers in its
 /// [`chrono::format::strftime`]
 ///
-/// The default format string is "%H:%M:%S"
+/// The default format string is "%H:%M:%S". If the given format string is
+/// not valid (see [`validate_format`]), the default format is used instead.
 pub fn current_time_box(format_string: Option<String>) -> String {
+    time_box(SystemTime::now(), format_string)
+}
+
+/// The same as [`current_time_box`], but for the given time instead of now.
+/// The time is shown in the local timezone.
+pub fn time_box(time: SystemTime, format_string: Option<String>) -> String {
+    format_box(DateTime::<Local>::from(time), format_string)
+}
+
+/// Gets the timestamp for a log message at the given time, in the given
+/// style.
+///
+/// The `format_string` is only used by [`TimestampStyle::Format`], and
+/// `start` is only used by [`TimestampStyle::Elapsed`]. When `utc` is false,
+/// the local timezone is used.
+pub fn styled_time_box(
+    time: SystemTime,
+    style: TimestampStyle,
+    format_string: Option<String>,
+    utc: bool,
+    start: SystemTime
+) -> String {
+    match style {
+        TimestampStyle::Format if utc => format_box(DateTime::<Utc>::from(time), format_string),
+        TimestampStyle::Format => format_box(DateTime::<Local>::from(time), format_string),
+        TimestampStyle::Rfc3339 => rfc3339_box(time, SecondsFormat::Secs, utc),
+        TimestampStyle::Iso8601Millis => rfc3339_box(time, SecondsFormat::Millis, utc),
+        TimestampStyle::Iso8601Micros => rfc3339_box(time, SecondsFormat::Micros, utc),
+        TimestampStyle::UnixSeconds => format!("[{}]", since_epoch(time).as_secs()),
+        TimestampStyle::UnixMillis => format!("[{}]", since_epoch(time).as_millis()),
+        TimestampStyle::Elapsed => {
+            // A clock that was moved back gives zero rather than failing.
+            let elapsed = time.duration_since(start).unwrap_or_default();
+            format!("[{}.{:03}s]", elapsed.as_secs(), elapsed.subsec_millis())
+        }
+    }
+}
+
+/// Format a time with a strftime format string, falling back to the default
+/// format if it is invalid.
+fn format_box<Tz: TimeZone>(time: DateTime<Tz>, format_string: Option<String>) -> String
+where
+    Tz::Offset: Display
+{
     let format_string = match format_string {
-        Some(fs) => fs,
-        None => "%H:%M:%S".to_string()
+        Some(fs) if validate_format(&fs).is_ok() => fs,
+        _ => DEFAULT_FORMAT.to_string()
     };
 
-    // Get the time
-    let time: DateTime<Local> = Local::now();
-    // Format the time
-    format!("[{}]", time.format(&format_string))
+    let mut result = String::new();
+    if write!(result, "[{}]", time.format(&format_string)).is_err() {
+        // Some specifiers can still fail for certain values, such as a
+        // timezone name that is not available.
+        result = format!("[{}]", time.format(DEFAULT_FORMAT));
+    }
+
+    result
+}
+
+/// An RFC 3339 timestamp with the given precision. UTC times end in `Z`.
+fn rfc3339_box(time: SystemTime, precision: SecondsFormat, utc: bool) -> String {
+    if utc {
+        format!(
+            "[{}]",
+            DateTime::<Utc>::from(time).to_rfc3339_opts(precision, true)
+        )
+    } else {
+        format!(
+            "[{}]",
+            DateTime::<Local>::from(time).to_rfc3339_opts(precision, false)
+        )
+    }
+}
+
+/// The time since the Unix epoch, or zero for times before it.
+fn since_epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
 }
@@ -1,5 +1,4 @@
 use super::*;
-use crate::debug;
 
 #[test]
 pub fn test_time_box_generation() {
@@ -18,3 +17,121 @@ pub fn test_time_box_generation_with_same_format_string() {
 
     assert_eq!(std_result, custom_result);
 }
+
+#[test]
+pub fn test_validate_format_accepts_valid_formats() {
+    assert_eq!(Ok(()), validate_format("%H:%M:%S"));
+    assert_eq!(Ok(()), validate_format("%Y-%m-%d %H:%M:%S%.3f"));
+    assert_eq!(Ok(()), validate_format("no specifiers at all"));
+    assert_eq!(Ok(()), validate_format("100%%"));
+}
+
+#[test]
+pub fn test_validate_format_rejects_invalid_formats() {
+    for format in ["%Q", "%H:%M:%", "%.9", "%:"] {
+        assert_eq!(
+            Err(InvalidTimestampFormat(format.to_string())),
+            validate_format(format),
+            "format {:?} should be invalid",
+            format
+        );
+    }
+}
+
+#[test]
+pub fn test_time_box_generation_with_invalid_format_uses_default() {
+    let result = current_time_box(Some("%Q".to_string()));
+
+    assert_eq!(10, result.len());
+    assert_eq!(':', result.chars().nth(3).unwrap());
+}
+
+#[test]
+pub fn test_time_box_for_given_time() {
+    let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let local: DateTime<Local> = time.into();
+
+    assert_eq!(
+        time_box(time, Some("%Y-%m-%d %H:%M:%S".to_string())),
+        format!("[{}]", local.format("%Y-%m-%d %H:%M:%S"))
+    );
+    assert_eq!(time_box(time, None), time_box(time, None));
+}
+
+/// 2022-03-12T18:14:09.123456Z
+fn example_time() -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(1_647_108_849_123_456)
+}
+
+#[test]
+pub fn test_styled_time_box_utc_styles() {
+    let time = example_time();
+    let cases = [
+        (TimestampStyle::Rfc3339, "[2022-03-12T18:14:09Z]"),
+        (TimestampStyle::Iso8601Millis, "[2022-03-12T18:14:09.123Z]"),
+        (
+            TimestampStyle::Iso8601Micros,
+            "[2022-03-12T18:14:09.123456Z]"
+        ),
+        (TimestampStyle::UnixSeconds, "[1647108849]"),
+        (TimestampStyle::UnixMillis, "[1647108849123]"),
+        (TimestampStyle::Format, "[18:14:09]")
+    ];
+
+    for (style, expected) in cases {
+        assert_eq!(
+            styled_time_box(time, style, None, true, UNIX_EPOCH),
+            expected,
+            "{:?}",
+            style
+        );
+    }
+}
+
+#[test]
+pub fn test_styled_time_box_local_matches_chrono() {
+    let time = example_time();
+    let local: DateTime<Local> = time.into();
+
+    assert_eq!(
+        styled_time_box(time, TimestampStyle::Rfc3339, None, false, UNIX_EPOCH),
+        format!("[{}]", local.to_rfc3339_opts(SecondsFormat::Secs, false))
+    );
+    assert_eq!(
+        styled_time_box(
+            time,
+            TimestampStyle::Format,
+            Some("%Y %H".to_string()),
+            false,
+            UNIX_EPOCH
+        ),
+        format!("[{}]", local.format("%Y %H"))
+    );
+}
+
+#[test]
+pub fn test_styled_time_box_elapsed() {
+    let start = example_time();
+    let later = start + Duration::from_millis(12_345);
+
+    assert_eq!(
+        styled_time_box(later, TimestampStyle::Elapsed, None, true, start),
+        "[12.345s]"
+    );
+    // Before the start is shown as zero
+    assert_eq!(
+        styled_time_box(start, TimestampStyle::Elapsed, None, true, later),
+        "[0.000s]"
+    );
+}
+
+#[test]
+pub fn test_timestamp_style_from_str() {
+    assert_eq!(Ok(TimestampStyle::Rfc3339), "rfc3339".parse());
+    assert_eq!(Ok(TimestampStyle::Rfc3339), "RFC-3339".parse());
+    assert_eq!(Ok(TimestampStyle::Iso8601Millis), "iso8601_millis".parse());
+    assert_eq!(Ok(TimestampStyle::UnixMillis), "UnixMillis".parse());
+    assert_eq!(Ok(TimestampStyle::Elapsed), "elapsed".parse());
+    assert!("".parse::<TimestampStyle>().is_err());
+    assert!("iso".parse::<TimestampStyle>().is_err());
+}
@@ -0,0 +1,103 @@
+//! This module contains the [`WriteErrorPolicy`], which decides what happens
+//! to messages when they can not be written to the log file.
+
+#[cfg(test)]
+mod tests;
+
+use crate::parse_duration;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// What to do when writing to the log file fails, such as when the disk is
+/// full or a network file system is briefly gone.
+///
+/// Other than `Disable`, the file is reopened and tried again for each
+/// message until writing works again. The first error can be noticed with
+/// [`Logger::set_write_error_callback`](crate::Logger::set_write_error_callback).
+///
+/// As a string, such as in config files and environment variables, the
+/// policies are written as `disable`, `stderr`, `buffer:65536` and
+/// `retry:3:100ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub enum WriteErrorPolicy {
+    /// Stop logging to the file, and log an error about it to the other
+    /// outputs. This is the default.
+    #[default]
+    Disable,
+    /// Write messages to stderr instead until the file works again.
+    FallbackToStderr,
+    /// Keep messages in memory until the file works again, up to the given
+    /// number of bytes. Messages that do not fit are written to stderr.
+    Buffer {
+        /// The most bytes of messages to keep.
+        max_bytes: usize
+    },
+    /// Reopen the file and try again, waiting before each attempt. The wait
+    /// doubles after every attempt. The logging thread is blocked while
+    /// waiting. If every attempt fails, the file is disabled.
+    Retry {
+        /// How many times to try again.
+        attempts: u32,
+        /// How long to wait before the first attempt.
+        backoff: Duration
+    }
+}
+
+impl Display for WriteErrorPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteErrorPolicy::Disable => write!(f, "disable"),
+            WriteErrorPolicy::FallbackToStderr => write!(f, "stderr"),
+            WriteErrorPolicy::Buffer { max_bytes } => write!(f, "buffer:{max_bytes}"),
+            WriteErrorPolicy::Retry { attempts, backoff } => {
+                write!(f, "retry:{attempts}:{}ms", backoff.as_millis())
+            }
+        }
+    }
+}
+
+impl FromStr for WriteErrorPolicy {
+    type Err = String;
+
+    /// Parse a policy such as `stderr` or `retry:3:100ms`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid write error policy: {}", s);
+        let lowercase = s.trim().to_lowercase();
+        let parts: Vec<&str> = lowercase.split(':').map(str::trim).collect();
+
+        match parts.as_slice() {
+            ["disable"] => Ok(WriteErrorPolicy::Disable),
+            ["stderr"] => Ok(WriteErrorPolicy::FallbackToStderr),
+            ["buffer", max_bytes] => Ok(WriteErrorPolicy::Buffer {
+                max_bytes: max_bytes.parse().map_err(|_| invalid())?
+            }),
+            ["retry", attempts, backoff] => Ok(WriteErrorPolicy::Retry {
+                attempts: attempts.parse().map_err(|_| invalid())?,
+                backoff: parse_duration(backoff).ok_or_else(invalid)?
+            }),
+            _ => Err(invalid())
+        }
+    }
+}
+
+impl TryFrom<String> for WriteErrorPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<WriteErrorPolicy> for String {
+    fn from(policy: WriteErrorPolicy) -> Self {
+        policy.to_string()
+    }
+}
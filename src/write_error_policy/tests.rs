@@ -0,0 +1,50 @@
+use super::*;
+
+#[test]
+fn test_from_str() {
+    assert_eq!(Ok(WriteErrorPolicy::Disable), "disable".parse());
+    assert_eq!(Ok(WriteErrorPolicy::FallbackToStderr), "STDERR".parse());
+    assert_eq!(
+        Ok(WriteErrorPolicy::Buffer { max_bytes: 65536 }),
+        "buffer:65536".parse()
+    );
+    assert_eq!(
+        Ok(WriteErrorPolicy::Retry {
+            attempts: 3,
+            backoff: Duration::from_millis(100)
+        }),
+        "retry: 3: 100ms".parse()
+    );
+
+    for invalid in [
+        "",
+        "ignore",
+        "buffer",
+        "buffer:lots",
+        "retry:3",
+        "retry:x:1s",
+        "retry:3:1",
+        "stderr:1"
+    ] {
+        assert!(
+            invalid.parse::<WriteErrorPolicy>().is_err(),
+            "{:?}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_display_round_trips() {
+    for policy in [
+        WriteErrorPolicy::Disable,
+        WriteErrorPolicy::FallbackToStderr,
+        WriteErrorPolicy::Buffer { max_bytes: 10 },
+        WriteErrorPolicy::Retry {
+            attempts: 5,
+            backoff: Duration::from_secs(1)
+        }
+    ] {
+        assert_eq!(Ok(policy), policy.to_string().parse(), "{}", policy);
+    }
+}